@@ -0,0 +1,206 @@
+use crate::{Config, KeyGroups, Value};
+use core::fmt;
+use num_bigint::BigUint;
+use std::collections::HashMap;
+
+/// Differences between two evaluated [`Config`]s, see [`Config::diff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigDiff<'a> {
+    pub changes: Vec<Change<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<'a> {
+    Added { key: &'a str, new: Vec<Value<'a>> },
+    Removed { key: &'a str, old: Vec<Value<'a>> },
+    Changed { key: &'a str, kind: ChangeKind, old: Vec<Value<'a>>, new: Vec<Value<'a>> },
+}
+
+/// What changed about a key present on both sides, from the most to the least significant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The key is defined a different number of times.
+    Multiplicity,
+    /// The same values are defined in a different order.
+    Order,
+    /// At least one value changed its type, e.g. `"42"` to `42`.
+    Type,
+    /// Values of the same types changed.
+    Value,
+}
+
+impl ConfigDiff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl Change<'_> {
+    pub fn key(&self) -> &str {
+        match self {
+            Change::Added { key, .. }
+            | Change::Removed { key, .. }
+            | Change::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl ChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Multiplicity => "multiplicity",
+            ChangeKind::Order => "order",
+            ChangeKind::Type => "type",
+            ChangeKind::Value => "value",
+        }
+    }
+}
+
+impl Config<'_> {
+    /// Compares the evaluated entries of `self` (old) against `other` (new).
+    ///
+    /// Only effective values matter: formatting, comments and the prefix blocks used to spell a
+    /// key are invisible here, and integers compare by numeric value (`0x2A` equals `42`).
+    /// Removed and changed keys are reported in the first-occurrence order of `self`, followed by
+    /// added keys in the first-occurrence order of `other`.
+    pub fn diff<'a>(&'a self, other: &'a Config<'_>) -> ConfigDiff<'a> {
+        let values = |config: &'a Config<'a>, idxs: &[u32]| -> Vec<Value<'a>> {
+            idxs.iter().map(|&i| config.entries[i as usize].1.to_value(&config.arena)).collect()
+        };
+
+        let new_groups = KeyGroups::new(other, 0, other.sorted_indices.len())
+            .map(|(key, idxs)| (key, values(other, idxs)))
+            .collect::<Vec<_>>();
+        let mut new_by_key =
+            new_groups.iter().enumerate().map(|(i, (key, _))| (*key, i)).collect::<HashMap<_, _>>();
+
+        let mut changes = Vec::new();
+        for (key, idxs) in KeyGroups::new(self, 0, self.sorted_indices.len()) {
+            let old = values(self, idxs);
+            let Some(i) = new_by_key.remove(key) else {
+                changes.push(Change::Removed { key, old });
+                continue;
+            };
+            let new = &new_groups[i].1;
+            if let Some(kind) = classify(&old, new) {
+                changes.push(Change::Changed { key, kind, old, new: new.clone() });
+            }
+        }
+        for (key, new) in &new_groups {
+            if new_by_key.contains_key(key) {
+                changes.push(Change::Added { key, new: new.clone() });
+            }
+        }
+        ConfigDiff { changes }
+    }
+}
+
+fn classify(old: &[Value<'_>], new: &[Value<'_>]) -> Option<ChangeKind> {
+    if old.len() != new.len() {
+        return Some(ChangeKind::Multiplicity);
+    }
+    if old.iter().zip(new).all(|(a, b)| value_eq(a, b)) {
+        return None;
+    }
+    let mut unmatched = new.iter().collect::<Vec<_>>();
+    let is_permutation = old.iter().all(|a| match unmatched.iter().position(|b| value_eq(a, b)) {
+        Some(pos) => {
+            unmatched.swap_remove(pos);
+            true
+        }
+        None => false,
+    });
+    if is_permutation {
+        return Some(ChangeKind::Order);
+    }
+    let same_type = |(a, b): (&Value, &Value)| {
+        matches!(
+            (a, b),
+            (Value::Bool(_), Value::Bool(_))
+                | (Value::Integer(_), Value::Integer(_))
+                | (Value::String(_), Value::String(_))
+        )
+    };
+    if old.iter().zip(new).all(same_type) {
+        Some(ChangeKind::Value)
+    } else {
+        Some(ChangeKind::Type)
+    }
+}
+
+fn value_eq(a: &Value<'_>, b: &Value<'_>) -> bool {
+    match (a, b) {
+        // Texts that do not parse are only equal to themselves.
+        (Value::Integer(a), Value::Integer(b)) => {
+            a == b || integer_value(a).zip(integer_value(b)).is_some_and(|(a, b)| a == b)
+        }
+        _ => a == b,
+    }
+}
+
+/// Parses an integer literal (sign, radix prefix and `_` allowed) into (is_negative, magnitude).
+fn integer_value(s: &str) -> Option<(bool, BigUint)> {
+    let (is_negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (radix, s) = match s.as_bytes() {
+        [b'0', b'x', ..] => (16, &s[2..]),
+        [b'0', b'o', ..] => (8, &s[2..]),
+        [b'0', b'b', ..] => (2, &s[2..]),
+        _ => (10, s),
+    };
+    let clean = s.bytes().filter(|&b| b != b'_').collect::<Vec<_>>();
+    let magnitude = BigUint::parse_bytes(&clean, radix)?;
+    let is_zero = magnitude.bits() == 0;
+    Some((is_negative && !is_zero, magnitude))
+}
+
+/// Renders one change per line: `+` added, `-` removed, `~` changed. Values are written as JSON
+/// so that `"42"` and `42` stay distinguishable.
+impl fmt::Display for ConfigDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            match change {
+                Change::Added { key, new } => writeln!(f, "+ {key}: {}", DisplayValues(new))?,
+                Change::Removed { key, old } => writeln!(f, "- {key}: {}", DisplayValues(old))?,
+                Change::Changed { key, kind, old, new } => writeln!(
+                    f,
+                    "~ {key}: {} -> {} ({} changed)",
+                    DisplayValues(old),
+                    DisplayValues(new),
+                    kind.as_str()
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct DisplayValues<'a>(&'a [Value<'a>]);
+
+impl fmt::Display for DisplayValues<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn value(f: &mut fmt::Formatter<'_>, v: &Value<'_>) -> fmt::Result {
+            match v {
+                Value::Bool(b) => write!(f, "{b}"),
+                Value::Integer(s) => f.write_str(s),
+                Value::String(s) => f.write_str(&serde_json::to_string(s).map_err(|_| fmt::Error)?),
+            }
+        }
+        match self.0 {
+            [v] => value(f, v),
+            values => {
+                f.write_str("[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    value(f, v)?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
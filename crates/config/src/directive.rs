@@ -0,0 +1,77 @@
+use crate::Value;
+use mical_cli_syntax::TextRange;
+
+/// A directive, such as `#version 1.2`, as the evaluator sees it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Directive<'a> {
+    /// The word after the `#`.
+    pub name: &'a str,
+    /// The rest of the line, without the spaces around it.
+    pub args: &'a str,
+    /// Range of the directive, from the `#` to the end of its line.
+    pub range: TextRange,
+    /// Range of `args`, empty at the end of the name if there are none.
+    pub args_range: TextRange,
+    /// The prefix of the prefix blocks the directive is in, `""` at the top level.
+    pub prefix: &'a str,
+}
+
+/// Makes something of the directives of a config, such as `#version` or `#include`.
+///
+/// `#duplicates` is applied by the evaluator whatever the handler does, and is passed on as well.
+pub trait DirectiveHandler {
+    /// Takes each directive in source order, when the entries before it have been evaluated.
+    fn directive(&mut self, directive: &Directive<'_>, output: &mut DirectiveOutput);
+}
+
+/// What a [`DirectiveHandler`] makes of a directive.
+#[derive(Debug, Default)]
+pub struct DirectiveOutput {
+    pub(crate) entries: Vec<(String, OwnedValue)>,
+    pub(crate) errors: Vec<(TextRange, String)>,
+    pub(crate) metadata: Vec<(String, OwnedValue)>,
+}
+
+impl DirectiveOutput {
+    /// Adds an entry after the ones before the directive. `key` is under the prefix of the
+    /// directive, like the key of an entry in its place; the limits on keys and entries apply.
+    /// An integer that does not read as one is reported at the directive instead.
+    pub fn entry(&mut self, key: &str, value: Value<'_>) {
+        self.entries.push((key.to_owned(), OwnedValue::new(value)));
+    }
+
+    /// Reports an [`Error::InvalidDirective`](crate::Error::InvalidDirective) at `range`.
+    pub fn error(&mut self, range: TextRange, message: impl Into<String>) {
+        self.errors.push((range, message.into()));
+    }
+
+    /// Sets the metadata `key` of the config to `value`, see [`Config::metadata`](crate::Config::metadata).
+    pub fn metadata(&mut self, key: &str, value: Value<'_>) {
+        self.metadata.push((key.to_owned(), OwnedValue::new(value)));
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum OwnedValue {
+    Bool(bool),
+    Integer(String),
+    String(String),
+}
+
+impl OwnedValue {
+    fn new(value: Value<'_>) -> Self {
+        match value {
+            Value::Bool(b) => OwnedValue::Bool(b),
+            Value::Integer(text) => OwnedValue::Integer(text.to_owned()),
+            Value::String(text) => OwnedValue::String(text.to_owned()),
+        }
+    }
+
+    pub(crate) fn as_value(&self) -> Value<'_> {
+        match self {
+            OwnedValue::Bool(b) => Value::Bool(*b),
+            OwnedValue::Integer(text) => Value::Integer(text),
+            OwnedValue::String(text) => Value::String(text),
+        }
+    }
+}
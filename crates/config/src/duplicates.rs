@@ -0,0 +1,89 @@
+use crate::{
+    Error, ValueRaw,
+    text_arena::{KeyId, TextArena, TextId},
+};
+use core::{fmt, str::FromStr};
+use mical_cli_syntax::TextRange;
+
+/// What the evaluator does with a key defined more than once.
+///
+/// A file can declare its own policy with a `#duplicates <policy>` directive, which takes
+/// precedence over [`EvalOptions::duplicates`](crate::EvalOptions::duplicates).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keep every entry; [`JsonView`](crate::JsonView) renders the values as an array.
+    #[default]
+    KeepAll,
+    /// Keep only the first entry.
+    FirstWins,
+    /// Keep the value of the last entry, at the position of the first.
+    LastWins,
+    /// Keep every entry, but report each redefinition as [`Error::DuplicateKey`].
+    Error,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep-all" => Ok(DuplicatePolicy::KeepAll),
+            "first-wins" => Ok(DuplicatePolicy::FirstWins),
+            "last-wins" => Ok(DuplicatePolicy::LastWins),
+            "error" => Ok(DuplicatePolicy::Error),
+            _ => Err(format!(
+                "unknown duplicate policy: '{s}' (supported: keep-all, first-wins, last-wins, error)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DuplicatePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DuplicatePolicy::KeepAll => "keep-all",
+            DuplicatePolicy::FirstWins => "first-wins",
+            DuplicatePolicy::LastWins => "last-wins",
+            DuplicatePolicy::Error => "error",
+        })
+    }
+}
+
+/// Applies `policy` to `entries`, whose keys are at `key_ranges` and spelled out in `keys`.
+pub(crate) fn resolve(
+    policy: DuplicatePolicy,
+    arena: &TextArena<'_>,
+    keys: &[TextId],
+    entries: &mut Vec<(KeyId, ValueRaw)>,
+    key_ranges: &[TextRange],
+    errors: &mut Vec<Error>,
+) {
+    if policy == DuplicatePolicy::KeepAll {
+        return;
+    }
+    // key -> (index into `kept`, index into `entries`) of its first entry
+    let mut first = vec![None::<(usize, usize)>; keys.len()];
+    let mut kept = Vec::with_capacity(entries.len());
+    for (i, &(key_id, value)) in entries.iter().enumerate() {
+        let (kept_idx, first_idx) = match first[key_id as usize] {
+            Some(first) => first,
+            None => {
+                first[key_id as usize] = Some((kept.len(), i));
+                kept.push((key_id, value));
+                continue;
+            }
+        };
+        match policy {
+            DuplicatePolicy::KeepAll | DuplicatePolicy::FirstWins => {}
+            DuplicatePolicy::LastWins => kept[kept_idx].1 = value,
+            DuplicatePolicy::Error => {
+                errors.push(Error::DuplicateKey {
+                    key: arena[keys[key_id as usize]].to_owned(),
+                    range: key_ranges[i],
+                    first: key_ranges[first_idx],
+                });
+                kept.push((key_id, value));
+            }
+        }
+    }
+    *entries = kept;
+}
@@ -0,0 +1,153 @@
+use crate::{Config, Value, ValueRaw, text_arena::KeyId};
+use std::{collections::HashMap, mem};
+
+/// Twelve-factor style mapping from environment variables to config keys.
+///
+/// With the prefix `MYAPP`, the variable `MYAPP__SERVER__PORT` overrides the key `server.port`:
+/// the `MYAPP__` head is stripped and every remaining `__` stands for the key separator.
+/// Existing keys are matched through their [environment name](EnvOverrides::env_name), so
+/// `MYAPP__MAX_CONN` also reaches a key spelled `max-conn`. Keys that share an environment name,
+/// like `a.b` and `a__b` or `max-conn` and `max_conn`, are all overridden by their variable. A
+/// variable that matches no existing key adds a new key made of its lowercased segments.
+#[derive(Clone, Copy, Debug)]
+pub struct EnvOverrides<'a> {
+    prefix: &'a str,
+    separator: &'a str,
+}
+
+impl<'a> EnvOverrides<'a> {
+    /// Uses `.` as the key separator.
+    pub fn new(prefix: &'a str) -> Self {
+        EnvOverrides { prefix, separator: "." }
+    }
+
+    pub fn with_separator(prefix: &'a str, separator: &'a str) -> Self {
+        EnvOverrides { prefix, separator }
+    }
+
+    /// Returns the name of the environment variable that overrides `key`.
+    ///
+    /// Segments are uppercased, and any character other than an ASCII letter or digit becomes `_`,
+    /// so several keys may have the same name.
+    pub fn env_name(&self, key: &str) -> String {
+        let mut name = format!("{}__", self.prefix);
+        for (i, segment) in self.segments(key).enumerate() {
+            if i > 0 {
+                name.push_str("__");
+            }
+            name.extend(segment.chars().map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+                _ => '_',
+            }));
+        }
+        name
+    }
+
+    fn segments<'k>(&self, key: &'k str) -> impl Iterator<Item = &'k str> {
+        let separator = self.separator;
+        let mut rest = Some(key);
+        std::iter::from_fn(move || {
+            let text = rest?;
+            match text.find(separator).filter(|_| !separator.is_empty()) {
+                Some(i) => {
+                    rest = Some(&text[i + separator.len()..]);
+                    Some(&text[..i])
+                }
+                None => rest.take(),
+            }
+        })
+    }
+
+    fn default_key(&self, suffix: &str) -> String {
+        suffix.split("__").map(str::to_ascii_lowercase).collect::<Vec<_>>().join(self.separator)
+    }
+}
+
+impl Config<'_> {
+    /// Overrides entries with the matching variables in `vars`, typically [`std::env::vars`].
+    ///
+    /// All values of an overridden key are replaced by the single variable value, placed where the
+    /// key first occurred. A variable overrides every key with its name. New keys are appended in variable name order. Values are typed by
+    /// [`Value::from_text`]. The resulting entries report [`Origin::Env`](crate::Origin::Env).
+    pub fn apply_env_overrides<K, V>(
+        &mut self,
+        overrides: &EnvOverrides<'_>,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let head_len = overrides.prefix.len() + 2;
+        let mut vars = vars
+            .into_iter()
+            .filter(|(name, _)| {
+                let name = name.as_ref();
+                name.len() > head_len
+                    && name.starts_with(overrides.prefix)
+                    && name[overrides.prefix.len()..].starts_with("__")
+            })
+            .map(|(name, value)| (name.as_ref().to_owned(), value.as_ref().to_owned()))
+            .collect::<Vec<_>>();
+        if vars.is_empty() {
+            return;
+        }
+        vars.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        // The entries of a key come together, so a key is pushed once.
+        let mut known = HashMap::<String, Vec<String>>::new();
+        for (key, _) in self.entries() {
+            let keys = known.entry(overrides.env_name(key)).or_default();
+            if keys.last().is_none_or(|last| last != key) {
+                keys.push(key.to_owned());
+            }
+        }
+
+        // key -> (variable name, value); taken once the override has been placed.
+        let mut replacements = HashMap::new();
+        let mut order = Vec::new();
+        for (name, value) in &vars {
+            let keys = match known.get(name) {
+                Some(keys) => keys.clone(),
+                None => vec![overrides.default_key(&name[head_len..])],
+            };
+            let var_id = self.arena.alloc(name);
+            let raw = ValueRaw::alloc(Value::from_text(value), &mut self.arena);
+            for key in keys {
+                if replacements.insert(key.clone(), Some((var_id, raw))).is_none() {
+                    order.push(key);
+                }
+            }
+        }
+
+        let old_entries = mem::take(&mut self.entries);
+        let mut old_origins = mem::take(&mut self.env_origins).into_iter().peekable();
+        for (i, (key_id, raw)) in old_entries.into_iter().enumerate() {
+            let origin = old_origins.next_if(|&(j, _)| j as usize == i).map(|(_, var_id)| var_id);
+            match replacements.get_mut(self.key(key_id)) {
+                None => {
+                    if let Some(var_id) = origin {
+                        self.env_origins.push((self.entries.len() as u32, var_id));
+                    }
+                    self.entries.push((key_id, raw));
+                }
+                Some(slot) => {
+                    if let Some((var_id, raw)) = slot.take() {
+                        self.env_origins.push((self.entries.len() as u32, var_id));
+                        self.entries.push((key_id, raw));
+                    }
+                }
+            }
+        }
+        for key in order {
+            if let Some(Some((var_id, raw))) = replacements.remove(&key) {
+                let key_id = self.keys.len() as KeyId;
+                self.keys.push(self.arena.alloc(&key));
+                self.env_origins.push((self.entries.len() as u32, var_id));
+                self.entries.push((key_id, raw));
+            }
+        }
+
+        (self.sorted_indices, self.group_starts, self.group_order, self.prefix_runs) =
+            Self::build_indices(&self.arena, &self.keys, &self.entries);
+    }
+}
@@ -1,25 +1,121 @@
 use core::fmt;
-use mical_cli_syntax::TextRange;
+use mical_cli_syntax::{Fix, Indel, TextEdit, TextRange};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Error {
-    InvalidEscape { range: TextRange, sequence: String },
-    EmptyEscape { range: TextRange },
-    InvalidRadixDigits { range: TextRange, text: String },
+    InvalidEscape {
+        range: TextRange,
+        sequence: String,
+    },
+    EmptyEscape {
+        range: TextRange,
+    },
+    /// A `\x` escape above `\x7F` or a `\u{...}` escape that is not a Unicode scalar value, at
+    /// its digits.
+    InvalidCodePoint {
+        range: TextRange,
+        sequence: String,
+    },
+    InvalidRadixDigits {
+        range: TextRange,
+        text: String,
+    },
+    /// A key defined again under [`DuplicatePolicy::Error`](crate::DuplicatePolicy::Error).
+    DuplicateKey {
+        key: String,
+        range: TextRange,
+        first: TextRange,
+    },
+    InvalidDirective {
+        range: TextRange,
+        message: String,
+    },
+    /// A full key longer than [`Limits::max_key_len`](mical_cli_syntax::Limits::max_key_len).
+    KeyTooLong {
+        range: TextRange,
+        limit: usize,
+    },
+    /// An integer longer than [`Limits::max_integer_len`](mical_cli_syntax::Limits::max_integer_len).
+    IntegerTooLong {
+        range: TextRange,
+        limit: usize,
+    },
+    /// The first entry over [`Limits::max_entries`](mical_cli_syntax::Limits::max_entries), where
+    /// evaluation stopped.
+    TooManyEntries {
+        range: TextRange,
+        limit: usize,
+    },
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Error {
+    pub fn range(&self) -> TextRange {
+        match self {
+            Error::InvalidEscape { range, .. }
+            | Error::EmptyEscape { range }
+            | Error::InvalidCodePoint { range, .. }
+            | Error::InvalidRadixDigits { range, .. }
+            | Error::DuplicateKey { range, .. }
+            | Error::InvalidDirective { range, .. }
+            | Error::KeyTooLong { range, .. }
+            | Error::IntegerTooLong { range, .. }
+            | Error::TooManyEntries { range, .. } => *range,
+        }
+    }
+
+    /// What went wrong, without the ranges [`Display`](fmt::Display) adds for a reader without
+    /// the source at hand.
+    pub fn message(&self) -> String {
         match self {
-            Error::InvalidEscape { range, sequence } => {
-                write!(f, "invalid escape sequence '{}' at {:?}", sequence, range)
+            Error::InvalidEscape { sequence, .. } => {
+                format!("invalid escape sequence '{}'", sequence)
+            }
+            Error::EmptyEscape { .. } => "empty escape".to_owned(),
+            Error::InvalidCodePoint { sequence, .. } => {
+                format!("invalid code point in escape sequence '{}'", sequence)
+            }
+            Error::InvalidRadixDigits { text, .. } => {
+                format!("invalid digits for radix in '{}'", text)
             }
-            Error::EmptyEscape { range } => {
-                write!(f, "empty escape at {:?}", range)
+            Error::DuplicateKey { key, .. } => format!("duplicate key '{}'", key),
+            Error::InvalidDirective { message, .. } => message.clone(),
+            Error::KeyTooLong { limit, .. } => {
+                format!("key longer than the limit of {} bytes", limit)
             }
-            Error::InvalidRadixDigits { range, text } => {
-                write!(f, "invalid digits for radix in '{}' at {:?}", text, range)
+            Error::IntegerTooLong { limit, .. } => {
+                format!("integer longer than the limit of {} bytes", limit)
             }
+            Error::TooManyEntries { limit, .. } => {
+                format!("more entries than the limit of {}", limit)
+            }
+        }
+    }
+
+    /// A machine-applicable edit that resolves the error, if there is an obvious one.
+    pub fn fix(&self) -> Option<Fix> {
+        match self {
+            // `\q` keeps the backslash the author most likely meant literally.
+            Error::InvalidEscape { range, .. } | Error::EmptyEscape { range } => Some(Fix::new(
+                "escape the backslash",
+                TextEdit::new(vec![Indel::insert(range.start(), "\\")]),
+            )),
+            Error::InvalidCodePoint { .. }
+            | Error::InvalidRadixDigits { .. }
+            | Error::DuplicateKey { .. }
+            | Error::InvalidDirective { .. }
+            | Error::KeyTooLong { .. }
+            | Error::IntegerTooLong { .. }
+            | Error::TooManyEntries { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {:?}", self.message(), self.range())?;
+        if let Error::DuplicateKey { first, .. } = self {
+            write!(f, " (first defined at {:?})", first)?;
         }
+        Ok(())
     }
 }
@@ -1,67 +1,545 @@
 use crate::{
-    Error, ValueRaw,
-    text_arena::{TextArena, TextId},
+    Directive, DirectiveHandler, DirectiveOutput, DirectiveRaw, DuplicatePolicy, Error, Value,
+    ValueRaw,
+    text_arena::{Interner, KeyId, TextArena, TextId},
 };
 use mical_cli_syntax::{
-    SyntaxKind,
-    ast::{self, BooleanKind},
+    Limits, SyntaxError, SyntaxKind, TextRange, TextSize,
+    ast::{self, AstNode, BooleanKind},
+    token::TokenKind,
 };
+use std::{mem, ops::ControlFlow};
 
 mod joined_str;
 use joined_str::*;
 
+mod stream;
+pub(crate) use stream::eval_text;
+
 mod temporary_string;
 use temporary_string::*;
 
 mod unescape;
 use unescape::*;
+pub(crate) use unescape::{escape_sequences, quoted};
+
+/// Receives what the evaluator produces, whether it walks a syntax tree or parser events.
+pub(crate) trait Sink {
+    /// Takes an entry: its full `key`, the range of its key node, and its value.
+    fn entry(&mut self, key: Text<'_>, range: TextRange, value: Evaluated<'_>) -> ControlFlow<()>;
+    fn duplicates(&mut self, policy: DuplicatePolicy);
+    /// Takes every directive, after the evaluator has applied it.
+    fn directive(&mut self, directive: &Directive<'_>);
+    /// Takes metadata a [`DirectiveHandler`] set.
+    fn metadata(&mut self, key: &str, value: Value<'_>);
+    fn error(&mut self, error: Error) -> ControlFlow<()>;
+    fn syntax_error(&mut self, error: SyntaxError) -> ControlFlow<()>;
+}
+
+/// An evaluated text, and the range of the source it is a verbatim slice of if it is one.
+#[derive(Clone, Copy)]
+pub(crate) struct Text<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) range: TextRange,
+}
+
+pub(crate) enum Evaluated<'a> {
+    Bool(bool),
+    Integer(Text<'a>),
+    String(Text<'a>),
+}
+
+impl<'a> Evaluated<'a> {
+    pub(crate) fn to_value(&self) -> Value<'a> {
+        match *self {
+            Evaluated::Bool(b) => Value::Bool(b),
+            Evaluated::Integer(text) => Value::Integer(text.text),
+            Evaluated::String(text) => Value::String(text.text),
+        }
+    }
+}
+
+/// A key as spelled in the source: the word, or the text between the quotes, and its range.
+#[derive(Clone, Copy)]
+pub(crate) enum KeyText<'a> {
+    Word(Text<'a>),
+    Quoted(Text<'a>),
+}
+
+/// The parts of a value the evaluator reads.
+pub(crate) enum ValueText<'a> {
+    Boolean(bool),
+    Integer {
+        sign: Option<&'a str>,
+        numeral: Text<'a>,
+        range: TextRange,
+    },
+    Line(Text<'a>),
+    /// The line string whose lines were joined by [`Context::start_continued_line`].
+    ContinuedLine,
+    Quoted(Text<'a>),
+    /// A value a [`DirectiveHandler`] made, taken as it is.
+    Emitted(Value<'a>),
+    /// The block string whose lines were passed to [`BlockString::line`].
+    Block {
+        chomp: Option<SyntaxKind>,
+    },
+}
 
-pub(crate) struct Output {
-    pub(crate) arena: TextArena,
-    pub(crate) entries: Vec<(TextId, ValueRaw)>,
+pub(crate) struct Output<'s> {
+    pub(crate) arena: TextArena<'s>,
+    /// Each distinct full key, indexed by [`KeyId`].
+    pub(crate) keys: Vec<TextId>,
+    pub(crate) entries: Vec<(KeyId, ValueRaw)>,
+    /// Range of the key of each entry in `entries`.
+    pub(crate) key_ranges: Vec<TextRange>,
+    /// The policy declared by the last `#duplicates` directive, if any.
+    pub(crate) duplicates: Option<DuplicatePolicy>,
+    pub(crate) directives: Vec<DirectiveRaw>,
+    pub(crate) metadata: Vec<(TextId, ValueRaw)>,
     pub(crate) errors: Vec<Error>,
+    pub(crate) syntax_errors: Vec<SyntaxError>,
 }
 
-pub(crate) fn eval_source_file(source_file: &ast::SourceFile) -> Output {
-    let mut ctx = Context::new();
+/// Evaluates `source_file`. Keys and values that are spelled verbatim in `source`, the text
+/// `source_file` was parsed from, borrow it; pass `""` to copy everything.
+pub(crate) fn eval_source_file<'s>(
+    source_file: &ast::SourceFile,
+    source: &'s str,
+    limits: &Limits,
+    handler: Option<&mut dyn DirectiveHandler>,
+) -> Output<'s> {
+    let mut ctx = Context::new(Collect::new(source), limits, handler);
     source_file.eval(&mut ctx);
-    ctx.finish()
+    ctx.finish().finish()
+}
+
+/// Collects the entries of a config.
+pub(crate) struct Collect<'s> {
+    output: Output<'s>,
+    interner: Interner,
+}
+
+impl<'s> Collect<'s> {
+    pub(crate) fn new(source: &'s str) -> Self {
+        let output = Output {
+            arena: TextArena::with_source(source),
+            keys: Vec::new(),
+            entries: Vec::new(),
+            key_ranges: Vec::new(),
+            duplicates: None,
+            directives: Vec::new(),
+            metadata: Vec::new(),
+            errors: Vec::new(),
+            syntax_errors: Vec::new(),
+        };
+        Collect { output, interner: Interner::new() }
+    }
+
+    pub(crate) fn finish(self) -> Output<'s> {
+        self.output
+    }
+}
+
+impl Sink for Collect<'_> {
+    fn entry(&mut self, key: Text<'_>, range: TextRange, value: Evaluated<'_>) -> ControlFlow<()> {
+        let Output { arena, keys, entries, key_ranges, .. } = &mut self.output;
+        let key_id = self
+            .interner
+            .intern(arena, keys, key.text, |arena, text| arena.alloc_at(text, key.range));
+        let value_raw = match value {
+            Evaluated::Bool(b) => ValueRaw::Bool(b),
+            Evaluated::Integer(text) => ValueRaw::Integer(arena.alloc_at(text.text, text.range)),
+            Evaluated::String(text) => ValueRaw::String(arena.alloc_at(text.text, text.range)),
+        };
+        entries.push((key_id, value_raw));
+        key_ranges.push(range);
+        ControlFlow::Continue(())
+    }
+
+    fn duplicates(&mut self, policy: DuplicatePolicy) {
+        self.output.duplicates = Some(policy);
+    }
+
+    fn directive(&mut self, directive: &Directive<'_>) {
+        let name_start = directive.range.start() + TextSize::of("#");
+        let name_range = TextRange::at(name_start, TextSize::of(directive.name));
+        let arena = &mut self.output.arena;
+        self.output.directives.push(DirectiveRaw {
+            name: arena.alloc_at(directive.name, name_range),
+            args: arena.alloc_at(directive.args, directive.args_range),
+            range: directive.range,
+            args_range: directive.args_range,
+            prefix: arena.alloc(directive.prefix),
+        });
+    }
+
+    fn metadata(&mut self, key: &str, value: Value<'_>) {
+        let arena = &mut self.output.arena;
+        let key = arena.alloc(key);
+        self.output.metadata.push((key, ValueRaw::alloc(value, arena)));
+    }
+
+    fn error(&mut self, error: Error) -> ControlFlow<()> {
+        self.output.errors.push(error);
+        ControlFlow::Continue(())
+    }
+
+    fn syntax_error(&mut self, error: SyntaxError) -> ControlFlow<()> {
+        self.output.syntax_errors.push(error);
+        ControlFlow::Continue(())
+    }
 }
 
-struct Context {
-    arena: TextArena,
-    entries: Vec<(TextId, ValueRaw)>,
+pub(crate) struct Context<'h, S> {
+    sink: S,
+    handler: Option<&'h mut dyn DirectiveHandler>,
+    directive_output: DirectiveOutput,
+    limits: Limits,
+    /// Number of entries passed on.
+    entries: usize,
     prefix: String,
     temporary_string: TemporaryString,
+    block_string: BlockString,
+    continued_line: String,
     errors: Vec<Error>,
+    /// Whether the sink has asked to stop.
+    stopped: bool,
 }
 
-impl Context {
-    fn new() -> Self {
+impl<'h, S: Sink> Context<'h, S> {
+    pub(crate) fn new(
+        sink: S,
+        limits: &Limits,
+        handler: Option<&'h mut dyn DirectiveHandler>,
+    ) -> Self {
         Context {
-            arena: TextArena::new(),
-            entries: Vec::new(),
+            sink,
+            handler,
+            directive_output: DirectiveOutput::default(),
+            limits: *limits,
+            entries: 0,
             prefix: String::new(),
             temporary_string: TemporaryString::new(),
+            block_string: BlockString::new(),
+            continued_line: String::new(),
             errors: Vec::new(),
+            stopped: false,
+        }
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub(crate) fn finish(self) -> S {
+        self.sink
+    }
+
+    /// Passes an entry on, unless its value does not evaluate. `key_range` is the range of the key
+    /// node and `value` is `None` if the value node lacks the parts it needs.
+    pub(crate) fn entry(
+        &mut self,
+        key: KeyText<'_>,
+        key_range: TextRange,
+        value: Option<ValueText<'_>>,
+    ) {
+        // Outside prefix blocks, a full key without escapes is spelled at `range`.
+        let (full_key, range) = match key {
+            KeyText::Word(word) => (self.prefix.joined(word.text), word.range),
+            KeyText::Quoted(string) => {
+                let espaced: &mut String = self.temporary_string.get();
+                unescape(string.text, espaced, string.range.start(), &mut self.errors);
+                (self.prefix.joined(espaced), string.range)
+            }
+        };
+        if full_key.len() > self.limits.max_key_len {
+            let limit = self.limits.max_key_len;
+            self.errors.push(Error::KeyTooLong { range: key_range, limit });
+            drop(full_key);
+            self.flush_errors();
+            return;
+        }
+        let value = value.and_then(|value| {
+            let Context { temporary_string, block_string, continued_line, errors, limits, .. } =
+                self;
+            eval_value(value, temporary_string, block_string, continued_line, errors, limits)
+        });
+        for error in self.errors.drain(..) {
+            self.stopped |= self.sink.error(error).is_break();
+        }
+        let Some(value) = value else { return };
+        if self.entries == self.limits.max_entries {
+            let limit = self.limits.max_entries;
+            self.stopped = true;
+            let _ = self.sink.error(Error::TooManyEntries { range: key_range, limit });
+            return;
+        }
+        self.entries += 1;
+        let key = Text { text: &full_key, range };
+        self.stopped |= self.sink.entry(key, key_range, value).is_break();
+    }
+
+    /// Appends the text of the key of a prefix block to the prefix, returning the length to
+    /// truncate it back to.
+    pub(crate) fn start_block(&mut self, key: KeyText<'_>) -> usize {
+        let prev_prefix_len = self.prefix.len();
+        match key {
+            KeyText::Word(word) => self.prefix.push_str(word.text),
+            KeyText::Quoted(string) => {
+                let espaced: &mut String = self.temporary_string.get();
+                unescape(string.text, espaced, string.range.start(), &mut self.errors);
+                self.prefix.push_str(espaced);
+            }
+        }
+        self.flush_errors();
+        prev_prefix_len
+    }
+
+    pub(crate) fn finish_block(&mut self, prev_prefix_len: usize) {
+        self.prefix.truncate(prev_prefix_len);
+    }
+
+    /// Evaluates a directive named `name`, whose arguments are the line string `args`, spanning
+    /// `range`.
+    pub(crate) fn directive(&mut self, name: Text<'_>, args: Option<Text<'_>>, range: TextRange) {
+        let has_args = args.is_some();
+        let args = match args {
+            Some(args) => {
+                let text = args.text.trim_start();
+                let start = args.range.end() - TextSize::of(text);
+                let text = text.trim_end();
+                Text { text, range: TextRange::at(start, TextSize::of(text)) }
+            }
+            None => Text { text: "", range: TextRange::empty(name.range.end()) },
+        };
+        if name.text == "duplicates" {
+            match args.text.parse() {
+                Ok(policy) => self.sink.duplicates(policy),
+                Err(message) => {
+                    let range = if has_args { args.range } else { name.range };
+                    self.errors.push(Error::InvalidDirective { range, message });
+                }
+            }
+            self.flush_errors();
+        }
+
+        let directive = Directive {
+            name: name.text,
+            args: args.text,
+            range,
+            args_range: args.range,
+            prefix: &self.prefix,
+        };
+        self.sink.directive(&directive);
+        let Some(handler) = &mut self.handler else { return };
+        handler.directive(&directive, &mut self.directive_output);
+
+        // The buffers are put back for the next directive.
+        let mut output = mem::take(&mut self.directive_output);
+        for (range, message) in output.errors.drain(..) {
+            self.errors.push(Error::InvalidDirective { range, message });
+        }
+        // An integer must read as one, or it could not be written out as a number.
+        let mut is_valid = |value: &Value<'_>| match value {
+            Value::Integer(text) if type_value(text) != Value::Integer(text) => {
+                let message = format!("'{text}' is not an integer");
+                self.errors.push(Error::InvalidDirective { range, message });
+                false
+            }
+            _ => true,
+        };
+        output.entries.retain(|(_, value)| is_valid(&value.as_value()));
+        output.metadata.retain(|(_, value)| is_valid(&value.as_value()));
+        self.flush_errors();
+        for (key, value) in output.entries.drain(..) {
+            if self.stopped {
+                break;
+            }
+            let key = KeyText::Word(Text { text: &key, range });
+            self.entry(key, range, Some(ValueText::Emitted(value.as_value())));
+        }
+        for (key, value) in output.metadata.drain(..) {
+            self.sink.metadata(&key, value.as_value());
+        }
+        self.directive_output = output;
+    }
+
+    pub(crate) fn syntax_error(&mut self, error: SyntaxError) {
+        self.stopped |= self.sink.syntax_error(error).is_break();
+    }
+
+    /// Collects the lines of the next block string value.
+    pub(crate) fn block_string(&mut self) -> &mut BlockString {
+        &mut self.block_string
+    }
+
+    /// Starts joining the lines of the next line string that goes on over several lines.
+    pub(crate) fn start_continued_line(&mut self) -> &mut String {
+        self.continued_line.clear();
+        &mut self.continued_line
+    }
+
+    pub(crate) fn continued_line(&mut self) -> &mut String {
+        &mut self.continued_line
+    }
+
+    fn flush_errors(&mut self) {
+        for error in self.errors.drain(..) {
+            self.stopped |= self.sink.error(error).is_break();
+        }
+    }
+}
+
+fn eval_value<'a>(
+    value: ValueText<'a>,
+    temporary_string: &'a mut TemporaryString,
+    block_string: &'a mut BlockString,
+    continued_line: &'a str,
+    errors: &mut Vec<Error>,
+    limits: &Limits,
+) -> Option<Evaluated<'a>> {
+    let value = match value {
+        ValueText::Boolean(b) => Evaluated::Bool(b),
+        ValueText::Integer { sign, numeral, range } => {
+            if usize::from(range.len()) > limits.max_integer_len {
+                errors.push(Error::IntegerTooLong { range, limit: limits.max_integer_len });
+                return None;
+            }
+            if !is_valid_numeral(numeral.text) {
+                errors.push(Error::InvalidRadixDigits {
+                    range: numeral.range,
+                    text: numeral.text.to_string(),
+                });
+                return None;
+            }
+            let buf = temporary_string.get();
+            if let Some(sign) = sign {
+                buf.push_str(sign);
+            }
+            buf.push_str(numeral.text);
+            Evaluated::Integer(Text { text: buf, range })
+        }
+        ValueText::Line(string) => Evaluated::String(string),
+        // Checked by `Context::directive`, but never a slice of the source.
+        ValueText::Emitted(value) => match value {
+            Value::Bool(b) => Evaluated::Bool(b),
+            Value::Integer(text) => Evaluated::Integer(Text { text, range: TextRange::default() }),
+            Value::String(text) => Evaluated::String(Text { text, range: TextRange::default() }),
+        },
+        ValueText::ContinuedLine => {
+            // Joined lines are never a slice of the source either.
+            Evaluated::String(Text { text: continued_line, range: TextRange::default() })
+        }
+        ValueText::Quoted(string) => {
+            let buf = temporary_string.get();
+            unescape(string.text, buf, string.range.start(), errors);
+            Evaluated::String(Text { text: buf, range: string.range })
+        }
+        ValueText::Block { chomp } => {
+            // Folded text is never a slice of the source, and the empty range only matches "".
+            let text = block_string.finish(chomp);
+            Evaluated::String(Text { text, range: TextRange::default() })
+        }
+    };
+    Some(value)
+}
+
+/// Joins the lines of a block string as they come.
+pub(crate) struct BlockString {
+    buffer: String,
+    is_folded: bool,
+    has_lines: bool,
+    /// For a folded string, whether the previous line was more indented, if it had content.
+    prev_content: Option<bool>,
+}
+
+impl BlockString {
+    fn new() -> Self {
+        BlockString {
+            buffer: String::new(),
+            is_folded: false,
+            has_lines: false,
+            prev_content: None,
+        }
+    }
+
+    pub(crate) fn start(&mut self, is_folded: bool) {
+        self.buffer.clear();
+        (self.is_folded, self.has_lines, self.prev_content) = (is_folded, false, None);
+    }
+
+    /// Adds a line, given by the text of its string token if it has one.
+    pub(crate) fn line(&mut self, text: Option<&str>) {
+        let buf = &mut self.buffer;
+        if self.is_folded {
+            match text {
+                Some(text) => {
+                    let more_indented = text.starts_with(' ');
+                    if let Some(prev_was_more) = self.prev_content {
+                        if prev_was_more || more_indented {
+                            buf.push('\n');
+                        } else {
+                            buf.push(' ');
+                        }
+                    }
+                    buf.push_str(text);
+                    self.prev_content = Some(more_indented);
+                }
+                None => {
+                    buf.push('\n');
+                    self.prev_content = None;
+                }
+            }
+        } else {
+            if self.has_lines {
+                buf.push('\n');
+            }
+            if let Some(text) = text {
+                buf.push_str(text);
+            }
         }
+        self.has_lines = true;
     }
 
-    fn finish(self) -> Output {
-        Output { arena: self.arena, entries: self.entries, errors: self.errors }
+    fn finish(&mut self, chomp: Option<SyntaxKind>) -> &str {
+        let buf = &mut self.buffer;
+        if !self.has_lines {
+            return "";
+        }
+
+        buf.push('\n');
+
+        match chomp {
+            Some(SyntaxKind::MINUS) => {
+                let end = buf.trim_end_matches('\n').len();
+                buf.truncate(end);
+            }
+            Some(SyntaxKind::PLUS) => {}
+            _ => {
+                let end = buf.trim_end_matches('\n').len();
+                buf.truncate(end);
+                buf.push('\n');
+            }
+        }
+
+        buf
     }
 }
 
 trait Eval {
     type Output;
-    fn eval(&self, ctx: &mut Context) -> Self::Output;
+    fn eval<S: Sink>(&self, ctx: &mut Context<'_, S>) -> Self::Output;
 }
 
 impl Eval for ast::SourceFile {
     type Output = ();
 
-    fn eval(&self, ctx: &mut Context) {
+    fn eval<S: Sink>(&self, ctx: &mut Context<'_, S>) {
         for item in self.items() {
+            if ctx.is_stopped() {
+                break;
+            }
             item.eval(ctx);
         }
     }
@@ -70,161 +548,113 @@ impl Eval for ast::SourceFile {
 impl Eval for ast::Item {
     type Output = ();
 
-    fn eval(&self, ctx: &mut Context) {
+    fn eval<S: Sink>(&self, ctx: &mut Context<'_, S>) {
         match self {
             ast::Item::Entry(entry) => entry.eval(ctx),
             ast::Item::PrefixBlock(block) => block.eval(ctx),
-            ast::Item::Directive(_) => {}
+            ast::Item::Directive(directive) => directive.eval(ctx),
         }
     }
 }
 
-impl Eval for ast::Entry {
+impl Eval for ast::Directive {
     type Output = ();
 
-    fn eval(&self, ctx: &mut Context) {
-        let Some(key) = self.key() else { return };
-        let Some(value) = self.value() else { return };
-
-        let key_id = {
-            let full_key = match key {
-                ast::Key::Word(word_key) => {
-                    let Some(token) = word_key.word() else { return };
-                    ctx.prefix.joined(token.text())
-                }
-                ast::Key::Quoted(quoted_key) => {
-                    let Some(string) = quoted_key.string() else { return };
-                    let espaced: &mut String = ctx.temporary_string.get();
-                    unescape(string.text(), espaced, string.text_range().start(), &mut ctx.errors);
-                    ctx.prefix.joined(espaced)
-                }
-            };
-            ctx.arena.alloc(&full_key)
-        };
-
-        let Some(value_raw) = value.eval(ctx) else { return };
-        ctx.entries.push((key_id, value_raw));
+    fn eval<S: Sink>(&self, ctx: &mut Context<'_, S>) {
+        let Some(name) = self.name() else { return };
+        let args = self.args().and_then(|args| args.string());
+        let args = args.as_ref().map(|args| Text { text: args.text(), range: args.text_range() });
+        let name = Text { text: name.text(), range: name.text_range() };
+        ctx.directive(name, args, self.syntax().text_range());
     }
 }
 
-impl Eval for ast::PrefixBlock {
-    type Output = ();
-
-    fn eval(&self, ctx: &mut Context) {
-        let Some(key) = self.key() else { return };
-
-        let prev_prefix_len = ctx.prefix.len();
-
-        match key {
-            ast::Key::Word(word_key) => {
-                let Some(token) = word_key.word() else { return };
-                ctx.prefix.push_str(token.text());
-            }
-            ast::Key::Quoted(quoted_key) => {
-                let Some(string) = quoted_key.string() else { return };
-                let espaced: &mut String = ctx.temporary_string.get();
-                unescape(string.text(), espaced, string.text_range().start(), &mut ctx.errors);
-                ctx.prefix.push_str(espaced);
-            }
-        };
-
-        for item in self.items() {
-            item.eval(ctx);
+/// Runs `f` with the spelling of `key`, if it has one.
+fn with_key_text<R>(key: &ast::Key, f: impl FnOnce(KeyText<'_>) -> R) -> Option<R> {
+    Some(match key {
+        ast::Key::Word(word_key) => {
+            let token = word_key.word()?;
+            f(KeyText::Word(Text { text: token.text(), range: token.text_range() }))
         }
-
-        ctx.prefix.truncate(prev_prefix_len);
-    }
+        ast::Key::Quoted(quoted_key) => {
+            let string = quoted_key.string()?;
+            f(KeyText::Quoted(Text { text: string.text(), range: string.text_range() }))
+        }
+    })
 }
 
-impl Eval for ast::Value {
-    type Output = Option<ValueRaw>;
+impl Eval for ast::Entry {
+    type Output = ();
 
-    fn eval(&self, ctx: &mut Context) -> Option<ValueRaw> {
-        let value = match self {
-            ast::Value::Boolean(b) => {
-                let val = b.eval(ctx)?;
-                ValueRaw::Bool(val)
-            }
+    fn eval<S: Sink>(&self, ctx: &mut Context<'_, S>) {
+        let Some(key) = self.key() else { return };
+        let Some(value) = self.value() else { return };
+        let key_range = key.syntax().text_range();
+
+        let (sign, numeral, string);
+        let value = match &value {
+            ast::Value::Boolean(b) => b.kind().map(|kind| match kind {
+                BooleanKind::True => ValueText::Boolean(true),
+                BooleanKind::False => ValueText::Boolean(false),
+            }),
             ast::Value::Integer(i) => {
-                let text_id = i.eval(ctx)?;
-                ValueRaw::Integer(text_id)
+                (sign, numeral) = (i.sign(), i.numeral());
+                numeral.as_ref().map(|numeral| ValueText::Integer {
+                    sign: sign.as_ref().map(|sign| sign.text()),
+                    numeral: Text { text: numeral.text(), range: numeral.text_range() },
+                    range: i.syntax().text_range(),
+                })
+            }
+            ast::Value::LineString(ls) if is_continued(ls) => {
+                let joined = ctx.start_continued_line();
+                for token in ls.syntax().children_with_tokens().filter_map(|e| e.into_token()) {
+                    if token.kind() == SyntaxKind::STRING {
+                        joined.push_str(token.text());
+                    }
+                }
+                Some(ValueText::ContinuedLine)
             }
             ast::Value::LineString(ls) => {
-                let string = ls.string()?;
-                let text = string.text();
-                let text_id = ctx.arena.alloc(text);
-                ValueRaw::String(text_id)
+                string = ls.string();
+                string
+                    .as_ref()
+                    .map(|s| ValueText::Line(Text { text: s.text(), range: s.text_range() }))
             }
             ast::Value::QuotedString(qs) => {
-                let text_id = qs.eval(ctx)?;
-                ValueRaw::String(text_id)
-            }
-            ast::Value::BlockString(bs) => {
-                let text_id = bs.eval(ctx)?;
-                ValueRaw::String(text_id)
+                string = qs.string();
+                string
+                    .as_ref()
+                    .map(|s| ValueText::Quoted(Text { text: s.text(), range: s.text_range() }))
             }
+            ast::Value::BlockString(bs) => Some(bs.eval(ctx)),
         };
-        Some(value)
+
+        with_key_text(&key, |key| ctx.entry(key, key_range, value));
     }
 }
 
-impl Eval for ast::Boolean {
-    type Output = Option<bool>;
+impl Eval for ast::PrefixBlock {
+    type Output = ();
 
-    fn eval(&self, _ctx: &mut Context) -> Self::Output {
-        let val = match self.kind()? {
-            BooleanKind::True => true,
-            BooleanKind::False => false,
-        };
-        Some(val)
-    }
-}
+    fn eval<S: Sink>(&self, ctx: &mut Context<'_, S>) {
+        let Some(key) = self.key() else { return };
+        let Some(prev_prefix_len) = with_key_text(&key, |key| ctx.start_block(key)) else { return };
 
-impl Eval for ast::Integer {
-    type Output = Option<TextId>;
-
-    fn eval(&self, ctx: &mut Context) -> Self::Output {
-        let numeral = self.numeral()?;
-        let text = numeral.text();
-        let valid = match text.as_bytes() {
-            [b'0', b'b', ..] => text[2..].bytes().all(|b| matches!(b, b'0' | b'1' | b'_')),
-            [b'0', b'o', ..] => text[2..].bytes().all(|b| matches!(b, b'0'..=b'7' | b'_')),
-            [b'0', b'x', ..] => text[2..]
-                .bytes()
-                .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' | b'_')),
-            _ => true,
-        };
-        if !valid {
-            ctx.errors.push(Error::InvalidRadixDigits {
-                range: numeral.text_range(),
-                text: text.to_string(),
-            });
-            return None;
-        }
-        let buf = ctx.temporary_string.get();
-        if let Some(sign) = self.sign() {
-            buf.push_str(sign.text());
+        for item in self.items() {
+            if ctx.is_stopped() {
+                break;
+            }
+            item.eval(ctx);
         }
-        buf.push_str(text);
-        Some(ctx.arena.alloc(buf))
-    }
-}
-
-impl Eval for ast::QuotedString {
-    type Output = Option<TextId>;
 
-    fn eval(&self, ctx: &mut Context) -> Self::Output {
-        let string = self.string()?;
-        let buf = ctx.temporary_string.get();
-        unescape(string.text(), buf, string.text_range().start(), &mut ctx.errors);
-        Some(ctx.arena.alloc(buf))
+        ctx.finish_block(prev_prefix_len);
     }
 }
 
 impl Eval for ast::BlockString {
-    type Output = Option<TextId>;
+    type Output = ValueText<'static>;
 
-    fn eval(&self, ctx: &mut Context) -> Self::Output {
+    fn eval<S: Sink>(&self, ctx: &mut Context<'_, S>) -> Self::Output {
         let (is_folded, chomp) = match self.header() {
             Some(h) => {
                 let is_folded = h.style().is_some_and(|s| s.kind() == SyntaxKind::GT);
@@ -233,65 +663,78 @@ impl Eval for ast::BlockString {
             }
             None => (false, None),
         };
+        let block_string = ctx.block_string();
+        block_string.start(is_folded);
+        for line in self.lines() {
+            block_string.line(line.string().as_ref().map(|token| token.text()));
+        }
+        ValueText::Block { chomp }
+    }
+}
 
-        let buf = ctx.temporary_string.get();
-        let mut has_lines = false;
-
-        if is_folded {
-            let mut prev_content: Option<bool> = None;
-            for line in self.lines() {
-                has_lines = true;
-                match line.string() {
-                    Some(token) => {
-                        let text = token.text();
-                        let more_indented = text.starts_with(' ');
-                        if let Some(prev_was_more) = prev_content {
-                            if prev_was_more || more_indented {
-                                buf.push('\n');
-                            } else {
-                                buf.push(' ');
-                            }
-                        }
-                        buf.push_str(text);
-                        prev_content = Some(more_indented);
-                    }
-                    None => {
-                        buf.push('\n');
-                        prev_content = None;
-                    }
-                }
-            }
-        } else {
-            for (i, line) in self.lines().enumerate() {
-                has_lines = true;
-                if i > 0 {
-                    buf.push('\n');
-                }
-                if let Some(token) = line.string() {
-                    buf.push_str(token.text());
-                }
-            }
+/// Whether `line_string` goes on at the next line after a `\`.
+fn is_continued(line_string: &ast::LineString) -> bool {
+    line_string.syntax().children_with_tokens().any(|e| e.kind() == SyntaxKind::BACKSLASH)
+}
+
+/// Checks that every digit of `text` (a `NUMERAL` token) is valid for its radix prefix.
+pub(crate) fn is_valid_numeral(text: &str) -> bool {
+    match text.as_bytes() {
+        [b'0', b'b', ..] => text[2..].bytes().all(|b| matches!(b, b'0' | b'1' | b'_')),
+        [b'0', b'o', ..] => text[2..].bytes().all(|b| matches!(b, b'0'..=b'7' | b'_')),
+        [b'0', b'x', ..] => {
+            text[2..].bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' | b'_'))
         }
+        _ => true,
+    }
+}
 
-        if !has_lines {
-            return Some(ctx.arena.alloc(""));
+pub(crate) fn key_text(key: &ast::Key) -> Option<String> {
+    match key {
+        ast::Key::Word(word_key) => Some(word_key.word()?.text().to_owned()),
+        ast::Key::Quoted(quoted_key) => {
+            let string = quoted_key.string()?;
+            let mut text = String::new();
+            unescape(string.text(), &mut text, string.text_range().start(), &mut Vec::new());
+            Some(text)
         }
+    }
+}
 
-        buf.push('\n');
+pub(crate) fn type_value(text: &str) -> Value<'_> {
+    // The grammar leaves the spaces at the end of a line out of the value.
+    let text = text.trim_end_matches(' ');
+    let mut kinds = mical_cli_lexer::tokenize(text).map(|token| token.kind);
+    let numeral = match (kinds.next(), kinds.next(), kinds.next()) {
+        (Some(TokenKind::True), None, _) => return Value::Bool(true),
+        (Some(TokenKind::False), None, _) => return Value::Bool(false),
+        (Some(TokenKind::Numeral { is_empty: false, .. }), None, _) => text,
+        (
+            Some(TokenKind::Plus | TokenKind::Minus),
+            Some(TokenKind::Numeral { is_empty: false, .. }),
+            None,
+        ) => &text[1..],
+        _ => return Value::String(text),
+    };
+    if is_valid_numeral(numeral) { Value::Integer(text) } else { Value::String(text) }
+}
 
-        match chomp {
-            Some(SyntaxKind::MINUS) => {
-                let end = buf.trim_end_matches('\n').len();
-                buf.truncate(end);
-            }
-            Some(SyntaxKind::PLUS) => {}
-            _ => {
-                let end = buf.trim_end_matches('\n').len();
-                buf.truncate(end);
-                buf.push('\n');
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, EvalOptions};
+
+    #[test]
+    fn values_are_typed_like_the_parser() {
+        let texts = [
+            "true", "true ", "false  ", "42", "42 ", "-0x1F ", "1_000", "12abc", "0x", "tru",
+            "hi  ", "4 2",
+        ];
+        for text in texts {
+            let source = format!("k {text}\n");
+            let (config, _, _) = Config::parse(&source, &EvalOptions::default());
+            let parsed = config.query("k").collect::<Vec<_>>();
+            assert_eq!(parsed, [type_value(text)], "{text:?}");
         }
-
-        Some(ctx.arena.alloc(buf))
     }
 }
@@ -0,0 +1,250 @@
+//! Evaluation straight from the events of the parser, without building a syntax tree.
+//!
+//! Each node is read the way its accessor in `ast` reads it: from its first child of the right
+//! kind. Entries are passed on when their node finishes, in the order the tree walk would.
+
+use super::{Context, KeyText, Sink, Text, ValueText};
+use crate::DirectiveHandler;
+use mical_cli_parser::{ParseEvent, ParseOptions};
+use mical_cli_syntax::{SyntaxKind, TextRange, TextSize};
+use std::ops::ControlFlow;
+
+/// Evaluates `text` into `sink`, stopping soon after `sink` breaks.
+pub(crate) fn eval_text<S: Sink>(
+    text: &str,
+    options: &ParseOptions,
+    handler: Option<&mut dyn DirectiveHandler>,
+    sink: S,
+) -> S {
+    let ctx = Context::new(sink, &options.limits, handler);
+    let mut walker = Walker { ctx, stack: Vec::new(), lines: Vec::new() };
+    let mut offset = TextSize::new(0);
+    mical_cli_parser::parse_with(mical_cli_lexer::tokenize(text), options, |event| {
+        match event {
+            ParseEvent::StartNode(kind) => walker.start_node(kind, offset),
+            ParseEvent::FinishNode => walker.finish_node(offset),
+            ParseEvent::Token { kind, range, text } => {
+                walker.token(kind, Text { text, range });
+                offset = range.end();
+            }
+            ParseEvent::Error(error) => walker.ctx.syntax_error(error),
+        }
+        if walker.ctx.is_stopped() { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+    });
+    walker.ctx.finish()
+}
+
+struct Walker<'s, 'h, S> {
+    ctx: Context<'h, S>,
+    stack: Vec<Frame<'s>>,
+    /// Lines of the block strings being read; each block string frame records where its lines start.
+    lines: Vec<Option<&'s str>>,
+}
+
+struct Frame<'s> {
+    start: TextSize,
+    node: Node<'s>,
+}
+
+/// What has been read of an open node.
+enum Node<'s> {
+    /// A node the evaluator skips, along with everything in it.
+    Other,
+    SourceFile,
+    Entry {
+        key: Option<(Option<KeyText<'s>>, TextRange)>,
+        /// `Some(None)` once a value node without the parts it needs was read.
+        value: Option<Option<ValueText<'s>>>,
+    },
+    PrefixBlock {
+        key_read: bool,
+        /// Set once the key was appended to the prefix; the items are skipped until then.
+        prev_prefix_len: Option<usize>,
+    },
+    Directive {
+        name: Option<Text<'s>>,
+        args: Option<Option<Text<'s>>>,
+    },
+    WordKey(Option<Text<'s>>),
+    QuotedKey(Option<Text<'s>>),
+    Boolean(Option<bool>),
+    Integer {
+        sign: Option<&'s str>,
+        numeral: Option<Text<'s>>,
+    },
+    LineString {
+        string: Option<Text<'s>>,
+        /// Set at the first `\`; the lines are then joined in the context.
+        continued: bool,
+    },
+    QuotedString(Option<Text<'s>>),
+    BlockString {
+        header: Option<(bool, Option<SyntaxKind>)>,
+        lines_start: usize,
+    },
+    BlockStringHeader {
+        style: Option<SyntaxKind>,
+        chomp: Option<SyntaxKind>,
+    },
+}
+
+impl<'s, S: Sink> Walker<'s, '_, S> {
+    fn start_node(&mut self, kind: SyntaxKind, start: TextSize) {
+        let parent = self.stack.last().map(|frame| &frame.node);
+        let in_items = match parent {
+            None => false,
+            Some(Node::SourceFile) => true,
+            Some(Node::PrefixBlock { prev_prefix_len, .. }) => prev_prefix_len.is_some(),
+            Some(_) => false,
+        };
+        let node = match (parent, kind) {
+            (None, SyntaxKind::SOURCE_FILE) => Node::SourceFile,
+            (_, SyntaxKind::ENTRY) if in_items => Node::Entry { key: None, value: None },
+            (_, SyntaxKind::PREFIX_BLOCK) if in_items => {
+                Node::PrefixBlock { key_read: false, prev_prefix_len: None }
+            }
+            (_, SyntaxKind::DIRECTIVE) if in_items => Node::Directive { name: None, args: None },
+            (Some(Node::Entry { .. } | Node::PrefixBlock { .. }), SyntaxKind::WORD_KEY) => {
+                Node::WordKey(None)
+            }
+            (Some(Node::Entry { .. } | Node::PrefixBlock { .. }), SyntaxKind::QUOTED_KEY) => {
+                Node::QuotedKey(None)
+            }
+            (Some(Node::Entry { .. }), SyntaxKind::BOOLEAN) => Node::Boolean(None),
+            (Some(Node::Entry { .. }), SyntaxKind::INTEGER) => {
+                Node::Integer { sign: None, numeral: None }
+            }
+            (Some(Node::Entry { .. }), SyntaxKind::QUOTED_STRING) => Node::QuotedString(None),
+            (Some(Node::Entry { .. }), SyntaxKind::BLOCK_STRING) => {
+                Node::BlockString { header: None, lines_start: self.lines.len() }
+            }
+            (
+                Some(Node::Entry { .. } | Node::Directive { .. } | Node::BlockString { .. }),
+                SyntaxKind::LINE_STRING,
+            ) => Node::LineString { string: None, continued: false },
+            (Some(Node::BlockString { .. }), SyntaxKind::BLOCK_STRING_HEADER) => {
+                Node::BlockStringHeader { style: None, chomp: None }
+            }
+            _ => Node::Other,
+        };
+        self.stack.push(Frame { start, node });
+    }
+
+    fn token(&mut self, kind: SyntaxKind, token: Text<'s>) {
+        let Some(frame) = self.stack.last_mut() else { return };
+        match (&mut frame.node, kind) {
+            (Node::LineString { continued: true, .. }, SyntaxKind::STRING) => {
+                self.ctx.continued_line().push_str(token.text)
+            }
+            (Node::LineString { string, continued: continued @ false }, SyntaxKind::BACKSLASH) => {
+                *continued = true;
+                let joined = self.ctx.start_continued_line();
+                if let Some(string) = string {
+                    joined.push_str(string.text);
+                }
+            }
+            (Node::Directive { name: name @ None, .. }, SyntaxKind::WORD)
+            | (Node::WordKey(name @ None), SyntaxKind::WORD) => *name = Some(token),
+            (
+                Node::QuotedKey(string @ None)
+                | Node::LineString { string: string @ None, .. }
+                | Node::QuotedString(string @ None),
+                SyntaxKind::STRING,
+            ) => *string = Some(token),
+            (Node::Boolean(b @ None), SyntaxKind::TRUE) => *b = Some(true),
+            (Node::Boolean(b @ None), SyntaxKind::FALSE) => *b = Some(false),
+            (Node::Integer { sign: sign @ None, .. }, SyntaxKind::PLUS | SyntaxKind::MINUS) => {
+                *sign = Some(token.text)
+            }
+            (Node::Integer { numeral: numeral @ None, .. }, SyntaxKind::NUMERAL) => {
+                *numeral = Some(token)
+            }
+            (
+                Node::BlockStringHeader { style: style @ None, .. },
+                SyntaxKind::PIPE | SyntaxKind::GT,
+            ) => *style = Some(kind),
+            (
+                Node::BlockStringHeader { chomp: chomp @ None, .. },
+                SyntaxKind::PLUS | SyntaxKind::MINUS,
+            ) => *chomp = Some(kind),
+            _ => {}
+        }
+    }
+
+    fn finish_node(&mut self, end: TextSize) {
+        let Some(Frame { start, node }) = self.stack.pop() else { return };
+        let range = TextRange::new(start, end);
+        let parent = self.stack.last_mut().map(|frame| &mut frame.node);
+        match node {
+            Node::Other | Node::SourceFile => {}
+            Node::Entry { key, value } => {
+                let (Some((Some(key), key_range)), Some(value)) = (key, value) else { return };
+                self.ctx.entry(key, key_range, value);
+            }
+            Node::PrefixBlock { prev_prefix_len, .. } => {
+                if let Some(prev_prefix_len) = prev_prefix_len {
+                    self.ctx.finish_block(prev_prefix_len);
+                }
+            }
+            Node::Directive { name, args } => {
+                if let Some(name) = name {
+                    self.ctx.directive(name, args.flatten(), range);
+                }
+            }
+            Node::WordKey(text) | Node::QuotedKey(text) => {
+                let key = text.map(|text| match node {
+                    Node::WordKey(_) => KeyText::Word(text),
+                    _ => KeyText::Quoted(text),
+                });
+                match parent {
+                    Some(Node::Entry { key: entry_key @ None, .. }) => {
+                        *entry_key = Some((key, range))
+                    }
+                    Some(Node::PrefixBlock { key_read: key_read @ false, prev_prefix_len }) => {
+                        *key_read = true;
+                        *prev_prefix_len = key.map(|key| self.ctx.start_block(key));
+                    }
+                    _ => {}
+                }
+            }
+            Node::Boolean(b) => set_value(parent, b.map(ValueText::Boolean)),
+            Node::Integer { sign, numeral } => set_value(
+                parent,
+                numeral.map(|numeral| ValueText::Integer { sign, numeral, range }),
+            ),
+            Node::LineString { string, continued } => match parent {
+                Some(Node::Directive { args: args @ None, .. }) => *args = Some(string),
+                Some(Node::BlockString { .. }) => self.lines.push(string.map(|s| s.text)),
+                parent if continued => set_value(parent, Some(ValueText::ContinuedLine)),
+                parent => set_value(parent, string.map(ValueText::Line)),
+            },
+            Node::QuotedString(string) => set_value(parent, string.map(ValueText::Quoted)),
+            Node::BlockString { header, lines_start } => {
+                // Only the first value of an entry is read, and it must not clobber the lines of
+                // an earlier one.
+                if let Some(Node::Entry { value: value @ None, .. }) = parent {
+                    let (is_folded, chomp) = header.unwrap_or((false, None));
+                    let block_string = self.ctx.block_string();
+                    block_string.start(is_folded);
+                    for &line in &self.lines[lines_start..] {
+                        block_string.line(line);
+                    }
+                    *value = Some(Some(ValueText::Block { chomp }));
+                }
+                self.lines.truncate(lines_start);
+            }
+            Node::BlockStringHeader { style, chomp } => {
+                if let Some(Node::BlockString { header: header @ None, .. }) = parent {
+                    *header = Some((style == Some(SyntaxKind::GT), chomp));
+                }
+            }
+        }
+    }
+}
+
+/// Reads `value` as the value of the parent entry, unless it already has one.
+fn set_value<'s>(parent: Option<&mut Node<'s>>, value: Option<ValueText<'s>>) {
+    if let Some(Node::Entry { value: slot @ None, .. }) = parent {
+        *slot = Some(value);
+    }
+}
@@ -1,5 +1,109 @@
 use crate::Error;
 use mical_cli_syntax::{TextRange, TextSize};
+use std::ops::Range;
+
+/// What an escape sequence stands for.
+enum Escaped {
+    Char(char),
+    /// Not an escape sequence. The text after the backslash is read as it is.
+    Invalid,
+    /// A `\x` or `\u` escape with a code point it cannot stand for, and the range of its digits
+    /// in the text after the backslash.
+    InvalidCodePoint(Range<usize>),
+}
+
+/// Reads the escape sequence that starts `rest`, the text after a backslash, returning what it
+/// stands for and its length, excluding the backslash. Returns `None` for an empty `rest`.
+fn escaped(rest: &str) -> Option<(Escaped, usize)> {
+    let c = rest.chars().next()?;
+    let escaped = match c {
+        '"' | '\'' | '\\' => c,
+        '0' => '\0',
+        'e' => '\x1b',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'x' => return Some(ascii_escaped(rest)),
+        'u' => return Some(unicode_escaped(rest)),
+        _ => return Some((Escaped::Invalid, c.len_utf8())),
+    };
+    Some((Escaped::Char(escaped), 1))
+}
+
+/// Reads `xHH`, an ASCII character in exactly two hex digits.
+fn ascii_escaped(rest: &str) -> (Escaped, usize) {
+    let digits = rest[1..].bytes().take(2).take_while(u8::is_ascii_hexdigit).count();
+    if digits < 2 {
+        return (Escaped::Invalid, 1 + digits);
+    }
+    match u8::from_str_radix(&rest[1..3], 16) {
+        Ok(b) if b.is_ascii() => (Escaped::Char(b as char), 3),
+        _ => (Escaped::InvalidCodePoint(1..3), 3),
+    }
+}
+
+/// Reads `u{H...}`, a Unicode scalar value in one to six hex digits.
+fn unicode_escaped(rest: &str) -> (Escaped, usize) {
+    let Some(braced) = rest[1..].strip_prefix('{') else {
+        return (Escaped::Invalid, 1);
+    };
+    let digits = braced.bytes().take_while(u8::is_ascii_hexdigit).count();
+    if digits == 0 || !braced[digits..].starts_with('}') {
+        return (Escaped::Invalid, 2 + digits);
+    }
+    let c = Some(&braced[..digits])
+        .filter(|digits| digits.len() <= 6)
+        .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+        .and_then(char::from_u32);
+    match c {
+        Some(c) => (Escaped::Char(c), 2 + digits + 1),
+        None => (Escaped::InvalidCodePoint(2..2 + digits), 2 + digits + 1),
+    }
+}
+
+/// Returns the range of every escape sequence in `text`, relative to its start, and whether it is
+/// valid. A trailing lone backslash is an invalid escape of its own.
+pub(crate) fn escape_sequences(text: &str) -> Vec<(TextRange, bool)> {
+    let mut escapes = Vec::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('\\') {
+        let start = TextSize::new((text.len() - rest.len() + i) as u32);
+        rest = &rest[i + 1..];
+        let (valid, len) = match escaped(rest) {
+            Some((Escaped::Char(_), len)) => (true, len),
+            Some((_, len)) => (false, len),
+            None => (false, 0),
+        };
+        escapes.push((TextRange::at(start, TextSize::new(1 + len as u32)), valid));
+        rest = &rest[len..];
+    }
+    escapes
+}
+
+/// Renders `text` as a string quoted with `quote`, the escapes [`unescape`] reads back in place of
+/// what cannot stand in it as it is.
+pub(crate) fn quoted(text: &str, quote: char) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push(quote);
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0' => out.push_str("\\0"),
+            '\x1b' => out.push_str("\\e"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{{{:X}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
 
 pub(super) fn unescape(
     text: &str,
@@ -7,34 +111,37 @@ pub(super) fn unescape(
     base_offset: TextSize,
     errors: &mut Vec<Error>,
 ) {
-    let mut chars = text.chars();
-    let mut offset: u32 = base_offset.into();
-    while let Some(c) = chars.next() {
-        if c != '\\' {
-            result.push(c);
-            continue;
-        }
-        let Some(c) = chars.next() else {
-            let start = offset + result.len() as u32;
-            let range = TextRange::at(start.into(), 1.into());
+    let mut rest = text;
+    while let Some(i) = rest.find('\\') {
+        result.push_str(&rest[..i]);
+        let start = base_offset + TextSize::new((text.len() - rest.len() + i) as u32);
+        rest = &rest[i + 1..];
+        let Some((escaped, len)) = escaped(rest) else {
+            let range = TextRange::at(start, TextSize::of('\\'));
             errors.push(Error::EmptyEscape { range });
-            continue;
+            break;
         };
-        result.push(match c {
-            '"' | '\'' | '\\' => c,
-            'n' => '\n',
-            'r' => '\r',
-            't' => '\t',
-            other => {
-                let start = offset + result.len() as u32;
-                let len = 1 + other.len_utf8() as u32;
-                let range = TextRange::at(start.into(), len.into());
-                errors.push(Error::InvalidEscape { range, sequence: format!("\\{other}") });
-                other
+        let sequence = || format!("\\{}", &rest[..len]);
+        match escaped {
+            Escaped::Char(c) => result.push(c),
+            Escaped::Invalid => {
+                let range = TextRange::at(start, TextSize::new(1 + len as u32));
+                errors.push(Error::InvalidEscape { range, sequence: sequence() });
+                // The backslash alone is dropped.
+                continue;
             }
-        });
-        offset += 1;
+            Escaped::InvalidCodePoint(digits) => {
+                let range = TextRange::new(
+                    start + TextSize::new(1 + digits.start as u32),
+                    start + TextSize::new(1 + digits.end as u32),
+                );
+                errors.push(Error::InvalidCodePoint { range, sequence: sequence() });
+                result.push(char::REPLACEMENT_CHARACTER);
+            }
+        }
+        rest = &rest[len..];
     }
+    result.push_str(rest);
 }
 
 #[cfg(test)]
@@ -71,13 +178,56 @@ mod tests {
         assert!(errors.is_empty());
     }
 
+    #[test]
+    fn code_points() {
+        let (result, errors) = unescape2(r"\0\e\x41\x7f");
+        assert_eq!(result, "\0\x1bA\x7f");
+        assert!(errors.is_empty());
+
+        let (result, errors) = unescape2(r"\u{41}\u{e9}\u{1F600}\u{10FFFF}\u{000041}");
+        assert_eq!(result, "A\u{e9}\u{1F600}\u{10FFFF}A");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn invalid_code_points() {
+        let range = |start: u32, len: u32| TextRange::at(start.into(), len.into());
+        let (result, errors) = unescape2(r"a\x80b\u{D800}c\u{110000}d\u{0000041}");
+        assert_eq!(result, "a\u{fffd}b\u{fffd}c\u{fffd}d\u{fffd}");
+        assert_eq!(
+            errors,
+            [
+                Error::InvalidCodePoint { range: range(3, 2), sequence: r"\x80".into() },
+                Error::InvalidCodePoint { range: range(9, 4), sequence: r"\u{D800}".into() },
+                Error::InvalidCodePoint { range: range(18, 6), sequence: r"\u{110000}".into() },
+                Error::InvalidCodePoint { range: range(29, 7), sequence: r"\u{0000041}".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_code_points() {
+        let range = |start: u32, len: u32| TextRange::at(start.into(), len.into());
+        let (result, errors) = unescape2(r"\x4g\u41\u{}\u{4x}");
+        assert_eq!(result, "x4gu41u{}u{4x}");
+        assert_eq!(
+            errors,
+            [
+                Error::InvalidEscape { range: range(0, 3), sequence: r"\x4".into() },
+                Error::InvalidEscape { range: range(4, 2), sequence: r"\u".into() },
+                Error::InvalidEscape { range: range(8, 3), sequence: r"\u{".into() },
+                Error::InvalidEscape { range: range(12, 4), sequence: r"\u{4".into() },
+            ]
+        );
+    }
+
     #[test]
     fn invalid() {
-        let (result, errors) = unescape2(r"invalid\escape");
-        assert_eq!(result, "invalidescape");
+        let (result, errors) = unescape2(r"invalid\qscape");
+        assert_eq!(result, "invalidqscape");
         assert_eq!(errors.len(), 1);
         assert!(
-            matches!(&errors[0], Error::InvalidEscape { sequence, range } if sequence == r"\e" && *range == TextRange::at(7.into(), 2.into()))
+            matches!(&errors[0], Error::InvalidEscape { sequence, range } if sequence == r"\q" && *range == TextRange::at(7.into(), 2.into()))
         );
     }
 
@@ -97,4 +247,18 @@ mod tests {
             matches!(&errors[0], Error::EmptyEscape { range } if *range == TextRange::at(5.into(), 1.into()))
         );
     }
+
+    #[test]
+    fn sequences() {
+        let range = |start: u32, len: u32| TextRange::at(start.into(), len.into());
+        assert_eq!(
+            escape_sequences(r#"a\"b\é\\\"#),
+            [(range(1, 2), true), (range(4, 3), false), (range(7, 2), true), (range(9, 1), false)]
+        );
+        assert_eq!(
+            escape_sequences(r"\u{1F600}\u{D800}\u{41"),
+            [(range(0, 9), true), (range(9, 8), false), (range(17, 5), false)]
+        );
+        assert!(escape_sequences("plain").is_empty());
+    }
 }
@@ -0,0 +1,42 @@
+use crate::key_text;
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{
+    SyntaxError, SyntaxNode,
+    ast::{self, AstChildren, AstNode},
+};
+
+/// Parses `text` into a syntax tree, for callers that edit or inspect it rather than evaluate it.
+pub fn parse_source_file(
+    text: &str,
+    options: &ParseOptions,
+) -> (ast::SourceFile, Vec<SyntaxError>) {
+    let (green, errors) =
+        mical_cli_parser::parse_with_options(mical_cli_lexer::tokenize(text), options);
+    let source_file = ast::SourceFile::cast(SyntaxNode::new_root(green))
+        .expect("the root node is always a source file");
+    (source_file, errors)
+}
+
+/// Calls `f` in document order with every entry and prefix block under `items` that has a key,
+/// its key node, the prefix that applies to it and the full key (for a block, the full prefix) it
+/// contributes, resolved the way the evaluator does.
+pub fn walk_items(
+    items: AstChildren<ast::Item>,
+    prefix: &str,
+    f: &mut impl FnMut(&ast::Item, &ast::Key, &str, &str),
+) {
+    for item in items {
+        let key = match &item {
+            ast::Item::Entry(entry) => entry.key(),
+            ast::Item::PrefixBlock(block) => block.key(),
+            ast::Item::Directive(_) => None,
+        };
+        let Some(key) = key else { continue };
+        let Some(text) = key_text(&key) else { continue };
+        let full_key = format!("{prefix}{text}");
+        f(&item, &key, prefix, &full_key);
+        if let ast::Item::PrefixBlock(block) = &item {
+            walk_items(block.items(), &full_key, f);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Config, KeyGroups, Value, Values};
+use crate::{Change, Config, ConfigDiff, Value, Values};
 use compact_str::CompactString;
 use num_bigint::BigUint;
 use serde::Serialize;
@@ -38,7 +38,8 @@ fn serialize_integer<S: serde::Serializer>(s: &str, serializer: S) -> Result<S::
         }
     }
 
-    let parsed = BigUint::parse_bytes(clean.as_bytes(), radix).expect("valid digits should parse");
+    let parsed = BigUint::parse_bytes(clean.as_bytes(), radix)
+        .ok_or_else(|| serde::ser::Error::custom(format!("invalid integer '{s}'")))?;
     let dec = if is_negative { format!("-{parsed}") } else { format!("{parsed}") };
     serde_json::value::RawValue::from_string(dec)
         .map_err(serde::ser::Error::custom)?
@@ -67,8 +68,8 @@ impl Serialize for JsonView<&[Value<'_>]> {
 
 impl Serialize for JsonView<&Values<'_>> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let KeyGroups { config, lo, hi, .. } = self.0.groups;
-        let groups = KeyGroups::new(config, lo, hi);
+        let groups = self.0.groups.restart();
+        let config = groups.config;
         let mut map = serializer.serialize_map(None)?;
         for (key, idxs) in groups {
             if idxs.len() == 1 {
@@ -76,7 +77,7 @@ impl Serialize for JsonView<&Values<'_>> {
                 map.serialize_entry(key, &JsonView(&val))?;
             } else {
                 struct Array<'a> {
-                    config: &'a Config,
+                    config: &'a Config<'a>,
                     idxs: &'a [u32],
                 }
                 impl Serialize for Array<'_> {
@@ -90,19 +91,55 @@ impl Serialize for JsonView<&Values<'_>> {
                         seq.end()
                     }
                 }
-                map.serialize_entry(key, &Array { config, idxs: &idxs })?;
+                map.serialize_entry(key, &Array { config, idxs })?;
             }
         }
         map.end()
     }
 }
 
-impl Serialize for JsonView<&Config> {
+impl Serialize for JsonView<&Config<'_>> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         JsonView(&self.0.entries()).serialize(serializer)
     }
 }
 
+impl Serialize for JsonView<&ConfigDiff<'_>> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.changes.len()))?;
+        for change in &self.0.changes {
+            seq.serialize_element(&JsonView(change))?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for JsonView<&Change<'_>> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match self.0 {
+            Change::Added { key, new } => {
+                map.serialize_entry("op", "added")?;
+                map.serialize_entry("key", key)?;
+                map.serialize_entry("new", &JsonView(new.as_slice()))?;
+            }
+            Change::Removed { key, old } => {
+                map.serialize_entry("op", "removed")?;
+                map.serialize_entry("key", key)?;
+                map.serialize_entry("old", &JsonView(old.as_slice()))?;
+            }
+            Change::Changed { key, kind, old, new } => {
+                map.serialize_entry("op", "changed")?;
+                map.serialize_entry("key", key)?;
+                map.serialize_entry("kind", kind.as_str())?;
+                map.serialize_entry("old", &JsonView(old.as_slice()))?;
+                map.serialize_entry("new", &JsonView(new.as_slice()))?;
+            }
+        }
+        map.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::JsonView;
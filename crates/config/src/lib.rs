@@ -1,25 +1,83 @@
-use mical_cli_syntax::ast;
-use smallvec::{SmallVec, ToSmallVec};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{SyntaxError, TextRange, ast};
+use std::{borrow::Cow, mem};
 
 mod text_arena;
-use text_arena::{TextArena, TextId};
+use text_arena::{Interner, KeyId, TextArena, TextId};
 
 mod error;
 pub use error::Error;
 
+mod diff;
+pub use diff::{Change, ChangeKind, ConfigDiff};
+
+mod directive;
+pub use directive::{Directive, DirectiveHandler, DirectiveOutput};
+
+mod duplicates;
+pub use duplicates::DuplicatePolicy;
+
+mod env;
+pub use env::EnvOverrides;
+
 mod eval;
+mod items;
+pub use items::{parse_source_file, walk_items};
+
 mod json;
 pub use json::JsonView;
 
-pub struct Config {
-    arena: TextArena,
+mod namespace;
+pub use namespace::{Child, Namespace};
+
+mod pattern;
+pub use pattern::{Pattern, PatternError};
+
+mod prefix_runs;
+use prefix_runs::PrefixRuns;
+
+mod suggest;
+
+mod visit;
+pub use visit::{Visitor, visit, visit_with_options};
+
+/// An evaluated config.
+///
+/// A config built by [`Config::from_source_text`] borrows the keys and values spelled verbatim in
+/// the source; the other constructors copy everything and return a `Config<'static>`.
+pub struct Config<'s> {
+    arena: TextArena<'s>,
+    /// Each distinct full key, indexed by `KeyId`.
+    keys: Vec<TextId>,
     /// Entry list in insertion order
-    entries: Vec<(TextId, ValueRaw)>,
-    /// Sorted list of indices into `entries` by key string (for binary search)
+    entries: Vec<(KeyId, ValueRaw)>,
+    /// Indices into `entries` sorted by key string (for binary search), and by insertion order
+    /// among equal keys: the entries of each unique key form a run in insertion order.
     sorted_indices: Vec<u32>,
-    /// Group information for unique keys (sorted by first occurrence order).
-    /// element (group_start, count) means sorted_indices[group_start..group_start+count] are the indices of entries with the same key.
-    group_order: Vec<(u32, u32)>,
+    /// Start of each run of `sorted_indices` (a group of entries with the same key) in key order,
+    /// followed by `sorted_indices.len()`.
+    group_starts: Vec<u32>,
+    /// Groups (indices into `group_starts`) in first-occurrence order.
+    group_order: Vec<u32>,
+    /// The groups of each prefix's keys, in first-occurrence order.
+    prefix_runs: PrefixRuns,
+    /// (entry index, variable name) for entries overridden from the environment, sorted by entry index.
+    env_origins: Vec<(u32, TextId)>,
+    /// Every directive in source order.
+    directives: Vec<DirectiveRaw>,
+    /// Metadata set by a [`DirectiveHandler`], in the order it was set.
+    metadata: Vec<(TextId, ValueRaw)>,
+}
+
+/// Options for [`Config::from_source_file_with`] and [`Config::parse`].
+#[derive(Clone, Debug, Default)]
+pub struct EvalOptions {
+    /// The duplicate policy for files without a `#duplicates` directive.
+    pub duplicates: DuplicatePolicy,
+    /// The limits and extensions of the language to parse with. [`Config::parse`] passes them to
+    /// the parser, and the evaluator applies the limits as well; a tree for
+    /// [`Config::from_source_file_with`] should be parsed with the same ones.
+    pub parse: ParseOptions,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -29,6 +87,15 @@ pub enum Value<'s> {
     String(&'s str),
 }
 
+/// Where the value of an entry came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin<'s> {
+    /// Defined in the evaluated source.
+    Source,
+    /// Overridden by the named environment variable.
+    Env(&'s str),
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub(crate) enum ValueRaw {
     Bool(bool),
@@ -36,8 +103,42 @@ pub(crate) enum ValueRaw {
     String(TextId),
 }
 
+impl<'s> Value<'s> {
+    /// Types `text` the way the parser types the value of an entry: `true`/`false` and integer
+    /// literals (`42`, `-0x1F`, `1_000`) keep their type, anything else is a string, verbatim. As in
+    /// the parser, trailing spaces are not part of the value.
+    pub fn from_text(text: &'s str) -> Self {
+        eval::type_value(text)
+    }
+}
+
+/// Returns the text `key` contributes to the full keys of its entries, with the escapes of a quoted
+/// key resolved.
+pub fn key_text(key: &ast::Key) -> Option<String> {
+    eval::key_text(key)
+}
+
+/// Returns the range of every escape sequence in the text of a quoted string or key, relative to
+/// its start, and whether it is valid.
+pub fn escape_sequences(text: &str) -> Vec<(mical_cli_syntax::TextRange, bool)> {
+    eval::escape_sequences(text)
+}
+
+/// Renders `text` as a string quoted with `quote`, escaping what cannot stand in it as it is.
+pub fn quoted(text: &str, quote: char) -> String {
+    eval::quoted(text, quote)
+}
+
 impl ValueRaw {
-    fn to_value<'s>(self, arena: &'s TextArena) -> Value<'s> {
+    fn alloc(value: Value<'_>, arena: &mut TextArena<'_>) -> Self {
+        match value {
+            Value::Bool(b) => ValueRaw::Bool(b),
+            Value::Integer(s) => ValueRaw::Integer(arena.alloc(s)),
+            Value::String(s) => ValueRaw::String(arena.alloc(s)),
+        }
+    }
+
+    fn to_value<'s>(self, arena: &'s TextArena<'_>) -> Value<'s> {
         match self {
             ValueRaw::Bool(b) => Value::Bool(b),
             ValueRaw::Integer(id) => Value::Integer(&arena[id]),
@@ -46,35 +147,80 @@ impl ValueRaw {
     }
 }
 
-/// Iterates over groups of entries within [lo, hi) range in first-occurrence order.
-/// Each item is (key, sorted_indices) where sorted_indices contains insertion-ordered entry indices.
+pub(crate) struct DirectiveRaw {
+    pub(crate) name: TextId,
+    pub(crate) args: TextId,
+    pub(crate) range: TextRange,
+    pub(crate) args_range: TextRange,
+    pub(crate) prefix: TextId,
+}
+
+impl DirectiveRaw {
+    fn to_directive<'s>(&self, arena: &'s TextArena<'_>) -> Directive<'s> {
+        Directive {
+            name: &arena[self.name],
+            args: &arena[self.args],
+            range: self.range,
+            args_range: self.args_range,
+            prefix: &arena[self.prefix],
+        }
+    }
+}
+
+/// Iterates over groups of entries in first-occurrence order.
+/// Each item is (key, entry indices) where the entry indices are in insertion order.
+#[derive(Clone)]
 pub(crate) struct KeyGroups<'a> {
-    pub(crate) config: &'a Config,
-    pub(crate) lo: usize,
-    pub(crate) hi: usize,
+    pub(crate) config: &'a Config<'a>,
+    /// The groups to visit, in first-occurrence order.
+    groups: Cow<'a, [u32]>,
+    /// Only groups whose key matches are yielded.
+    pub(crate) pattern: Option<&'a Pattern>,
     pos: usize,
 }
 
 impl<'a> KeyGroups<'a> {
-    pub(crate) fn new(config: &'a Config, lo: usize, hi: usize) -> Self {
-        KeyGroups { config, lo, hi, pos: 0 }
+    /// Visits the groups whose entries lie in the range [lo, hi) of `sorted_indices`, which must
+    /// not split a group.
+    ///
+    /// Finding the groups costs three binary searches, O(log n) for n groups. When the range holds
+    /// the keys with a prefix, the groups are already in first-occurrence order, so visiting the k
+    /// groups found costs O(k); any other range takes a pass over every group, O(n).
+    pub(crate) fn new(config: &'a Config<'a>, lo: usize, hi: usize) -> Self {
+        let starts = &config.group_starts;
+        let first = starts.partition_point(|&start| (start as usize) < lo);
+        let last = starts.partition_point(|&start| (start as usize) < hi);
+        let groups = match config.prefix_runs.get(first, last) {
+            Some(groups) => Cow::Borrowed(groups),
+            None => {
+                let in_range = |&g: &u32| (first..last).contains(&(g as usize));
+                Cow::Owned(config.group_order.iter().copied().filter(in_range).collect())
+            }
+        };
+        KeyGroups { config, groups, pattern: None, pos: 0 }
+    }
+
+    /// Returns a fresh iterator over the same groups.
+    pub(crate) fn restart(&self) -> Self {
+        KeyGroups { pos: 0, ..self.clone() }
     }
 }
 
 impl<'a> Iterator for KeyGroups<'a> {
-    type Item = (&'a str, SmallVec<[u32; 4]>);
+    type Item = (&'a str, &'a [u32]);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.pos < self.config.group_order.len() {
-            let (gs, count) = self.config.group_order[self.pos];
+        let config = self.config;
+        while let Some(&g) = self.groups.get(self.pos) {
             self.pos += 1;
-            if (gs as usize) >= self.lo && (gs as usize) < self.hi {
-                let (gs, count) = (gs as usize, count as usize);
-                let mut idxs = self.config.sorted_indices[gs..(gs + count)].to_smallvec();
-                idxs.sort_unstable();
-                let key = &self.config.arena[self.config.entries[idxs[0] as usize].0];
-                return Some((key, idxs));
+            let (start, end) =
+                (config.group_starts[g as usize], config.group_starts[g as usize + 1]);
+            let idxs = &config.sorted_indices[start as usize..end as usize];
+            let key = config.entry_key(idxs[0]);
+            if self.pattern.is_some_and(|pattern| !pattern.matches(key)) {
+                continue;
             }
+            return Some((key, idxs));
         }
         None
     }
@@ -82,135 +228,303 @@ impl<'a> Iterator for KeyGroups<'a> {
 
 pub struct Values<'a> {
     groups: KeyGroups<'a>,
-    current_idxs: SmallVec<[u32; 4]>,
-    idx_pos: usize,
+    current_idxs: &'a [u32],
 }
-// Most cases have 1 entry per key, use SmallVec to optimize for that case.
-// SmallVec<[u32; 4]> is used because it has the same size as SmallVec<[u32; 1]>.
-const _: () = {
-    assert!(size_of::<SmallVec<[u32; 4]>>() == size_of::<SmallVec<[u32; 1]>>());
-    assert!(size_of::<SmallVec<[u32; 5]>>() > size_of::<SmallVec<[u32; 1]>>());
-};
 
 impl<'a> Iterator for Values<'a> {
     type Item = (&'a str, Value<'a>);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            if self.idx_pos < self.current_idxs.len() {
-                let i = self.current_idxs[self.idx_pos];
-                self.idx_pos += 1;
+            if let Some((&i, rest)) = self.current_idxs.split_first() {
+                self.current_idxs = rest;
                 let config = self.groups.config;
                 let (key_id, raw) = config.entries[i as usize];
-                return Some((&config.arena[key_id], raw.to_value(&config.arena)));
+                return Some((config.key(key_id), raw.to_value(&config.arena)));
             }
             let (_, idxs) = self.groups.next()?;
             self.current_idxs = idxs;
-            self.idx_pos = 0;
         }
     }
 }
 
-impl Config {
+impl Config<'static> {
     pub fn from_source_file(source_file: ast::SourceFile) -> (Self, Vec<Error>) {
-        let eval::Output { arena, entries, errors } = eval::eval_source_file(&source_file);
-        let (sorted_indices, group_order) = Self::build_indices(&arena, &entries);
-        (Config { arena, entries, sorted_indices, group_order }, errors)
+        Self::from_source_file_with(source_file, &EvalOptions::default())
+    }
+
+    pub fn from_source_file_with(
+        source_file: ast::SourceFile,
+        options: &EvalOptions,
+    ) -> (Self, Vec<Error>) {
+        Config::from_output(
+            eval::eval_source_file(&source_file, "", &options.parse.limits, None),
+            options,
+        )
     }
 
     pub fn from_kv_entries<'a>(items: impl IntoIterator<Item = (&'a str, Value<'a>)>) -> Self {
         let mut arena = TextArena::new();
+        let mut keys = Vec::new();
+        let mut interner = Interner::new();
         let mut entries = Vec::new();
         for (key, val) in items {
-            let key_id = arena.alloc(key);
-            let raw = match val {
-                Value::Bool(b) => ValueRaw::Bool(b),
-                Value::Integer(s) => ValueRaw::Integer(arena.alloc(s)),
-                Value::String(s) => ValueRaw::String(arena.alloc(s)),
-            };
-            entries.push((key_id, raw));
+            let key_id = interner.intern(&mut arena, &mut keys, key, TextArena::alloc);
+            entries.push((key_id, ValueRaw::alloc(val, &mut arena)));
+        }
+        let (sorted_indices, group_starts, group_order, prefix_runs) =
+            Self::build_indices(&arena, &keys, &entries);
+        Config {
+            arena,
+            keys,
+            entries,
+            sorted_indices,
+            group_starts,
+            group_order,
+            prefix_runs,
+            env_origins: Vec::new(),
+            directives: Vec::new(),
+            metadata: Vec::new(),
         }
-        let (sorted_indices, group_order) = Self::build_indices(&arena, &entries);
-        Config { arena, entries, sorted_indices, group_order }
     }
+}
 
-    fn build_indices(
-        arena: &TextArena,
-        entries: &[(TextId, ValueRaw)],
-    ) -> (Vec<u32>, Vec<(u32, u32)>) {
-        let sorted_indices = {
-            let mut indices = (0..entries.len() as u32).collect::<Vec<_>>();
-            indices.sort_unstable_by(|&a, &b| {
-                arena[entries[a as usize].0].cmp(&arena[entries[b as usize].0])
-            });
-            indices
+impl<'s> Config<'s> {
+    /// Same as [`Config::from_source_file_with`], but keys and values spelled verbatim in `text`,
+    /// the source `source_file` was parsed from, borrow it instead of being copied. That covers
+    /// line strings, integers, quoted strings without escapes, and keys outside prefix blocks.
+    pub fn from_source_text(
+        text: &'s str,
+        source_file: ast::SourceFile,
+        options: &EvalOptions,
+    ) -> (Self, Vec<Error>) {
+        Config::from_output(
+            eval::eval_source_file(&source_file, text, &options.parse.limits, None),
+            options,
+        )
+    }
+
+    /// Same as [`Config::from_source_text`], passing each directive to `handler`. Pass `""` as
+    /// `text` to copy everything instead.
+    pub fn from_source_text_with_handler(
+        text: &'s str,
+        source_file: ast::SourceFile,
+        options: &EvalOptions,
+        handler: &mut dyn DirectiveHandler,
+    ) -> (Self, Vec<Error>) {
+        let limits = &options.parse.limits;
+        Config::from_output(
+            eval::eval_source_file(&source_file, text, limits, Some(handler)),
+            options,
+        )
+    }
+
+    /// Parses and evaluates `text` in one pass, without building a syntax tree, and borrows it
+    /// like [`Config::from_source_text`]. The config and errors are the same as parsing `text` and
+    /// calling [`Config::from_source_text`] would give.
+    pub fn parse(text: &'s str, options: &EvalOptions) -> (Self, Vec<SyntaxError>, Vec<Error>) {
+        Config::parse_with(text, options, None)
+    }
+
+    /// Same as [`Config::parse`], passing each directive to `handler`.
+    pub fn parse_with_handler(
+        text: &'s str,
+        options: &EvalOptions,
+        handler: &mut dyn DirectiveHandler,
+    ) -> (Self, Vec<SyntaxError>, Vec<Error>) {
+        Config::parse_with(text, options, Some(handler))
+    }
+
+    fn parse_with(
+        text: &'s str,
+        options: &EvalOptions,
+        handler: Option<&mut dyn DirectiveHandler>,
+    ) -> (Self, Vec<SyntaxError>, Vec<Error>) {
+        let collect = eval::Collect::new(text);
+        let mut output = eval::eval_text(text, &options.parse, handler, collect).finish();
+        let syntax_errors = mem::take(&mut output.syntax_errors);
+        let (config, errors) = Config::from_output(output, options);
+        (config, syntax_errors, errors)
+    }
+
+    fn from_output(output: eval::Output<'s>, options: &EvalOptions) -> (Self, Vec<Error>) {
+        let eval::Output {
+            arena,
+            keys,
+            mut entries,
+            key_ranges,
+            duplicates,
+            directives,
+            metadata,
+            mut errors,
+            ..
+        } = output;
+        let policy = duplicates.unwrap_or(options.duplicates);
+        duplicates::resolve(policy, &arena, &keys, &mut entries, &key_ranges, &mut errors);
+        let (sorted_indices, group_starts, group_order, prefix_runs) =
+            Self::build_indices(&arena, &keys, &entries);
+        let env_origins = Vec::new();
+        let config = Config {
+            arena,
+            keys,
+            entries,
+            sorted_indices,
+            group_starts,
+            group_order,
+            prefix_runs,
+            env_origins,
+            directives,
+            metadata,
         };
-        let group_order = {
-            let mut groups: Vec<(u32, u32, u32)> = Vec::new(); // (sorted_start, count, first_entry_idx)
-            let mut i = 0;
-            while i < sorted_indices.len() {
-                let group_start = i;
-                let cur_key_id = entries[sorted_indices[i] as usize].0;
-                let mut min_idx = sorted_indices[i];
-                i += 1;
-                while i < sorted_indices.len() {
-                    let next_key_id = entries[sorted_indices[i] as usize].0;
-                    if arena[cur_key_id] != arena[next_key_id] {
-                        break;
-                    }
-                    min_idx = min_idx.min(sorted_indices[i]); // chmin
-                    i += 1;
-                }
-                groups.push((group_start as u32, (i - group_start) as u32, min_idx));
+        (config, errors)
+    }
+}
+
+impl Config<'_> {
+    /// Sorts the distinct keys with entries by their text, then places the entries by the rank of
+    /// their key, so entries are never compared by text.
+    fn build_indices(
+        arena: &TextArena<'_>,
+        keys: &[TextId],
+        entries: &[(KeyId, ValueRaw)],
+    ) -> (Vec<u32>, Vec<u32>, Vec<u32>, PrefixRuns) {
+        // The group of each key, once it is known to have entries.
+        let mut group_of = vec![None::<u32>; keys.len()];
+        let mut first_seen = Vec::new();
+        for &(key_id, _) in entries {
+            if group_of[key_id as usize].replace(0).is_none() {
+                first_seen.push(key_id);
             }
-            groups.sort_unstable_by_key(|&(_, _, first)| first);
-            groups.into_iter().map(|(s, c, _)| (s, c)).collect()
-        };
-        (sorted_indices, group_order)
+        }
+        let mut by_key = first_seen.clone();
+        by_key.sort_unstable_by(|&a, &b| arena[keys[a as usize]].cmp(&arena[keys[b as usize]]));
+        for (g, &key_id) in by_key.iter().enumerate() {
+            group_of[key_id as usize] = Some(g as u32);
+        }
+        let group = |key_id: KeyId| group_of[key_id as usize].unwrap() as usize;
+
+        // A counting sort by group keeps each run in insertion order.
+        let mut group_starts = vec![0u32; by_key.len() + 1];
+        for &(key_id, _) in entries {
+            group_starts[group(key_id) + 1] += 1;
+        }
+        for g in 1..group_starts.len() {
+            group_starts[g] += group_starts[g - 1];
+        }
+        let mut next = group_starts.clone();
+        let mut sorted_indices = vec![0u32; entries.len()];
+        for (i, &(key_id, _)) in entries.iter().enumerate() {
+            let slot = &mut next[group(key_id)];
+            sorted_indices[*slot as usize] = i as u32;
+            *slot += 1;
+        }
+        let group_order = first_seen.iter().map(|&key_id| group(key_id) as u32).collect::<Vec<_>>();
+        let sorted_keys =
+            by_key.iter().map(|&key_id| &arena[keys[key_id as usize]]).collect::<Vec<_>>();
+        let prefix_runs = PrefixRuns::new(&sorted_keys, &group_order);
+        (sorted_indices, group_starts, group_order, prefix_runs)
+    }
+
+    #[inline]
+    fn key(&self, key_id: KeyId) -> &str {
+        &self.arena[self.keys[key_id as usize]]
+    }
+
+    /// Returns the key of the entry at `entry_idx`.
+    #[inline]
+    fn entry_key(&self, entry_idx: u32) -> &str {
+        self.key(self.entries[entry_idx as usize].0)
+    }
+
+    fn key_range(&self, key: &str) -> (usize, usize) {
+        let lo = self.sorted_indices.partition_point(|&i| self.entry_key(i) < key);
+        let hi = self.sorted_indices[lo..].partition_point(|&i| self.entry_key(i) <= key) + lo;
+        (lo, hi)
+    }
+
+    /// Returns the range of `sorted_indices` whose keys start with `prefix`.
+    fn prefix_range(&self, prefix: &str) -> (usize, usize) {
+        let lo = self.sorted_indices.partition_point(|&i| self.entry_key(i) < prefix);
+        let hi = self.sorted_indices.partition_point(|&i| {
+            let key = self.entry_key(i);
+            key.starts_with(prefix) || key < prefix
+        });
+        (lo, hi)
+    }
+
+    /// Return entry indices that exactly match `key` in insertion order.
+    fn key_indices(&self, key: &str) -> &[u32] {
+        let (lo, hi) = self.key_range(key);
+        &self.sorted_indices[lo..hi]
+    }
+
+    fn origin_of(&self, entry_idx: u32) -> Origin<'_> {
+        match self.env_origins.binary_search_by_key(&entry_idx, |&(i, _)| i) {
+            Ok(pos) => Origin::Env(&self.arena[self.env_origins[pos].1]),
+            Err(_) => Origin::Source,
+        }
     }
 
     /// Return values that exactly match `key` in insertion order (grouped by first occurrence).
     pub fn query<'a>(&'a self, key: &str) -> impl Iterator<Item = Value<'a>> + 'a {
-        let lo = self.sorted_indices.partition_point(|i| {
-            let key_id = self.entries[*i as usize].0;
-            &self.arena[key_id] < key
-        });
-        let hi = self.sorted_indices[lo..].partition_point(|i| {
-            let key_id = self.entries[*i as usize].0;
-            &self.arena[key_id] <= key
-        }) + lo;
-        let idxs: SmallVec<[u32; 4]> = {
-            const _: () = {
-                assert!(size_of::<SmallVec<[u32; 4]>>() == size_of::<SmallVec<[u32; 1]>>());
-                assert!(size_of::<SmallVec<[u32; 5]>>() > size_of::<SmallVec<[u32; 1]>>());
-            };
-            let mut v = self.sorted_indices[lo..hi].to_smallvec();
-            v.sort_unstable(); // insertion order
-            v
-        };
-        idxs.into_iter().map(move |i| {
+        self.key_indices(key).iter().map(move |&i| {
             let (_, raw) = self.entries[i as usize];
             raw.to_value(&self.arena)
         })
     }
 
+    /// Same as [`Config::query`], but also reports where each value came from.
+    pub fn query_with_origin<'a>(
+        &'a self,
+        key: &str,
+    ) -> impl Iterator<Item = (Value<'a>, Origin<'a>)> + 'a {
+        self.key_indices(key).iter().map(move |&i| {
+            let (_, raw) = self.entries[i as usize];
+            (raw.to_value(&self.arena), self.origin_of(i))
+        })
+    }
+
+    /// Return (key, value, variable name) for every entry overridden from the environment, in
+    /// insertion order.
+    pub fn env_overrides(&self) -> impl Iterator<Item = (&str, Value<'_>, &str)> {
+        self.env_origins.iter().map(|&(i, var_id)| {
+            let (key_id, raw) = self.entries[i as usize];
+            (self.key(key_id), raw.to_value(&self.arena), &self.arena[var_id])
+        })
+    }
+
     /// Return (key, value) pairs whose keys start with `prefix` in insertion order (grouped by first occurrence).
+    ///
+    /// Finding the k keys with the prefix costs O(log n + k) for n keys.
     pub fn query_prefix(&self, prefix: &str) -> Values<'_> {
-        let lo = self.sorted_indices.partition_point(|i| {
-            let key_id = self.entries[*i as usize].0;
-            &self.arena[key_id] < prefix
-        });
-        let hi = self.sorted_indices.partition_point(|i| {
-            let key_id = self.entries[*i as usize].0;
-            let key = &self.arena[key_id];
-            key.starts_with(prefix) || key < prefix
-        });
-        Values { groups: KeyGroups::new(self, lo, hi), current_idxs: SmallVec::new(), idx_pos: 0 }
+        let (lo, hi) = self.prefix_range(prefix);
+        Values { groups: KeyGroups::new(self, lo, hi), current_idxs: &[] }
+    }
+
+    /// Return (key, value) pairs whose keys match `pattern` in insertion order (grouped by first
+    /// occurrence).
+    ///
+    /// Only keys starting with the pattern's literal prefix are examined.
+    pub fn query_pattern<'a>(&'a self, pattern: &'a Pattern) -> Values<'a> {
+        let Values { mut groups, .. } = self.query_prefix(pattern.literal_prefix());
+        groups.pattern = Some(pattern);
+        Values { groups, current_idxs: &[] }
+    }
+
+    /// Returns every directive of the source, `#duplicates` included, in source order.
+    pub fn directives(&self) -> impl Iterator<Item = Directive<'_>> {
+        self.directives.iter().map(|directive| directive.to_directive(&self.arena))
+    }
+
+    /// Returns the value a [`DirectiveHandler`] last set the metadata `key` to.
+    pub fn metadata(&self, key: &str) -> Option<Value<'_>> {
+        let (_, raw) = self.metadata.iter().rev().find(|&&(id, _)| &self.arena[id] == key)?;
+        Some(raw.to_value(&self.arena))
     }
 
     /// Return all (key, value) pairs in the order they were inserted. (grouped by first occurrence)
     pub fn entries(&self) -> Values<'_> {
         let hi = self.sorted_indices.len();
-        Values { groups: KeyGroups::new(self, 0, hi), current_idxs: SmallVec::new(), idx_pos: 0 }
+        Values { groups: KeyGroups::new(self, 0, hi), current_idxs: &[] }
     }
 }
@@ -0,0 +1,90 @@
+use crate::{Config, Value};
+use std::collections::BTreeMap;
+
+/// A segment-aware view over the keys of a [`Config`], see [`Config::namespace`].
+///
+/// Unlike [`Config::query_prefix`], which matches raw text, a namespace path only matches whole
+/// segments: `server` covers `server.port` but not `serverless.port`. Every lookup is a handful
+/// of binary searches over the sorted key index.
+#[derive(Clone, Copy)]
+pub struct Namespace<'a> {
+    config: &'a Config<'a>,
+    separator: &'a str,
+}
+
+/// An immediate child segment of a namespace path, see [`Namespace::children`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Child<'a> {
+    pub segment: &'a str,
+    /// Number of entries whose key is exactly this child.
+    pub values: usize,
+    /// Number of entries below this child.
+    pub descendants: usize,
+}
+
+impl Config<'_> {
+    /// Returns a namespace view splitting keys on `separator`, which must not be empty.
+    pub fn namespace<'a>(&'a self, separator: &'a str) -> Namespace<'a> {
+        assert!(!separator.is_empty(), "namespace separator must not be empty");
+        Namespace { config: self, separator }
+    }
+
+    fn sorted_key(&self, pos: usize) -> &str {
+        self.entry_key(self.sorted_indices[pos])
+    }
+}
+
+impl<'a> Namespace<'a> {
+    /// The text every key below `path` starts with; the root path `""` covers every key.
+    fn head(&self, path: &str) -> String {
+        if path.is_empty() { String::new() } else { format!("{path}{}", self.separator) }
+    }
+
+    /// Lists the immediate child segments of `path` in key order.
+    pub fn children(&self, path: &str) -> Vec<Child<'a>> {
+        let config = self.config;
+        let head = self.head(path);
+        let (mut pos, hi) = config.prefix_range(&head);
+        let mut children = BTreeMap::<&'a str, Child<'a>>::new();
+        // Keys equal to a child and keys below it are not necessarily adjacent (`a` < `a-b` <
+        // `a.c`), so jump over one block at a time: either the exact key or the subtree.
+        while pos < hi {
+            let rest = &config.sorted_key(pos)[head.len()..];
+            let segment = match rest.find(self.separator) {
+                Some(i) => &rest[..i],
+                None => rest,
+            };
+            let child =
+                children.entry(segment).or_insert(Child { segment, values: 0, descendants: 0 });
+            let child_key = format!("{head}{segment}");
+            if rest.len() == segment.len() {
+                let (_, end) = config.key_range(&child_key);
+                child.values += end - pos;
+                pos = end;
+            } else {
+                let (_, end) = config.prefix_range(&format!("{child_key}{}", self.separator));
+                child.descendants += end - pos;
+                pos = end;
+            }
+        }
+        children.into_values().collect()
+    }
+
+    /// Returns the entries below `path` with `path` and the separator stripped from their keys, in
+    /// insertion order (grouped by first occurrence).
+    pub fn subtree(&self, path: &str) -> impl Iterator<Item = (&'a str, Value<'a>)> + 'a {
+        let head = self.head(path);
+        let strip = head.len();
+        self.config.query_prefix(&head).map(move |(key, value)| (&key[strip..], value))
+    }
+
+    /// Returns whether `path` is a key with nothing below it.
+    pub fn is_leaf(&self, path: &str) -> bool {
+        let (lo, hi) = self.config.key_range(path);
+        if lo == hi {
+            return false;
+        }
+        let (lo, hi) = self.config.prefix_range(&format!("{path}{}", self.separator));
+        lo == hi
+    }
+}
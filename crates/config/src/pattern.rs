@@ -0,0 +1,328 @@
+use core::fmt;
+
+/// A glob pattern over full keys, used by [`Config::query_pattern`](crate::Config::query_pattern).
+///
+/// - `*` matches any run of characters within one segment (it never crosses the separator)
+/// - `**` matches any run of characters, across segments
+/// - `?` matches one character other than the separator
+/// - `{a,b}` matches either alternative; alternatives may contain any of the above
+/// - `\` escapes the next character
+///
+/// For example, with the default `.` separator, `services.*.port` matches `services.web.port`
+/// but not `services.web.http.port`, while `services.**.port` matches both.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    /// Brace-expanded alternatives; a key matches if any of them matches.
+    alternatives: Vec<Vec<Piece>>,
+    separator: String,
+    /// Literal text every match starts with.
+    prefix: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Any,
+    Star,
+    DoubleStar,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternError {
+    message: &'static str,
+    offset: usize,
+}
+
+impl PatternError {
+    pub fn message(&self) -> &str {
+        self.message
+    }
+
+    /// Byte offset in the pattern text.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+const MAX_ALTERNATIVES: usize = 1024;
+
+impl Pattern {
+    /// Parses `pattern` with `.` as the segment separator.
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        Self::with_separator(pattern, ".")
+    }
+
+    pub fn with_separator(pattern: &str, separator: &str) -> Result<Self, PatternError> {
+        let mut parser = Parser { text: pattern, pos: 0 };
+        let alternatives = parser.sequence(false)?;
+        if parser.pos < pattern.len() {
+            return Err(PatternError { message: "unmatched '}'", offset: parser.pos });
+        }
+        let alternatives = alternatives.into_iter().map(normalize).collect::<Vec<Vec<Piece>>>();
+        let prefix = alternatives
+            .iter()
+            .map(|pieces| match pieces.first() {
+                Some(Piece::Literal(s)) => s.as_str(),
+                _ => "",
+            })
+            .reduce(common_prefix)
+            .unwrap_or("")
+            .to_owned();
+        Ok(Pattern { alternatives, separator: separator.to_owned(), prefix })
+    }
+
+    /// The literal text every matching key starts with.
+    pub fn literal_prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        key.starts_with(&self.prefix)
+            && self.alternatives.iter().any(|pieces| self.match_pieces(pieces, key))
+    }
+
+    /// Matches by dynamic programming over the positions in `pieces` and `key`, so that a
+    /// pattern with many stars takes O(pieces × key) steps rather than backtracking.
+    fn match_pieces(&self, pieces: &[Piece], key: &str) -> bool {
+        let width = pieces.len() + 1;
+        // `matched[j * width + i]`: whether `pieces[i..]` matches `key[j..]`.
+        let mut matched = vec![false; (key.len() + 1) * width];
+        for j in (0..=key.len()).rev().filter(|&j| key.is_char_boundary(j)) {
+            let row = j * width;
+            let next = key[j..].chars().next().map(|c| j + c.len_utf8());
+            let at_separator = self.at_separator(&key[j..]);
+            matched[row + pieces.len()] = j == key.len();
+            for (i, piece) in pieces.iter().enumerate().rev() {
+                matched[row + i] = match piece {
+                    Piece::Literal(s) => {
+                        key[j..].starts_with(s.as_str()) && matched[(j + s.len()) * width + i + 1]
+                    }
+                    Piece::Any => {
+                        next.is_some_and(|next| !at_separator && matched[next * width + i + 1])
+                    }
+                    Piece::Star => {
+                        matched[row + i + 1]
+                            || next.is_some_and(|next| !at_separator && matched[next * width + i])
+                    }
+                    Piece::DoubleStar => {
+                        matched[row + i + 1] || next.is_some_and(|next| matched[next * width + i])
+                    }
+                };
+            }
+        }
+        matched[0]
+    }
+
+    fn at_separator(&self, text: &str) -> bool {
+        !self.separator.is_empty() && text.starts_with(&self.separator)
+    }
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map(|((i, _), _)| i)
+        .unwrap_or(a.len().min(b.len()));
+    &a[..len]
+}
+
+/// Merges adjacent literals so that the first piece holds the whole literal prefix, and runs of
+/// stars into one (a `**` anywhere in the run makes it a `**`).
+fn normalize(pieces: Vec<Piece>) -> Vec<Piece> {
+    let mut out: Vec<Piece> = Vec::with_capacity(pieces.len());
+    for piece in pieces {
+        match (out.last_mut(), piece) {
+            (Some(Piece::Literal(prev)), Piece::Literal(s)) => prev.push_str(&s),
+            (
+                Some(prev @ (Piece::Star | Piece::DoubleStar)),
+                star @ (Piece::Star | Piece::DoubleStar),
+            ) => {
+                if star == Piece::DoubleStar {
+                    *prev = star;
+                }
+            }
+            (_, Piece::Literal(s)) if s.is_empty() => {}
+            (_, piece) => out.push(piece),
+        }
+    }
+    out
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// Parses until the end of the text (or a `,`/`}` when `in_braces`), returning the expanded
+    /// alternatives.
+    fn sequence(&mut self, in_braces: bool) -> Result<Vec<Vec<Piece>>, PatternError> {
+        let mut alternatives = vec![Vec::new()];
+        let push = |alternatives: &mut Vec<Vec<Piece>>, piece: Piece| {
+            for pieces in alternatives.iter_mut() {
+                pieces.push(piece.clone());
+            }
+        };
+        while let Some(c) = self.text[self.pos..].chars().next() {
+            match c {
+                ',' | '}' if in_braces => break,
+                '}' => break,
+                '*' => {
+                    self.pos += 1;
+                    if self.text[self.pos..].starts_with('*') {
+                        self.pos += 1;
+                        push(&mut alternatives, Piece::DoubleStar);
+                    } else {
+                        push(&mut alternatives, Piece::Star);
+                    }
+                }
+                '?' => {
+                    self.pos += 1;
+                    push(&mut alternatives, Piece::Any);
+                }
+                '{' => {
+                    let open = self.pos;
+                    self.pos += 1;
+                    let mut choices = Vec::new();
+                    loop {
+                        choices.extend(self.sequence(true)?);
+                        match self.text[self.pos..].chars().next() {
+                            Some(',') => self.pos += 1,
+                            Some('}') => {
+                                self.pos += 1;
+                                break;
+                            }
+                            _ => {
+                                return Err(PatternError { message: "unclosed '{'", offset: open });
+                            }
+                        }
+                    }
+                    if alternatives.len() * choices.len() > MAX_ALTERNATIVES {
+                        return Err(PatternError {
+                            message: "too many alternatives",
+                            offset: open,
+                        });
+                    }
+                    alternatives = alternatives
+                        .iter()
+                        .flat_map(|pieces| {
+                            choices.iter().map(move |choice| {
+                                let mut pieces = pieces.clone();
+                                pieces.extend(choice.iter().cloned());
+                                pieces
+                            })
+                        })
+                        .collect();
+                }
+                '\\' => {
+                    let Some(escaped) = self.text[self.pos + 1..].chars().next() else {
+                        return Err(PatternError { message: "trailing '\\'", offset: self.pos });
+                    };
+                    self.pos += 1 + escaped.len_utf8();
+                    push(&mut alternatives, Piece::Literal(escaped.to_string()));
+                }
+                _ => {
+                    self.pos += c.len_utf8();
+                    push(&mut alternatives, Piece::Literal(c.to_string()));
+                }
+            }
+        }
+        Ok(alternatives)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(pattern: &str, key: &str) -> bool {
+        Pattern::new(pattern).unwrap().matches(key)
+    }
+
+    #[test]
+    fn star_stays_within_segment() {
+        assert!(m("services.*.port", "services.web.port"));
+        assert!(m("services.*.port", "services..port"));
+        assert!(!m("services.*.port", "services.web.http.port"));
+        assert!(m("*", "abc"));
+        assert!(!m("*", "a.b"));
+    }
+
+    #[test]
+    fn double_star_crosses_segments() {
+        assert!(m("services.**.port", "services.web.port"));
+        assert!(m("services.**.port", "services.web.http.port"));
+        assert!(!m("services.**.port", "services.port"));
+        assert!(m("**", "a.b.c"));
+        assert!(m("**", ""));
+    }
+
+    #[test]
+    fn alternation() {
+        assert!(m("{web,db}.port", "web.port"));
+        assert!(m("{web,db}.port", "db.port"));
+        assert!(!m("{web,db}.port", "cache.port"));
+        assert!(m("a.{b,c*}.{d,e}", "a.cx.e"));
+        assert!(m("a{,.b}", "a"));
+        assert!(m("a{,.b}", "a.b"));
+    }
+
+    #[test]
+    fn question_mark_and_escape() {
+        assert!(m("a?c", "abc"));
+        assert!(!m("a?c", "a.c"));
+        assert!(m(r"a\*", "a*"));
+        assert!(!m(r"a\*", "ab"));
+    }
+
+    #[test]
+    fn custom_separator() {
+        let p = Pattern::with_separator("a/*/c", "/").unwrap();
+        assert!(p.matches("a/b.x/c"));
+        assert!(!p.matches("a/b/x/c"));
+    }
+
+    #[test]
+    fn literal_prefix() {
+        assert_eq!(Pattern::new("services.*.port").unwrap().literal_prefix(), "services.");
+        assert_eq!(Pattern::new("{ab,ac}.x").unwrap().literal_prefix(), "a");
+        assert_eq!(Pattern::new("*.x").unwrap().literal_prefix(), "");
+    }
+
+    #[test]
+    fn runs_of_stars_are_collapsed() {
+        assert_eq!(
+            Pattern::new("a***b").unwrap().alternatives,
+            [[Piece::Literal("a".to_owned()), Piece::DoubleStar, Piece::Literal("b".to_owned()),]]
+        );
+        assert!(m("a***b", "a.x.b"));
+        assert!(m("a*?*b", "axb"));
+        assert!(!m("a*?*b", "ab"));
+    }
+
+    #[test]
+    fn many_stars_do_not_backtrack() {
+        // Backtracking would try every way of splitting the key between the stars.
+        let key = "a".repeat(1000);
+        assert!(!m(&format!("{}**b", "**a".repeat(30)), &key));
+        assert!(!m(&format!("{}*b", "*a".repeat(30)), &key));
+        assert!(m(&format!("{}**", "**a".repeat(30)), &key));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Pattern::new("a{b").unwrap_err().offset(), 1);
+        assert_eq!(Pattern::new("a}b").unwrap_err().message(), "unmatched '}'");
+        assert_eq!(Pattern::new("a\\").unwrap_err().message(), "trailing '\\'");
+    }
+}
@@ -0,0 +1,117 @@
+/// The groups of every run of keys sharing a prefix, each in first-occurrence order.
+///
+/// In key order, the groups whose keys start with a prefix form a run, which is a node of the trie
+/// of the keys. So there are fewer than twice as many runs as groups, and a group is in at most two
+/// runs more than its key is long: all runs together take about as much room as the keys.
+pub(crate) struct PrefixRuns {
+    /// The first and the last (exclusive) group of each run, by first group and then by length,
+    /// longest first.
+    bounds: Vec<(u32, u32)>,
+    /// Where the groups of each run start in `groups`, followed by `groups.len()`.
+    starts: Vec<u32>,
+    groups: Vec<u32>,
+}
+
+impl PrefixRuns {
+    /// Builds the runs of the groups whose keys are `keys`, in key order, from the order the
+    /// groups first occur in.
+    pub(crate) fn new(keys: &[&str], group_order: &[u32]) -> Self {
+        // A run ends where the prefix shared with the next key gets shorter than the one it was
+        // opened for, so a stack of (shared length, first group) finds them all.
+        let n = keys.len();
+        let mut bounds = Vec::new();
+        let mut open = vec![(0, 0)];
+        for g in 1..=n {
+            let shared = match g < n {
+                true => common_prefix_len(keys[g - 1], keys[g]),
+                false => 0,
+            };
+            bounds.push(((g - 1) as u32, g as u32));
+            let mut first = g - 1;
+            while let Some(&(len, start)) = open.last()
+                && len > shared
+            {
+                open.pop();
+                bounds.push((start as u32, g as u32));
+                first = start;
+            }
+            if open.last().is_none_or(|&(len, _)| len < shared) {
+                open.push((shared, first));
+            }
+        }
+        bounds.push((0, n as u32));
+        bounds.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        bounds.dedup();
+
+        // Going through the runs by first group, each run is nested in the ones still open.
+        let mut parent = vec![None::<usize>; bounds.len()];
+        let mut innermost = vec![None::<usize>; n];
+        let mut open = Vec::<usize>::new();
+        let mut next_run = 0;
+        for (g, innermost) in innermost.iter_mut().enumerate() {
+            while open.last().is_some_and(|&r| bounds[r].1 as usize <= g) {
+                open.pop();
+            }
+            while bounds.get(next_run).is_some_and(|&(first, _)| first as usize == g) {
+                parent[next_run] = open.last().copied();
+                open.push(next_run);
+                next_run += 1;
+            }
+            *innermost = open.last().copied();
+        }
+
+        let mut starts = Vec::with_capacity(bounds.len() + 1);
+        starts.push(0u32);
+        for &(first, last) in &bounds {
+            starts.push(starts.last().unwrap() + (last - first));
+        }
+        let mut next = starts.clone();
+        let mut groups = vec![0u32; *starts.last().unwrap() as usize];
+        for &g in group_order {
+            let mut run = innermost[g as usize];
+            while let Some(r) = run {
+                groups[next[r] as usize] = g;
+                next[r] += 1;
+                run = parent[r];
+            }
+        }
+        PrefixRuns { bounds, starts, groups }
+    }
+
+    /// Returns the groups from `first` to `last` (exclusive) in first-occurrence order, if they
+    /// are the run of a prefix. Costs a binary search, O(log n) for n groups.
+    pub(crate) fn get(&self, first: usize, last: usize) -> Option<&[u32]> {
+        let (first, last) = (first as u32, last as u32);
+        let run = self.bounds.binary_search_by(|&(f, l)| f.cmp(&first).then(last.cmp(&l))).ok()?;
+        Some(&self.groups[self.starts[run] as usize..self.starts[run + 1] as usize])
+    }
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_prefix_has_a_run() {
+        let keys = ["a", "a.b", "a.b.c", "a.bc", "a.c", "b", "ba", "c.x", "c.y"];
+        let group_order = [8, 2, 0, 5, 1, 7, 3, 6, 4];
+        let runs = PrefixRuns::new(&keys, &group_order);
+        for key in keys {
+            for end in 0..=key.len() {
+                let prefix = &key[..end];
+                let first = keys.partition_point(|k| *k < prefix);
+                let last = keys.partition_point(|k| k.starts_with(prefix) || *k < prefix);
+                let expected = group_order
+                    .iter()
+                    .copied()
+                    .filter(|&g| (first..last).contains(&(g as usize)))
+                    .collect::<Vec<_>>();
+                assert_eq!(runs.get(first, last), Some(&expected[..]), "{prefix:?}");
+            }
+        }
+    }
+}
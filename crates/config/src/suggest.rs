@@ -0,0 +1,85 @@
+use crate::{Config, KeyGroups};
+
+impl Config<'_> {
+    /// Returns up to `limit` existing keys close to `key`, nearest first, for "did you mean"
+    /// hints.
+    ///
+    /// Closeness is the edit distance (insertions, deletions, substitutions and transpositions of
+    /// adjacent characters); keys further than a third of the length of `key` (at least 2) are
+    /// not considered similar. Ties keep first-occurrence order.
+    pub fn similar_keys(&self, key: &str, limit: usize) -> Vec<&str> {
+        let key_chars = key.chars().collect::<Vec<_>>();
+        let max_distance = (key_chars.len() / 3).max(2);
+        let mut candidates = KeyGroups::new(self, 0, self.sorted_indices.len())
+            .filter_map(|(candidate, _)| {
+                let distance = edit_distance(&key_chars, candidate, max_distance)?;
+                (distance > 0).then_some((distance, candidate))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|&(distance, _)| distance); // stable: ties keep first occurrence
+        candidates.into_iter().take(limit).map(|(_, candidate)| candidate).collect()
+    }
+}
+
+/// Optimal string alignment distance between `a` and `b`, or `None` if it exceeds `max`.
+fn edit_distance(a: &[char], b: &str, max: usize) -> Option<usize> {
+    let b = b.chars().collect::<Vec<_>>();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    // Three rolling rows: i-2, i-1 and i.
+    let mut prev2 = vec![0; b.len() + 1];
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut cur = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        cur[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev2[j - 2] + 1);
+            }
+        }
+        if cur.iter().min().is_some_and(|&min| min > max) {
+            return None;
+        }
+        std::mem::swap(&mut prev2, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    Some(prev[b.len()]).filter(|&d| d <= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(a: &str, b: &str) -> Option<usize> {
+        edit_distance(&a.chars().collect::<Vec<_>>(), b, 10)
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(d("", ""), Some(0));
+        assert_eq!(d("abc", "abc"), Some(0));
+        assert_eq!(d("abc", "abd"), Some(1));
+        assert_eq!(d("abc", "acb"), Some(1));
+        assert_eq!(d("abc", "ab"), Some(1));
+        assert_eq!(d("", "abc"), Some(3));
+        assert_eq!(d("kitten", "sitting"), Some(3));
+        assert_eq!(edit_distance(&['a'], "abcd", 2), None);
+    }
+
+    #[test]
+    fn suggestions() {
+        use crate::Value;
+        let config = Config::from_kv_entries([
+            ("server.port", Value::Integer("1")),
+            ("server.host", Value::String("h")),
+            ("server.prot", Value::Integer("2")),
+            ("client.port", Value::Integer("3")),
+        ]);
+        assert_eq!(config.similar_keys("server.prot", 5), ["server.port", "server.host"]);
+        assert_eq!(config.similar_keys("sever.port", 1), ["server.port"]);
+        assert!(config.similar_keys("database", 5).is_empty());
+    }
+}
@@ -1,37 +1,126 @@
-use std::ops::Index;
+use hashbrown::HashTable;
+use mical_cli_syntax::TextRange;
+use std::{hash::BuildHasher, hash::RandomState, ops::Index};
 
-pub(crate) struct TextArena {
+/// Text of a config: slices of the evaluated source, if it was given, followed by copies of
+/// everything else in one buffer.
+pub(crate) struct TextArena<'s> {
+    source: &'s str,
     buffer: String,
 }
 
+/// A slice of a [`TextArena`]. Offsets below the length of the source point into it, the rest
+/// into the buffer.
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct TextId {
-    offset: u32,
-    length: u32,
+    offset: usize,
+    length: usize,
 }
 
-impl TextArena {
+impl<'s> TextArena<'s> {
     #[inline]
     pub(crate) fn new() -> Self {
-        Self { buffer: String::new() }
+        Self::with_source("")
+    }
+
+    /// Creates an arena that can borrow slices of `source` instead of copying them.
+    #[inline]
+    pub(crate) fn with_source(source: &'s str) -> Self {
+        Self { source, buffer: String::new() }
     }
 
     #[inline]
     pub(crate) fn alloc(&mut self, text: &str) -> TextId {
-        let offset = self.buffer.len() as u32;
-        let length = text.len() as u32;
+        let offset = self.source.len() + self.buffer.len();
         self.buffer.push_str(text);
-        TextId { offset, length }
+        TextId { offset, length: text.len() }
+    }
+
+    /// Borrows `text` from the source if it is the slice at `range`, or copies it.
+    #[inline]
+    pub(crate) fn alloc_at(&mut self, text: &str, range: TextRange) -> TextId {
+        match self.source.get(std::ops::Range::<usize>::from(range)) {
+            Some(slice) if slice == text => {
+                TextId { offset: range.start().into(), length: text.len() }
+            }
+            _ => self.alloc(text),
+        }
     }
 }
 
-impl Index<TextId> for TextArena {
+impl Index<TextId> for TextArena<'_> {
     type Output = str;
 
     #[inline]
     fn index(&self, id: TextId) -> &Self::Output {
-        let start = id.offset as usize;
-        let end = start + id.length as usize;
-        &self.buffer[start..end]
+        match id.offset.checked_sub(self.source.len()) {
+            Some(start) => &self.buffer[start..start + id.length],
+            None => &self.source[id.offset..id.offset + id.length],
+        }
+    }
+}
+
+/// Index of a unique full key in the key table of a config.
+pub(crate) type KeyId = u32;
+
+/// Assigns each distinct key one [`KeyId`] while a config is built, so its text is stored once.
+pub(crate) struct Interner {
+    table: HashTable<KeyId>,
+    hasher: RandomState,
+}
+
+impl Interner {
+    pub(crate) fn new() -> Self {
+        Interner { table: HashTable::new(), hasher: RandomState::new() }
+    }
+
+    /// Returns the id of `key` in `keys`, storing it with `alloc` if it is new.
+    pub(crate) fn intern<'s>(
+        &mut self,
+        arena: &mut TextArena<'s>,
+        keys: &mut Vec<TextId>,
+        key: &str,
+        alloc: impl FnOnce(&mut TextArena<'s>, &str) -> TextId,
+    ) -> KeyId {
+        let hash = self.hasher.hash_one(key);
+        if let Some(&id) = self.table.find(hash, |&id| &arena[keys[id as usize]] == key) {
+            return id;
+        }
+        let id = keys.len() as KeyId;
+        keys.push(alloc(arena, key));
+        let hasher = &self.hasher;
+        self.table.insert_unique(hash, id, |&id| hasher.hash_one(&arena[keys[id as usize]]));
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mical_cli_syntax::TextSize;
+
+    #[test]
+    fn borrows_matching_slices() {
+        let source = "key value";
+        let mut arena = TextArena::with_source(source);
+        let range =
+            |start: u32, end: u32| TextRange::new(TextSize::from(start), TextSize::from(end));
+        let borrowed = arena.alloc_at("value", range(4, 9));
+        let copied = arena.alloc_at("other", range(4, 9));
+        assert_eq!(&arena[borrowed], "value");
+        assert!(std::ptr::eq(arena[borrowed].as_ptr(), source[4..].as_ptr()));
+        assert_eq!(&arena[copied], "other");
+        assert_eq!(arena.buffer, "other");
+    }
+
+    #[test]
+    fn interns_each_key_once() {
+        let mut arena = TextArena::new();
+        let mut keys = Vec::new();
+        let mut interner = Interner::new();
+        let ids = ["a", "b", "a", "c", "b"]
+            .map(|key| interner.intern(&mut arena, &mut keys, key, |arena, key| arena.alloc(key)));
+        assert_eq!(ids, [0, 1, 0, 2, 1]);
+        assert_eq!(arena.buffer, "abc");
     }
 }
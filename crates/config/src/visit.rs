@@ -0,0 +1,74 @@
+use crate::{
+    Directive, DuplicatePolicy, Error, Value,
+    eval::{self, Evaluated, Sink, Text},
+};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{SyntaxError, TextRange};
+use std::ops::ControlFlow;
+
+/// Receives a config entry by entry as [`visit`] evaluates it.
+///
+/// Entries come in source order and before duplicates are resolved: a key may come more than
+/// once, whatever policy the source declares.
+pub trait Visitor {
+    /// Takes an entry with its full key and the range of its key in the source.
+    fn entry(&mut self, key: &str, value: Value<'_>, range: TextRange) -> ControlFlow<()>;
+
+    /// Takes the policy of a `#duplicates` directive.
+    fn duplicates(&mut self, policy: DuplicatePolicy) {
+        let _ = policy;
+    }
+
+    /// Takes each directive, `#duplicates` included, where it comes among the entries.
+    fn directive(&mut self, directive: &Directive<'_>) {
+        let _ = directive;
+    }
+
+    fn syntax_error(&mut self, error: SyntaxError) -> ControlFlow<()> {
+        let _ = error;
+        ControlFlow::Continue(())
+    }
+
+    fn error(&mut self, error: Error) -> ControlFlow<()> {
+        let _ = error;
+        ControlFlow::Continue(())
+    }
+}
+
+/// Parses and evaluates `text` into `visitor` without building a syntax tree or keeping any
+/// entry, so memory does not grow with the size of `text`. Stops soon after `visitor` breaks.
+pub fn visit(text: &str, visitor: &mut impl Visitor) {
+    visit_with_options(text, &ParseOptions::default(), visitor);
+}
+
+/// Same as [`visit`], accepting the extensions `options` turn on and reporting what goes over
+/// their limits instead of evaluating it.
+pub fn visit_with_options(text: &str, options: &ParseOptions, visitor: &mut impl Visitor) {
+    eval::eval_text(text, options, None, VisitorSink(visitor));
+}
+
+struct VisitorSink<'v, V>(&'v mut V);
+
+impl<V: Visitor> Sink for VisitorSink<'_, V> {
+    fn entry(&mut self, key: Text<'_>, range: TextRange, value: Evaluated<'_>) -> ControlFlow<()> {
+        self.0.entry(key.text, value.to_value(), range)
+    }
+
+    fn duplicates(&mut self, policy: DuplicatePolicy) {
+        self.0.duplicates(policy);
+    }
+
+    fn directive(&mut self, directive: &Directive<'_>) {
+        self.0.directive(directive);
+    }
+
+    fn metadata(&mut self, _key: &str, _value: Value<'_>) {}
+
+    fn error(&mut self, error: Error) -> ControlFlow<()> {
+        self.0.error(error)
+    }
+
+    fn syntax_error(&mut self, error: SyntaxError) -> ControlFlow<()> {
+        self.0.syntax_error(error)
+    }
+}
@@ -0,0 +1,99 @@
+use mical_cli_config::{Change, ChangeKind, Config, JsonView, Value};
+use mical_cli_syntax::{
+    SyntaxNode,
+    ast::{AstNode, SourceFile},
+};
+
+fn eval(source: &str) -> Config<'static> {
+    let (green, _) = mical_cli_parser::parse(mical_cli_lexer::tokenize(source));
+    let source_file = SourceFile::cast(SyntaxNode::new_root(green)).unwrap();
+    Config::from_source_file(source_file).0
+}
+
+#[test]
+fn prefix_block_restructuring_is_invisible() {
+    let old = eval("server.host localhost\nserver.port 8080\n# comment\n");
+    let new = eval("server. {\n  host   localhost\n  port 0x1F90\n}\n");
+    assert!(old.diff(&new).is_empty());
+}
+
+#[test]
+fn added_removed_and_changed() {
+    let old = eval("a 1\nb 2\nc 3\n");
+    let new = eval("a 1\nc 4\nd 5\n");
+    assert_eq!(
+        old.diff(&new).changes,
+        [
+            Change::Removed { key: "b", old: vec![Value::Integer("2")] },
+            Change::Changed {
+                key: "c",
+                kind: ChangeKind::Value,
+                old: vec![Value::Integer("3")],
+                new: vec![Value::Integer("4")],
+            },
+            Change::Added { key: "d", new: vec![Value::Integer("5")] },
+        ]
+    );
+}
+
+#[test]
+fn type_change() {
+    let old = eval("port \"42\"\n");
+    let new = eval("port 42\n");
+    let diff = old.diff(&new);
+    assert_eq!(
+        diff.changes,
+        [Change::Changed {
+            key: "port",
+            kind: ChangeKind::Type,
+            old: vec![Value::String("42")],
+            new: vec![Value::Integer("42")],
+        }]
+    );
+    assert_eq!(diff.to_string(), "~ port: \"42\" -> 42 (type changed)\n");
+}
+
+#[test]
+fn duplicate_multiplicity_and_order() {
+    let old = eval("tag a\ntag b\nid x\nid y\n");
+    let new = eval("tag b\ntag a\nid x\n");
+    let kinds = old
+        .diff(&new)
+        .changes
+        .iter()
+        .map(|c| match c {
+            Change::Changed { key, kind, .. } => (*key, *kind),
+            _ => panic!("unexpected change: {c:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, [("tag", ChangeKind::Order), ("id", ChangeKind::Multiplicity)]);
+}
+
+#[test]
+fn json_output() {
+    let old = eval("a 1\nb x\n");
+    let new = eval("a 2\nc true\n");
+    let json = serde_json::to_value(JsonView(&old.diff(&new))).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            { "op": "changed", "key": "a", "kind": "value", "old": [1], "new": [2] },
+            { "op": "removed", "key": "b", "old": ["x"] },
+            { "op": "added", "key": "c", "new": [true] },
+        ])
+    );
+}
+
+#[test]
+fn large_and_unparsable_integers() {
+    let old = eval("a 123456789012345678901234567890\nb 1_000_000_000_000_000_000_000_000\n");
+    let new = eval("a 123456789012345678901234567891\nb 1000000000000000000000000\n");
+    let diff = old.diff(&new);
+    assert_eq!(diff.changes.iter().map(Change::key).collect::<Vec<_>>(), ["a"]);
+
+    // Texts that are not integers only equal themselves.
+    let old = Config::from_kv_entries([("a", Value::Integer("0x")), ("b", Value::Integer("0xZZ"))]);
+    let new = Config::from_kv_entries([("a", Value::Integer("0b")), ("b", Value::Integer("0xZZ"))]);
+    let diff = old.diff(&new);
+    assert_eq!(diff.changes.iter().map(Change::key).collect::<Vec<_>>(), ["a"]);
+}
@@ -0,0 +1,182 @@
+use mical_cli_config::{
+    Config, Directive, DirectiveHandler, DirectiveOutput, Error, EvalOptions, Value, Visitor, visit,
+};
+use mical_cli_syntax::{
+    SyntaxNode, TextRange,
+    ast::{AstNode, SourceFile},
+};
+use pretty_assertions::assert_eq;
+use std::ops::ControlFlow;
+
+const SOURCE: &str = "#version 1.2
+#duplicates last-wins
+name app
+server. {
+#set port 8080
+#set bad
+  host localhost
+#unknown
+}
+name again
+";
+
+/// Sets `version` as metadata, adds the entries of `#set <key> <value>`, and rejects the rest.
+#[derive(Default)]
+struct Handler {
+    seen: Vec<String>,
+}
+
+impl DirectiveHandler for Handler {
+    fn directive(&mut self, directive: &Directive<'_>, output: &mut DirectiveOutput) {
+        self.seen.push(format!("{}{}", directive.prefix, directive.name));
+        match directive.name {
+            "version" => output.metadata("version", Value::String(directive.args)),
+            "set" => match directive.args.split_once(' ') {
+                Some((key, value)) => output.entry(key, Value::from_text(value)),
+                None => output.error(directive.args_range, "expected a key and a value"),
+            },
+            "duplicates" => {}
+            _ => output.error(directive.range, format!("unknown directive '{}'", directive.name)),
+        }
+    }
+}
+
+fn range(start: u32, end: u32) -> TextRange {
+    TextRange::new(start.into(), end.into())
+}
+
+fn entries(config: &Config<'_>) -> Vec<(String, String)> {
+    config.entries().map(|(key, value)| (key.to_owned(), format!("{value:?}"))).collect()
+}
+
+/// Evaluates `SOURCE` from the tree and from the parser events, checking that they agree.
+fn eval() -> (Config<'static>, Vec<Error>) {
+    let options = EvalOptions::default();
+    let (green, _) = mical_cli_parser::parse(mical_cli_lexer::tokenize(SOURCE));
+    let source_file = SourceFile::cast(SyntaxNode::new_root(green)).unwrap();
+    let mut tree_handler = Handler::default();
+    let (tree, tree_errors) =
+        Config::from_source_text_with_handler(SOURCE, source_file, &options, &mut tree_handler);
+    let mut handler = Handler::default();
+    let (streamed, _, errors) = Config::parse_with_handler(SOURCE, &options, &mut handler);
+    assert_eq!(entries(&tree), entries(&streamed));
+    assert_eq!(tree.directives().collect::<Vec<_>>(), streamed.directives().collect::<Vec<_>>());
+    assert_eq!(tree_errors, errors);
+    assert_eq!(tree_handler.seen, handler.seen);
+    assert_eq!(
+        handler.seen,
+        ["version", "duplicates", "server.set", "server.set", "server.unknown"]
+    );
+    (Config::from_kv_entries(streamed.entries()), errors)
+}
+
+#[test]
+fn handlers_add_entries_where_the_directive_is() {
+    let (config, _) = eval();
+    assert_eq!(
+        entries(&config),
+        [
+            ("name".to_owned(), "String(\"again\")".to_owned()),
+            ("server.port".to_owned(), "Integer(\"8080\")".to_owned()),
+            ("server.host".to_owned(), "String(\"localhost\")".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn handlers_report_errors() {
+    let (_, errors) = eval();
+    assert_eq!(
+        errors,
+        [
+            Error::InvalidDirective {
+                range: range(74, 77),
+                message: "expected a key and a value".to_owned(),
+            },
+            Error::InvalidDirective {
+                range: range(95, 103),
+                message: "unknown directive 'unknown'".to_owned(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn directives_and_metadata_are_kept() {
+    let mut handler = Handler::default();
+    let (config, _, _) = Config::parse_with_handler(SOURCE, &EvalOptions::default(), &mut handler);
+    assert_eq!(config.metadata("version"), Some(Value::String("1.2")));
+    assert_eq!(config.metadata("name"), None);
+
+    let directives = config.directives().collect::<Vec<_>>();
+    assert_eq!(directives.len(), 5);
+    assert_eq!(
+        directives[2],
+        Directive {
+            name: "set",
+            args: "port 8080",
+            range: range(54, 68),
+            args_range: range(59, 68),
+            prefix: "server.",
+        }
+    );
+    // Without arguments, they are empty at the end of the name.
+    assert_eq!((directives[4].args, directives[4].args_range), ("", range(103, 103)));
+
+    // Without a handler, directives are still recorded.
+    let (config, _, errors) = Config::parse(SOURCE, &EvalOptions::default());
+    assert_eq!(config.directives().count(), 5);
+    assert_eq!(config.metadata("version"), None);
+    assert_eq!(errors, []);
+}
+
+#[test]
+fn visitors_see_directives_among_entries() {
+    #[derive(Default)]
+    struct Record(Vec<String>);
+
+    impl Visitor for Record {
+        fn entry(&mut self, key: &str, _value: Value<'_>, _range: TextRange) -> ControlFlow<()> {
+            self.0.push(key.to_owned());
+            ControlFlow::Continue(())
+        }
+
+        fn directive(&mut self, directive: &Directive<'_>) {
+            self.0.push(format!("#{}", directive.name));
+        }
+    }
+
+    let mut record = Record::default();
+    visit(SOURCE, &mut record);
+    assert_eq!(
+        record.0,
+        ["#version", "#duplicates", "name", "#set", "#set", "server.host", "#unknown", "name"]
+    );
+}
+
+#[test]
+fn emitted_integers_are_checked() {
+    struct Emit;
+
+    impl DirectiveHandler for Emit {
+        fn directive(&mut self, directive: &Directive<'_>, output: &mut DirectiveOutput) {
+            output.entry("good", Value::Integer("-0x1F"));
+            output.entry("bad", Value::Integer(directive.args));
+            output.metadata("bad", Value::Integer(directive.args));
+        }
+    }
+
+    let source = "#emit 12abc\n";
+    let (config, _, errors) =
+        Config::parse_with_handler(source, &EvalOptions::default(), &mut Emit);
+    let message = "'12abc' is not an integer".to_owned();
+    assert_eq!(
+        errors,
+        [
+            Error::InvalidDirective { range: range(0, 11), message: message.clone() },
+            Error::InvalidDirective { range: range(0, 11), message },
+        ]
+    );
+    assert_eq!(entries(&config), [("good".to_owned(), "Integer(\"-0x1F\")".to_owned())]);
+    assert_eq!(config.metadata("bad"), None);
+}
@@ -0,0 +1,87 @@
+use mical_cli_config::{Config, DuplicatePolicy, Error, EvalOptions, Value};
+use mical_cli_syntax::{
+    SyntaxNode, TextRange,
+    ast::{AstNode, SourceFile},
+};
+use pretty_assertions::assert_eq;
+
+fn eval(source: &str, duplicates: DuplicatePolicy) -> (Config<'static>, Vec<Error>) {
+    let (green, _) = mical_cli_parser::parse(mical_cli_lexer::tokenize(source));
+    let source_file = SourceFile::cast(SyntaxNode::new_root(green)).unwrap();
+    Config::from_source_file_with(source_file, &EvalOptions { duplicates, ..Default::default() })
+}
+
+fn entries<'a>(config: &'a Config<'_>) -> Vec<(&'a str, Value<'a>)> {
+    config.entries().collect()
+}
+
+const SOURCE: &str = "a 1\nb x\na 2\na 3\n";
+
+#[test]
+fn keep_all_is_the_default() {
+    let (config, errors) = eval(SOURCE, DuplicatePolicy::default());
+    assert!(errors.is_empty());
+    assert_eq!(
+        entries(&config),
+        [
+            ("a", Value::Integer("1")),
+            ("a", Value::Integer("2")),
+            ("a", Value::Integer("3")),
+            ("b", Value::String("x")),
+        ]
+    );
+}
+
+#[test]
+fn first_wins() {
+    let (config, errors) = eval(SOURCE, DuplicatePolicy::FirstWins);
+    assert!(errors.is_empty());
+    assert_eq!(entries(&config), [("a", Value::Integer("1")), ("b", Value::String("x"))]);
+}
+
+#[test]
+fn last_wins_keeps_the_first_position() {
+    let (config, errors) = eval(SOURCE, DuplicatePolicy::LastWins);
+    assert!(errors.is_empty());
+    assert_eq!(entries(&config), [("a", Value::Integer("3")), ("b", Value::String("x"))]);
+}
+
+#[test]
+fn error_points_at_both_definitions() {
+    let (config, errors) = eval("x. {\n  y 1\n}\nx.y 2\n", DuplicatePolicy::Error);
+    assert_eq!(
+        errors,
+        [Error::DuplicateKey {
+            key: "x.y".to_owned(),
+            range: TextRange::new(13.into(), 16.into()),
+            first: TextRange::new(7.into(), 8.into()),
+        }]
+    );
+    assert_eq!(config.query("x.y").count(), 2);
+}
+
+#[test]
+fn directive_overrides_the_option() {
+    let source = format!("#duplicates last-wins\n{SOURCE}");
+    let (config, errors) = eval(&source, DuplicatePolicy::Error);
+    assert!(errors.is_empty());
+    assert_eq!(config.query("a").collect::<Vec<_>>(), [Value::Integer("3")]);
+
+    let (_, errors) = eval("a 1\n#duplicates error\na 2\n", DuplicatePolicy::KeepAll);
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn invalid_directive() {
+    let (config, errors) = eval("#duplicates newest\na 1\na 2\n", DuplicatePolicy::KeepAll);
+    assert_eq!(
+        errors,
+        [Error::InvalidDirective {
+            range: TextRange::new(12.into(), 18.into()),
+            message: "unknown duplicate policy: 'newest' (supported: keep-all, first-wins, \
+                      last-wins, error)"
+                .to_owned(),
+        }]
+    );
+    assert_eq!(config.query("a").count(), 2);
+}
@@ -0,0 +1,161 @@
+use mical_cli_config::{Config, EnvOverrides, Origin, Value};
+
+fn apply<'a>(config: &mut Config<'_>, vars: &[(&'a str, &'a str)]) {
+    config.apply_env_overrides(&EnvOverrides::new("MYAPP"), vars.iter().copied());
+}
+
+fn all<'a>(config: &'a Config<'_>) -> Vec<(&'a str, Value<'a>)> {
+    config.entries().collect()
+}
+
+#[test]
+fn overrides_existing_key() {
+    let mut config = Config::from_kv_entries([
+        ("server.host", Value::String("localhost")),
+        ("server.port", Value::Integer("8080")),
+    ]);
+    apply(&mut config, &[("MYAPP__SERVER__PORT", "9000")]);
+    assert_eq!(
+        all(&config),
+        [("server.host", Value::String("localhost")), ("server.port", Value::Integer("9000"))]
+    );
+    assert_eq!(
+        config.query_with_origin("server.port").collect::<Vec<_>>(),
+        [(Value::Integer("9000"), Origin::Env("MYAPP__SERVER__PORT"))]
+    );
+    assert_eq!(
+        config.query_with_origin("server.host").collect::<Vec<_>>(),
+        [(Value::String("localhost"), Origin::Source)]
+    );
+}
+
+#[test]
+fn ignores_other_prefixes() {
+    let mut config = Config::from_kv_entries([("port", Value::Integer("1"))]);
+    apply(&mut config, &[("OTHER__PORT", "2"), ("MYAPP_PORT", "3"), ("MYAPP__", "4")]);
+    assert_eq!(all(&config), [("port", Value::Integer("1"))]);
+    assert_eq!(config.env_overrides().count(), 0);
+}
+
+#[test]
+fn values_are_typed_like_the_parser() {
+    let mut config = Config::from_kv_entries(std::iter::empty());
+    apply(
+        &mut config,
+        &[
+            ("MYAPP__A", "true"),
+            ("MYAPP__B", "-0x1F"),
+            ("MYAPP__C", "10 items"),
+            ("MYAPP__D", "0b12"),
+            ("MYAPP__E", "true story"),
+            ("MYAPP__F", "\"42\""),
+            ("MYAPP__G", ""),
+        ],
+    );
+    assert_eq!(
+        all(&config),
+        [
+            ("a", Value::Bool(true)),
+            ("b", Value::Integer("-0x1F")),
+            ("c", Value::String("10 items")),
+            ("d", Value::String("0b12")),
+            ("e", Value::String("true story")),
+            ("f", Value::String("\"42\"")),
+            ("g", Value::String("")),
+        ]
+    );
+}
+
+#[test]
+fn duplicates_are_replaced_at_first_occurrence() {
+    let mut config = Config::from_kv_entries([
+        ("tag", Value::String("a")),
+        ("other", Value::Bool(false)),
+        ("tag", Value::String("b")),
+    ]);
+    apply(&mut config, &[("MYAPP__TAG", "c")]);
+    assert_eq!(all(&config), [("tag", Value::String("c")), ("other", Value::Bool(false))]);
+}
+
+#[test]
+fn matches_existing_spelling() {
+    let mut config = Config::from_kv_entries([
+        ("maxConn", Value::Integer("1")),
+        ("http-port", Value::Integer("80")),
+    ]);
+    apply(&mut config, &[("MYAPP__MAXCONN", "2"), ("MYAPP__HTTP_PORT", "8080")]);
+    assert_eq!(
+        all(&config),
+        [("maxConn", Value::Integer("2")), ("http-port", Value::Integer("8080"))]
+    );
+}
+
+#[test]
+fn keys_sharing_a_name_are_all_overridden() {
+    let overrides = EnvOverrides::new("MYAPP");
+    assert_eq!(overrides.env_name("a.b"), overrides.env_name("a__b"));
+    assert_eq!(overrides.env_name("max-conn"), overrides.env_name("max_conn"));
+    let mut config = Config::from_kv_entries([
+        ("a.b", Value::Integer("1")),
+        ("max-conn", Value::Integer("2")),
+        ("a__b", Value::Integer("3")),
+        ("max_conn", Value::Integer("4")),
+        ("a.c", Value::Integer("5")),
+    ]);
+    apply(&mut config, &[("MYAPP__A__B", "10"), ("MYAPP__MAX_CONN", "20")]);
+    assert_eq!(
+        all(&config),
+        [
+            ("a.b", Value::Integer("10")),
+            ("max-conn", Value::Integer("20")),
+            ("a__b", Value::Integer("10")),
+            ("max_conn", Value::Integer("20")),
+            ("a.c", Value::Integer("5")),
+        ]
+    );
+    assert_eq!(
+        config.query_with_origin("a__b").collect::<Vec<_>>(),
+        [(Value::Integer("10"), Origin::Env("MYAPP__A__B"))]
+    );
+}
+
+#[test]
+fn new_keys_are_appended_in_name_order() {
+    let mut config = Config::from_kv_entries([("a", Value::Integer("1"))]);
+    apply(&mut config, &[("MYAPP__Z__Y", "z"), ("MYAPP__B", "b")]);
+    assert_eq!(
+        all(&config),
+        [("a", Value::Integer("1")), ("b", Value::String("b")), ("z.y", Value::String("z"))]
+    );
+    assert_eq!(
+        config.env_overrides().collect::<Vec<_>>(),
+        [("b", Value::String("b"), "MYAPP__B"), ("z.y", Value::String("z"), "MYAPP__Z__Y")]
+    );
+}
+
+#[test]
+fn custom_separator() {
+    let overrides = EnvOverrides::with_separator("APP", "/");
+    assert_eq!(overrides.env_name("server/http.port"), "APP__SERVER__HTTP_PORT");
+    let mut config = Config::from_kv_entries([("server/http.port", Value::Integer("80"))]);
+    config.apply_env_overrides(&overrides, [("APP__SERVER__HTTP_PORT", "81"), ("APP__X__Y", "1")]);
+    assert_eq!(
+        all(&config),
+        [("server/http.port", Value::Integer("81")), ("x/y", Value::Integer("1"))]
+    );
+}
+
+#[test]
+fn repeated_application_keeps_origins() {
+    let mut config = Config::from_kv_entries([
+        ("a", Value::Integer("1")),
+        ("b", Value::Integer("2")),
+        ("a", Value::Integer("3")),
+    ]);
+    apply(&mut config, &[("MYAPP__B", "20")]);
+    apply(&mut config, &[("MYAPP__A", "10")]);
+    assert_eq!(
+        config.env_overrides().collect::<Vec<_>>(),
+        [("a", Value::Integer("10"), "MYAPP__A"), ("b", Value::Integer("20"), "MYAPP__B")]
+    );
+}
@@ -0,0 +1,85 @@
+use mical_cli_config::{Config, Error, EvalOptions, Value};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{
+    Limits, SyntaxNode, TextRange,
+    ast::{AstNode, SourceFile},
+};
+use pretty_assertions::assert_eq;
+
+/// Evaluates `source` both from the tree and from the parser events, checking that they agree.
+fn eval(source: &str, limits: Limits) -> (Vec<(String, Value<'static>)>, Vec<Error>) {
+    let options = EvalOptions { parse: ParseOptions::with_limits(limits), ..Default::default() };
+    let (green, _) =
+        mical_cli_parser::parse_with_options(mical_cli_lexer::tokenize(source), &options.parse);
+    let source_file = SourceFile::cast(SyntaxNode::new_root(green)).unwrap();
+    let (tree, tree_errors) = Config::from_source_file_with(source_file, &options);
+    let (streamed, _, errors) = Config::parse(source, &options);
+    assert_eq!(entries(&tree), entries(&streamed));
+    assert_eq!(tree_errors, errors);
+    (entries(&streamed), errors)
+}
+
+fn entries(config: &Config<'_>) -> Vec<(String, Value<'static>)> {
+    config
+        .entries()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::Bool(b) => Value::Bool(b),
+                Value::Integer(text) => Value::Integer(text.to_owned().leak()),
+                Value::String(text) => Value::String(text.to_owned().leak()),
+            };
+            (key.to_owned(), value)
+        })
+        .collect()
+}
+
+fn range(start: u32, end: u32) -> TextRange {
+    TextRange::new(start.into(), end.into())
+}
+
+#[test]
+fn keys_over_the_limit_are_skipped() {
+    let limits = Limits { max_key_len: 5, ..Limits::default() };
+    let (entries, errors) = eval("short 1\nlonger 2\nab. {\n  cde 3\n  cd 4\n}\n", limits);
+    assert_eq!(
+        entries,
+        [("short".to_owned(), Value::Integer("1")), ("ab.cd".to_owned(), Value::Integer("4"))]
+    );
+    assert_eq!(
+        errors,
+        [
+            Error::KeyTooLong { range: range(8, 14), limit: 5 },
+            Error::KeyTooLong { range: range(25, 28), limit: 5 },
+        ]
+    );
+}
+
+#[test]
+fn integers_over_the_limit_are_skipped() {
+    let limits = Limits { max_integer_len: 4, ..Limits::default() };
+    let (entries, errors) = eval("a -123\nb 12345\nc 0x_1\n", limits);
+    assert_eq!(
+        entries,
+        [("a".to_owned(), Value::Integer("-123")), ("c".to_owned(), Value::Integer("0x_1"))]
+    );
+    assert_eq!(errors, [Error::IntegerTooLong { range: range(9, 14), limit: 4 }]);
+}
+
+#[test]
+fn evaluation_stops_at_the_entry_limit() {
+    let limits = Limits { max_entries: 2, ..Limits::default() };
+    let (entries, errors) = eval("a 1\nb. {\n  c 2\n  d 3\n}\ne 4\n", limits);
+    assert_eq!(
+        entries,
+        [("a".to_owned(), Value::Integer("1")), ("b.c".to_owned(), Value::Integer("2"))]
+    );
+    assert_eq!(errors, [Error::TooManyEntries { range: range(17, 18), limit: 2 }]);
+}
+
+#[test]
+fn untrusted_limits_accept_ordinary_files() {
+    let source = "name app\nserver. {\n  host localhost\n  port 8080\n}\n";
+    let (entries, errors) = eval(source, Limits::untrusted());
+    assert_eq!(entries.len(), 3);
+    assert_eq!(errors, []);
+}
@@ -0,0 +1,86 @@
+use mical_cli_config::{Child, Config, Value};
+
+fn config() -> Config<'static> {
+    Config::from_kv_entries([
+        ("server", Value::String("main")),
+        ("server.port", Value::Integer("80")),
+        ("server.host", Value::String("localhost")),
+        ("serverless.port", Value::Integer("1")),
+        ("server-b.port", Value::Integer("2")),
+        ("server.tls.cert", Value::String("a.pem")),
+        ("server.port", Value::Integer("81")),
+        ("log", Value::Bool(true)),
+    ])
+}
+
+fn child(segment: &str, values: usize, descendants: usize) -> Child<'_> {
+    Child { segment, values, descendants }
+}
+
+#[test]
+fn children_of_root() {
+    let config = config();
+    assert_eq!(
+        config.namespace(".").children(""),
+        [
+            child("log", 1, 0),
+            child("server", 1, 4),
+            child("server-b", 0, 1),
+            child("serverless", 0, 1),
+        ]
+    );
+}
+
+#[test]
+fn children_match_whole_segments() {
+    let config = config();
+    let ns = config.namespace(".");
+    assert_eq!(
+        ns.children("server"),
+        [child("host", 1, 0), child("port", 2, 0), child("tls", 0, 1)]
+    );
+    assert_eq!(ns.children("server.tls"), [child("cert", 1, 0)]);
+    assert!(ns.children("server.port").is_empty());
+    assert!(ns.children("serv").is_empty());
+}
+
+#[test]
+fn subtree_strips_the_path() {
+    let config = config();
+    let ns = config.namespace(".");
+    assert_eq!(
+        ns.subtree("server").collect::<Vec<_>>(),
+        [
+            ("port", Value::Integer("80")),
+            ("port", Value::Integer("81")),
+            ("host", Value::String("localhost")),
+            ("tls.cert", Value::String("a.pem")),
+        ]
+    );
+    assert_eq!(ns.subtree("").count(), 8);
+    assert_eq!(ns.subtree("nothing").count(), 0);
+}
+
+#[test]
+fn leaves() {
+    let config = config();
+    let ns = config.namespace(".");
+    assert!(ns.is_leaf("server.port"));
+    assert!(ns.is_leaf("log"));
+    assert!(!ns.is_leaf("server"), "has a value but also children");
+    assert!(!ns.is_leaf("server.tls"), "has no value");
+    assert!(!ns.is_leaf("missing"));
+}
+
+#[test]
+fn custom_separator() {
+    let config = Config::from_kv_entries([
+        ("a::b", Value::Integer("1")),
+        ("a::c::d", Value::Integer("2")),
+        ("a.b", Value::Integer("3")),
+    ]);
+    let ns = config.namespace("::");
+    assert_eq!(ns.children(""), [child("a", 0, 2), child("a.b", 1, 0)]);
+    assert_eq!(ns.children("a"), [child("b", 1, 0), child("c", 0, 1)]);
+    assert_eq!(ns.subtree("a::c").collect::<Vec<_>>(), [("d", Value::Integer("2"))]);
+}
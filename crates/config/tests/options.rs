@@ -0,0 +1,72 @@
+use mical_cli_config::{Config, EvalOptions, Value};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{
+    SyntaxNode,
+    ast::{AstNode, SourceFile},
+};
+use pretty_assertions::assert_eq;
+use std::num::NonZeroU32;
+
+/// Evaluates `source` both from the tree and from the parser events, checking that they agree
+/// and that there are no errors.
+fn eval(source: &str, parse: ParseOptions) -> Vec<(String, String)> {
+    let options = EvalOptions { parse, ..Default::default() };
+    let (green, syntax_errors) =
+        mical_cli_parser::parse_with_options(mical_cli_lexer::tokenize(source), &parse);
+    assert_eq!(syntax_errors, []);
+    let source_file = SourceFile::cast(SyntaxNode::new_root(green)).unwrap();
+    let (tree, tree_errors) = Config::from_source_text(source, source_file, &options);
+    let (streamed, syntax_errors, errors) = Config::parse(source, &options);
+    assert_eq!((syntax_errors, tree_errors, &errors), (vec![], vec![], &vec![]));
+    assert_eq!(entries(&tree), entries(&streamed));
+    entries(&streamed)
+}
+
+fn entries(config: &Config<'_>) -> Vec<(String, String)> {
+    config
+        .entries()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::Bool(b) => b.to_string(),
+                Value::Integer(text) | Value::String(text) => text.to_owned(),
+            };
+            (key.to_owned(), value)
+        })
+        .collect()
+}
+
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|&(key, value)| (key.to_owned(), value.to_owned())).collect()
+}
+
+#[test]
+fn inline_comments_are_not_part_of_values() {
+    let options = ParseOptions { inline_comments: true, ..Default::default() };
+    let source = "a some text  # note\nb 0x1F # n\nc 'x' # n\nd |- # n\n  line\ne # kept\n";
+    assert_eq!(
+        eval(source, options),
+        pairs(&[("a", "some text"), ("b", "0x1F"), ("c", "x"), ("d", "line"), ("e", "# kept")])
+    );
+}
+
+#[test]
+fn tab_indented_block_strings() {
+    let options = ParseOptions { tab_width: NonZeroU32::new(4), ..Default::default() };
+    let source = "a. {\n\tb |\n\t\tone\n\t\t\ttwo\n\n\t\tthree\n\tc 1\n}\n";
+    assert_eq!(eval(source, options), pairs(&[("a.b", "one\n\ttwo\n\nthree\n"), ("a.c", "1")]));
+}
+
+#[test]
+fn continued_lines_are_joined() {
+    let options = ParseOptions { line_continuation: true, ..Default::default() };
+    let source = "a. {\n  b first \\\n      second \\\n  third\\\n\n  c x\\y\n}\n";
+    assert_eq!(eval(source, options), pairs(&[("a.b", "first second third"), ("a.c", "x\\y")]));
+}
+
+#[test]
+fn continued_lines_with_comments() {
+    let options =
+        ParseOptions { line_continuation: true, inline_comments: true, ..Default::default() };
+    let source = "a one \\\n  two # note \\\nb 2\n";
+    assert_eq!(eval(source, options), pairs(&[("a", "one two"), ("b", "2")]));
+}
@@ -1,7 +1,7 @@
 use mical_cli_config::{Config, Value};
 use proptest::{prelude::*, property_test};
 
-fn q<'a>(config: &'a Config, key: &str) -> Vec<Value<'a>> {
+fn q<'a>(config: &'a Config<'_>, key: &str) -> Vec<Value<'a>> {
     config.query(key).collect()
 }
 
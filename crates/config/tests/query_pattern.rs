@@ -0,0 +1,64 @@
+use mical_cli_config::{Config, Pattern, Value};
+use proptest::{prelude::*, property_test};
+
+fn qm<'a>(config: &'a Config<'_>, pattern: &'a Pattern) -> Vec<(&'a str, Value<'a>)> {
+    config.query_pattern(pattern).collect()
+}
+
+#[test]
+fn one_field_across_services() {
+    let config = Config::from_kv_entries([
+        ("services.web.port", Value::Integer("80")),
+        ("services.web.host", Value::String("web")),
+        ("services.db.port", Value::Integer("5432")),
+        ("services.db.replica.port", Value::Integer("5433")),
+        ("serviceless.port", Value::Integer("1")),
+    ]);
+    let pattern = Pattern::new("services.*.port").unwrap();
+    assert_eq!(
+        qm(&config, &pattern),
+        [("services.web.port", Value::Integer("80")), ("services.db.port", Value::Integer("5432"))]
+    );
+    let pattern = Pattern::new("services.**.port").unwrap();
+    assert_eq!(qm(&config, &pattern).len(), 3);
+}
+
+#[test]
+fn duplicates_grouped_by_first_occurrence() {
+    let config = Config::from_kv_entries([
+        ("b.x", Value::String("1")),
+        ("a.x", Value::String("2")),
+        ("b.x", Value::String("3")),
+    ]);
+    let pattern = Pattern::new("{a,b}.x").unwrap();
+    assert_eq!(
+        qm(&config, &pattern),
+        [("b.x", Value::String("1")), ("b.x", Value::String("3")), ("a.x", Value::String("2"))]
+    );
+}
+
+#[property_test]
+fn matches_linear_scan(
+    #[strategy = prop::collection::vec(("[ab.]{0,5}", "[a-z]{0,3}"), 0..40)] entries: Vec<(
+        String,
+        String,
+    )>,
+    #[strategy = "([ab.?]|\\*|\\*\\*|\\{a,b\\.\\}){0,4}"] pattern: String,
+) {
+    let config = Config::from_kv_entries(
+        entries.iter().map(|(k, v)| (k.as_str(), Value::String(v.as_str()))),
+    );
+    let pattern = Pattern::new(&pattern).unwrap();
+    let mut expected = Vec::<(&str, Vec<&str>)>::new();
+    for (k, v) in entries.iter().filter(|(k, _)| pattern.matches(k)) {
+        match expected.iter_mut().find(|(key, _)| key == k) {
+            Some((_, values)) => values.push(v),
+            None => expected.push((k, vec![v])),
+        }
+    }
+    let expected = expected
+        .into_iter()
+        .flat_map(|(k, vs)| vs.into_iter().map(move |v| (k, Value::String(v))))
+        .collect::<Vec<_>>();
+    prop_assert_eq!(qm(&config, &pattern), expected);
+}
@@ -1,7 +1,7 @@
 use mical_cli_config::{Config, Value};
 use proptest::{prelude::*, property_test};
 
-fn qp<'a>(config: &'a Config, prefix: &str) -> Vec<(&'a str, Value<'a>)> {
+fn qp<'a>(config: &'a Config<'_>, prefix: &str) -> Vec<(&'a str, Value<'a>)> {
     config.query_prefix(prefix).collect()
 }
 
@@ -205,6 +205,24 @@ fn many_overrides_of_single_key_among_others() {
     assert_eq!(result.iter().filter(|(k, _)| *k == "server.log").count(), 50);
 }
 
+#[test]
+fn narrow_prefix_in_large_config_keeps_first_occurrence_order() {
+    // Keys sort in the opposite order to their first occurrence.
+    let keys = (0..1000).rev().map(|i| format!("k{i:04}")).collect::<Vec<_>>();
+    let mut entries = keys.iter().map(|k| (k.as_str(), Value::Integer("1"))).collect::<Vec<_>>();
+    entries.push(("k0998", Value::Integer("2")));
+    let config = Config::from_kv_entries(entries);
+    let expected_keys = (990..1000).rev().map(|i| format!("k{i:04}")).collect::<Vec<_>>();
+    let mut expected = Vec::new();
+    for key in &expected_keys {
+        expected.push((key.as_str(), Value::Integer("1")));
+        if key == "k0998" {
+            expected.push((key.as_str(), Value::Integer("2")));
+        }
+    }
+    assert_eq!(qp(&config, "k099"), expected);
+}
+
 fn reference_query_prefix<'a>(
     entries: &'a [(String, String)],
     prefix: &str,
@@ -504,6 +504,144 @@ fn block_string_clip_multi_trailing() {
     utils::assert_json_output("block-string-clip-multi-trailing", source, expected_json);
 }
 #[test]
+fn block_string_explicit_indent_before_chomp() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-before-chomp/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-before-chomp/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-before-chomp", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-before-chomp", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_folded_clip_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-clip-exact/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-folded-clip-exact/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-clip-exact", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-folded-clip-exact", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_folded_clip_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-clip-extra/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-folded-clip-extra/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-clip-extra", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-folded-clip-extra", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_folded_keep_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-keep-exact/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-folded-keep-exact/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-keep-exact", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-folded-keep-exact", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_folded_keep_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-keep-extra/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-folded-keep-extra/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-keep-extra", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-folded-keep-extra", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_folded_strip_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-strip-exact/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-folded-strip-exact/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-strip-exact", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-folded-strip-exact", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_folded_strip_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-strip-extra/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-folded-strip-extra/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-strip-extra", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-folded-strip-extra", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_insufficient() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-insufficient/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-insufficient/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-insufficient", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-insufficient", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_invalid() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-invalid/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-invalid/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-invalid", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-invalid", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_leading_lines() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-leading-lines/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-leading-lines/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-leading-lines", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-leading-lines", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_literal_clip_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-clip-exact/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-literal-clip-exact/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-clip-exact", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-literal-clip-exact", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_literal_clip_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-clip-extra/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-literal-clip-extra/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-clip-extra", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-literal-clip-extra", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_literal_keep_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-keep-exact/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-literal-keep-exact/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-keep-exact", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-literal-keep-exact", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_literal_keep_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-keep-extra/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-literal-keep-extra/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-keep-extra", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-literal-keep-extra", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_literal_strip_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-strip-exact/input.mical");
+    let expected_json =
+        include_str!("../../../test-suite/block-string-explicit-indent-literal-strip-exact/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-strip-exact", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-literal-strip-exact", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_literal_strip_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-strip-extra/input.mical");
+    let expected_json =
+        include_str!("../../../test-suite/block-string-explicit-indent-literal-strip-extra/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-strip-extra", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-literal-strip-extra", source, expected_json);
+}
+#[test]
+fn block_string_explicit_indent_nested() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-nested/input.mical");
+    let expected_json = include_str!("../../../test-suite/block-string-explicit-indent-nested/output.json");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-nested", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("block-string-explicit-indent-nested", source, expected_json);
+}
+#[test]
 fn block_string_extra_indent() {
     let source = include_str!("../../../test-suite/block-string-extra-indent/input.mical");
     let expected_json = include_str!("../../../test-suite/block-string-extra-indent/output.json");
@@ -1871,6 +2009,22 @@ fn quoted_string_escape_only() {
     utils::assert_json_output("quoted-string-escape-only", source, expected_json);
 }
 #[test]
+fn quoted_string_invalid_code_point() {
+    let source = include_str!("../../../test-suite/quoted-string-invalid-code-point/input.mical");
+    let expected_json = include_str!("../../../test-suite/quoted-string-invalid-code-point/output.json");
+    let snapshot = utils::make_snapshot("quoted-string-invalid-code-point", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("quoted-string-invalid-code-point", source, expected_json);
+}
+#[test]
+fn quoted_string_malformed_code_point() {
+    let source = include_str!("../../../test-suite/quoted-string-malformed-code-point/input.mical");
+    let expected_json = include_str!("../../../test-suite/quoted-string-malformed-code-point/output.json");
+    let snapshot = utils::make_snapshot("quoted-string-malformed-code-point", source);
+    utils::assert_snapshot!(snapshot);
+    utils::assert_json_output("quoted-string-malformed-code-point", source, expected_json);
+}
+#[test]
 fn quoted_string_single_all_escapes() {
     let source = include_str!("../../../test-suite/quoted-string-single-all-escapes/input.mical");
     let expected_json = include_str!("../../../test-suite/quoted-string-single-all-escapes/output.json");
@@ -0,0 +1,106 @@
+use mical_cli_config::{Config, EvalOptions, Value};
+use mical_cli_syntax::{
+    SyntaxNode,
+    ast::{AstNode, SourceFile},
+};
+use pretty_assertions::assert_eq;
+
+const SOURCE: &str = r#"name app
+port -8080
+title "plain"
+motd "tab\there"
+server. {
+  host localhost
+}
+name again
+"#;
+
+fn parse(source: &str) -> SourceFile {
+    let (green, _) = mical_cli_parser::parse(mical_cli_lexer::tokenize(source));
+    SourceFile::cast(SyntaxNode::new_root(green)).unwrap()
+}
+
+fn is_in_source(text: &str) -> bool {
+    SOURCE.as_bytes().as_ptr_range().contains(&text.as_ptr())
+}
+
+fn text<'a>(value: &Value<'a>) -> &'a str {
+    match value {
+        Value::Integer(text) | Value::String(text) => text,
+        Value::Bool(_) => unreachable!(),
+    }
+}
+
+#[test]
+fn borrowed_config_matches_owned_config() {
+    let (owned, owned_errors) = Config::from_source_file(parse(SOURCE));
+    let (borrowed, borrowed_errors) =
+        Config::from_source_text(SOURCE, parse(SOURCE), &EvalOptions::default());
+    assert_eq!(owned_errors, borrowed_errors);
+    assert_eq!(owned.entries().collect::<Vec<_>>(), borrowed.entries().collect::<Vec<_>>());
+}
+
+#[test]
+fn verbatim_text_is_borrowed_from_the_source() {
+    let (config, _) = Config::from_source_text(SOURCE, parse(SOURCE), &EvalOptions::default());
+    let borrowed = config
+        .entries()
+        .map(|(key, value)| (key, is_in_source(key), text(&value), is_in_source(text(&value))))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        borrowed,
+        [
+            ("name", true, "app", true),
+            ("name", true, "again", true),
+            ("port", true, "-8080", true),
+            ("title", true, "plain", true),
+            ("motd", true, "tab\there", false),
+            ("server.host", false, "localhost", true),
+        ]
+    );
+}
+
+#[test]
+fn duplicate_keys_share_their_text() {
+    let (config, _) = Config::from_source_text(SOURCE, parse(SOURCE), &EvalOptions::default());
+    let names = config.entries().filter(|(key, _)| *key == "name").map(|(key, _)| key);
+    let names = names.map(str::as_ptr).collect::<Vec<_>>();
+    assert_eq!(names.len(), 2);
+    assert_eq!(names[0], names[1]);
+}
+
+#[test]
+fn walk_items_resolves_full_keys() {
+    let (source_file, errors) =
+        mical_cli_config::parse_source_file(SOURCE, &mical_cli_parser::ParseOptions::default());
+    assert_eq!(errors, []);
+    let mut items = Vec::new();
+    mical_cli_config::walk_items(source_file.items(), "", &mut |_, _, prefix, key| {
+        items.push((prefix.to_owned(), key.to_owned()));
+    });
+    let items = items.iter().map(|(p, k)| (p.as_str(), k.as_str())).collect::<Vec<_>>();
+    assert_eq!(
+        items,
+        [
+            ("", "name"),
+            ("", "port"),
+            ("", "title"),
+            ("", "motd"),
+            ("", "server."),
+            ("server.", "server.host"),
+            ("", "name"),
+        ]
+    );
+}
+
+#[test]
+fn quoted_reads_back() {
+    for text in ["plain", "tab\there", "it's \"both\"", "back\\slash", "\0\x1b\x7f"] {
+        for quote in ['"', '\''] {
+            let source = format!("k {}\n", mical_cli_config::quoted(text, quote));
+            let (config, _, errors) = Config::parse(&source, &EvalOptions::default());
+            assert_eq!(errors, []);
+            assert_eq!(config.query("k").collect::<Vec<_>>(), [Value::String(text)]);
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use mical_cli_config::{Config, JsonView};
+use mical_cli_config::{Config, Error, EvalOptions, JsonView};
 use mical_cli_syntax::{
-    SyntaxNode,
+    SyntaxError, SyntaxNode,
     ast::{AstNode, SourceFile},
 };
 use std::fmt::Write;
@@ -10,6 +10,7 @@ pub fn make_snapshot(name: &str, source: &str) -> String {
     let syntax = SyntaxNode::new_root(green);
     let source_file = SourceFile::cast(syntax).unwrap();
     let (config, config_errors) = Config::from_source_file(source_file);
+    assert_streaming_matches(source, &parser_errors, &config, &config_errors);
 
     let mut f = String::new();
     fn h(f: &mut String, level: u8, text: &str) {
@@ -54,9 +55,34 @@ pub fn assert_json_output(_name: &str, source: &str, expected_json: &str) {
     let (green, _) = mical_cli_parser::parse(mical_cli_lexer::tokenize(source));
     let syntax = SyntaxNode::new_root(green);
     let source_file = SourceFile::cast(syntax).unwrap();
-    let (config, _) = Config::from_source_file(source_file);
+    let (config, _) = Config::from_source_file(source_file.clone());
     let actual = serde_json::to_string_pretty(&JsonView(&config)).unwrap() + "\n";
     pretty_assertions::assert_eq!(actual, expected_json);
+
+    let (borrowed, _) = Config::from_source_text(source, source_file, &EvalOptions::default());
+    let actual = serde_json::to_string_pretty(&JsonView(&borrowed)).unwrap() + "\n";
+    pretty_assertions::assert_eq!(actual, expected_json);
+
+    let (streamed, _, _) = Config::parse(source, &EvalOptions::default());
+    let actual = serde_json::to_string_pretty(&JsonView(&streamed)).unwrap() + "\n";
+    pretty_assertions::assert_eq!(actual, expected_json);
+}
+
+/// Checks that evaluating `source` without a syntax tree gives the same config and errors.
+fn assert_streaming_matches(
+    source: &str,
+    parser_errors: &[SyntaxError],
+    config: &Config<'_>,
+    config_errors: &[Error],
+) {
+    let (streamed, streamed_parser_errors, streamed_config_errors) =
+        Config::parse(source, &EvalOptions::default());
+    pretty_assertions::assert_eq!(
+        serde_json::to_string_pretty(&JsonView(&streamed)).unwrap(),
+        serde_json::to_string_pretty(&JsonView(config)).unwrap(),
+    );
+    pretty_assertions::assert_eq!(streamed_parser_errors, parser_errors);
+    pretty_assertions::assert_eq!(streamed_config_errors, config_errors);
 }
 
 #[macro_export]
@@ -0,0 +1,114 @@
+use mical_cli_config::{
+    Config, DuplicatePolicy, Error, EvalOptions, JsonView, Value, Visitor, visit,
+};
+use mical_cli_syntax::{
+    SyntaxNode, TextRange,
+    ast::{AstNode, SourceFile},
+};
+use pretty_assertions::assert_eq;
+use proptest::{prelude::*, property_test};
+use std::ops::ControlFlow;
+
+const SOURCE: &str = r#"#duplicates last-wins
+name app
+server. {
+  host localhost
+  port 0b2
+}
+name again
+"#;
+
+/// A value the visitor lends, copied out.
+enum Recorded {
+    Bool(bool),
+    Integer(String),
+    String(String),
+}
+
+#[derive(Default)]
+struct Record {
+    entries: Vec<(String, Recorded)>,
+    policies: Vec<DuplicatePolicy>,
+    errors: Vec<Error>,
+    /// Stop at the first entry with this key.
+    stop_at: Option<&'static str>,
+}
+
+impl Record {
+    fn entries(&self) -> Vec<(&str, Value<'_>)> {
+        let entries = self.entries.iter().map(|(key, value)| {
+            let value = match value {
+                Recorded::Bool(b) => Value::Bool(*b),
+                Recorded::Integer(text) => Value::Integer(text),
+                Recorded::String(text) => Value::String(text),
+            };
+            (key.as_str(), value)
+        });
+        entries.collect()
+    }
+}
+
+impl Visitor for Record {
+    fn entry(&mut self, key: &str, value: Value<'_>, _range: TextRange) -> ControlFlow<()> {
+        let value = match value {
+            Value::Bool(b) => Recorded::Bool(b),
+            Value::Integer(text) => Recorded::Integer(text.to_owned()),
+            Value::String(text) => Recorded::String(text.to_owned()),
+        };
+        self.entries.push((key.to_owned(), value));
+        if self.stop_at == Some(key) { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+    }
+
+    fn duplicates(&mut self, policy: DuplicatePolicy) {
+        self.policies.push(policy);
+    }
+
+    fn error(&mut self, error: Error) -> ControlFlow<()> {
+        self.errors.push(error);
+        ControlFlow::Continue(())
+    }
+}
+
+#[test]
+fn visits_every_entry_in_source_order() {
+    let mut record = Record::default();
+    visit(SOURCE, &mut record);
+    assert_eq!(
+        record.entries(),
+        [
+            ("name", Value::String("app")),
+            ("server.host", Value::String("localhost")),
+            ("name", Value::String("again")),
+        ]
+    );
+    assert_eq!(record.policies, [DuplicatePolicy::LastWins]);
+    assert_eq!(record.errors.len(), 1);
+}
+
+#[test]
+fn stops_when_the_visitor_breaks() {
+    let mut record = Record { stop_at: Some("server.host"), ..Record::default() };
+    visit(SOURCE, &mut record);
+    assert_eq!(
+        record.entries(),
+        [("name", Value::String("app")), ("server.host", Value::String("localhost")),]
+    );
+    assert!(record.errors.is_empty());
+}
+
+#[property_test]
+fn parse_matches_tree_evaluation(
+    #[strategy = r#"([a-c .{}#"'|>+\-\\0-9x\n ]|true|duplicates){0,60}"#] source: String,
+) {
+    let (green, parser_errors) = mical_cli_parser::parse(mical_cli_lexer::tokenize(&source));
+    let source_file = SourceFile::cast(SyntaxNode::new_root(green)).unwrap();
+    let (config, errors) = Config::from_source_file(source_file);
+    let (streamed, streamed_parser_errors, streamed_errors) =
+        Config::parse(&source, &EvalOptions::default());
+    prop_assert_eq!(
+        serde_json::to_string(&JsonView(&streamed)).unwrap(),
+        serde_json::to_string(&JsonView(&config)).unwrap()
+    );
+    prop_assert_eq!(streamed_parser_errors, parser_errors);
+    prop_assert_eq!(streamed_errors, errors);
+}
@@ -0,0 +1,48 @@
+use crate::Document;
+use mical_cli_config::Config;
+use mical_cli_syntax::{Fix, TextSize};
+
+/// Fixes can uncover or shift other errors, so they are applied in rounds against a fresh parse.
+const MAX_ROUNDS: usize = 8;
+
+impl Document {
+    /// Returns the fixes attached to the syntax and evaluation errors of the document.
+    pub fn error_fixes(&self) -> Vec<Fix> {
+        let (_, config_errors) = Config::from_source_file(self.source_file.clone());
+        let syntax = self.errors.iter().filter_map(|error| error.fix().cloned());
+        let mut fixes =
+            syntax.chain(config_errors.iter().filter_map(|error| error.fix())).collect::<Vec<_>>();
+        fixes.sort_by_key(|fix| fix.edit.indels().first().map(|indel| indel.delete.start()));
+        fixes
+    }
+
+    /// Applies the fixes of [`Document::error_fixes`] until none is left and returns how many were
+    /// applied.
+    pub fn fix_errors(&mut self) -> usize {
+        let mut applied = 0;
+        for _ in 0..MAX_ROUNDS {
+            let mut indels = Vec::new();
+            let mut end = None::<TextSize>;
+            for fix in self.error_fixes() {
+                let (Some(first), Some(last)) =
+                    (fix.edit.indels().first(), fix.edit.indels().last())
+                else {
+                    continue;
+                };
+                // Fixes touching the previous one wait for the next round, where they are
+                // recomputed against the updated text.
+                if end.is_some_and(|end| first.delete.start() <= end) {
+                    continue;
+                }
+                end = Some(last.delete.end());
+                indels.extend(fix.edit.indels().iter().cloned());
+                applied += 1;
+            }
+            if indels.is_empty() {
+                break;
+            }
+            self.apply(indels);
+        }
+        applied
+    }
+}
@@ -0,0 +1,464 @@
+//! Format-preserving editing of MICAL documents.
+//!
+//! A [`Document`] keeps the source text together with its lossless syntax tree. Edits address
+//! entries by their full key, the way the evaluator sees them, and touch only the text they have
+//! to: comments, directives, alignment and block-string layout elsewhere are left as they are.
+
+use core::fmt;
+use mical_cli_config::{Value, Visitor, parse_source_file, visit_with_options, walk_items};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{
+    Indel, SyntaxElement, SyntaxError, SyntaxKind, SyntaxNode, TextEdit, TextRange, TextSize,
+    ast::{self, AstNode},
+};
+use std::ops::ControlFlow;
+
+mod fix;
+mod rename;
+pub use rename::{RenameError, RenameScope};
+mod render;
+mod restructure;
+pub use restructure::RestructureError;
+
+/// Which entries an edit applies to when a key occurs more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Occurrences {
+    #[default]
+    All,
+    First,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetError {
+    /// The document with the new value would not read back to it, or would have more syntax
+    /// errors; it is left unchanged.
+    Unverified,
+}
+
+impl fmt::Display for SetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetError::Unverified => f.write_str("the edited document does not read back the value"),
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemoveError {
+    /// The document without the entries would not read back to the other entries, or would have
+    /// more syntax errors; it is left unchanged.
+    Unverified,
+}
+
+impl fmt::Display for RemoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoveError::Unverified => {
+                f.write_str("the edited document does not read back the other entries")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RemoveError {}
+
+/// A MICAL source text that can be edited by key.
+#[derive(Clone)]
+pub struct Document {
+    text: String,
+    source_file: ast::SourceFile,
+    errors: Vec<SyntaxError>,
+    options: ParseOptions,
+}
+
+/// An entry together with the key prefix of the blocks it is nested in.
+struct EntryInfo {
+    entry: ast::Entry,
+    prefix: String,
+    key: String,
+}
+
+/// A prefix block together with the full prefix it applies, its own key included.
+struct BlockInfo {
+    block: ast::PrefixBlock,
+    prefix: String,
+}
+
+/// Where a new entry goes, see [`Document::insertion_point`].
+struct InsertionPoint {
+    offset: TextSize,
+    /// The indentation of the new line, copied from its neighbours so that tabs stay tabs.
+    indent: String,
+    /// The prefix that applies at `offset`.
+    prefix: String,
+}
+
+impl Document {
+    pub fn parse(text: impl Into<String>) -> Self {
+        Self::parse_with_options(text, &ParseOptions::default())
+    }
+
+    /// Same as [`parse`](Self::parse), accepting the extensions `options` turn on, in the text
+    /// and in every edit of it.
+    pub fn parse_with_options(text: impl Into<String>, options: &ParseOptions) -> Self {
+        let text = text.into();
+        let (source_file, errors) = parse_source_file(&text, options);
+        Document { text, source_file, errors, options: *options }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn source_file(&self) -> &ast::SourceFile {
+        &self.source_file
+    }
+
+    pub fn syntax_errors(&self) -> &[SyntaxError] {
+        &self.errors
+    }
+
+    /// Returns the number of entries with the full key `key`.
+    pub fn count(&self, key: &str) -> usize {
+        self.entries().iter().filter(|info| info.key == key).count()
+    }
+
+    /// Sets the value of `key`.
+    ///
+    /// Existing entries keep their place and only their value text changes. Without one, a new
+    /// entry is added at the end of the most specific prefix block whose prefix starts `key`, or
+    /// at the end of the file. Fails without editing if the result would not evaluate to `value`
+    /// where it was set and to the same entries elsewhere.
+    pub fn set(
+        &mut self,
+        key: &str,
+        value: Value<'_>,
+        occurrences: Occurrences,
+    ) -> Result<TextEdit, SetError> {
+        let entries = self.entries();
+        let targets = select(&entries, key, occurrences);
+        let mut expected = self.values_by_key(&self.text);
+        let rendered = format!("{value:?}");
+        if targets.is_empty() {
+            expected.push((key.to_owned(), rendered));
+            expected.sort_by(|a, b| a.0.cmp(&b.0));
+        } else {
+            let set = expected.iter_mut().filter(|(k, _)| k == key).take(targets.len());
+            set.for_each(|(_, v)| v.clone_from(&rendered));
+        }
+        let indels = if targets.is_empty() {
+            vec![self.insertion(key, &render::value(value, None))]
+        } else {
+            targets
+                .into_iter()
+                .filter_map(|info| {
+                    let old = info.entry.value()?;
+                    let quote = match &old {
+                        ast::Value::QuotedString(s) => {
+                            s.open_quote().and_then(|q| q.text().chars().next())
+                        }
+                        _ => None,
+                    };
+                    let quote = quote.filter(|_| matches!(value, Value::String(_)));
+                    let new = render::value(value.clone(), quote);
+                    Some(self.replacement(old.syntax().text_range(), &new))
+                })
+                .collect()
+        };
+        let edit = TextEdit::new(indels);
+        let mut text = self.text.clone();
+        edit.apply(&mut text);
+        let (source_file, errors) = parse_source_file(&text, &self.options);
+        if errors.len() > self.errors.len() || self.values_by_key(&text) != expected {
+            return Err(SetError::Unverified);
+        }
+        (self.text, self.source_file, self.errors) = (text, source_file, errors);
+        Ok(edit)
+    }
+
+    /// Removes the entries of `key`, each with its whole line and the comment lines directly
+    /// above it. Fails without editing if the result would not evaluate to the other entries.
+    pub fn remove(&mut self, key: &str, occurrences: Occurrences) -> Result<TextEdit, RemoveError> {
+        let entries = self.entries();
+        let targets = select(&entries, key, occurrences);
+        let removed = targets
+            .iter()
+            .filter_map(|info| Some(info.entry.key()?.syntax().text_range()))
+            .collect::<Vec<_>>();
+        let mut expected = self
+            .values(&self.text)
+            .into_iter()
+            .filter(|(_, _, range)| !removed.contains(range))
+            .map(|(key, value, _)| (key, value))
+            .collect::<Vec<_>>();
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        let indels = targets
+            .into_iter()
+            .map(|info| {
+                let entry = info.entry.syntax();
+                let start = attached_comments(entry)
+                    .map_or(entry.text_range().start(), |comment| comment.text_range().start());
+                Indel::delete(self.lines_range(TextRange::new(start, entry.text_range().end())))
+            })
+            .collect();
+        let edit = TextEdit::new(merge_deletions(indels));
+        let mut text = self.text.clone();
+        edit.apply(&mut text);
+        let (source_file, errors) = parse_source_file(&text, &self.options);
+        if errors.len() > self.errors.len() || self.values_by_key(&text) != expected {
+            return Err(RemoveError::Unverified);
+        }
+        (self.text, self.source_file, self.errors) = (text, source_file, errors);
+        Ok(edit)
+    }
+
+    fn apply(&mut self, indels: Vec<Indel>) -> TextEdit {
+        let edit = TextEdit::new(indels);
+        if !edit.is_empty() {
+            edit.apply(&mut self.text);
+            (self.source_file, self.errors) = parse_source_file(&self.text, &self.options);
+        }
+        edit
+    }
+
+    /// The debug text of every entry's value as [`visit_with_options`] evaluates `text`, sorted by
+    /// key and, under each key, in source order.
+    fn values_by_key(&self, text: &str) -> Vec<(String, String)> {
+        let mut values =
+            self.values(text).into_iter().map(|(key, value, _)| (key, value)).collect::<Vec<_>>();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+
+    /// Every entry of `text` in source order, with the debug text of its value and the range of
+    /// its key.
+    fn values(&self, text: &str) -> Vec<(String, String, TextRange)> {
+        struct Values(Vec<(String, String, TextRange)>);
+
+        impl Visitor for Values {
+            fn entry(&mut self, key: &str, value: Value<'_>, range: TextRange) -> ControlFlow<()> {
+                self.0.push((key.to_owned(), format!("{value:?}"), range));
+                ControlFlow::Continue(())
+            }
+        }
+
+        let mut values = Values(Vec::new());
+        visit_with_options(text, &self.options, &mut values);
+        values.0
+    }
+
+    fn entries(&self) -> Vec<EntryInfo> {
+        let mut entries = Vec::new();
+        walk_items(self.source_file.items(), "", &mut |item, _, prefix, key| {
+            if let ast::Item::Entry(entry) = item {
+                entries.push(EntryInfo {
+                    entry: entry.clone(),
+                    prefix: prefix.to_owned(),
+                    key: key.to_owned(),
+                });
+            }
+        });
+        entries
+    }
+
+    fn blocks(&self) -> Vec<BlockInfo> {
+        let mut blocks = Vec::new();
+        walk_items(self.source_file.items(), "", &mut |item, _, _, prefix| {
+            if let ast::Item::PrefixBlock(block) = item {
+                blocks.push(BlockInfo { block: block.clone(), prefix: prefix.to_owned() });
+            }
+        });
+        blocks
+    }
+
+    /// Replaces `range` with `new`, trimmed down to the part that actually differs.
+    fn replacement(&self, range: TextRange, new: &str) -> Indel {
+        let old = &self.text[range];
+        let prefix = old
+            .char_indices()
+            .zip(new.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(old.len().min(new.len()), |((i, _), _)| i);
+        let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+        let suffix = old_rest
+            .chars()
+            .rev()
+            .zip(new_rest.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum::<usize>();
+        let start = range.start() + TextSize::from(prefix as u32);
+        let end = range.end() - TextSize::from(suffix as u32);
+        Indel::replace(TextRange::new(start, end), &new_rest[..new_rest.len() - suffix])
+    }
+
+    /// Adds a new entry for `key` at its insertion point.
+    fn insertion(&self, key: &str, value_text: &str) -> Indel {
+        let point = self.insertion_point(key);
+        self.entry_at(&point, key, value_text)
+    }
+
+    fn entry_at(&self, point: &InsertionPoint, key: &str, value_text: &str) -> Indel {
+        let line =
+            format!("{}{} {value_text}", point.indent, render::key(&key[point.prefix.len()..]));
+        let at_eof = usize::from(point.offset) == self.text.len();
+        let text = if at_eof && !self.text.is_empty() && !self.text.ends_with('\n') {
+            format!("\n{line}")
+        } else {
+            format!("{line}\n")
+        };
+        Indel::insert(point.offset, text)
+    }
+
+    /// Returns where a new entry for `key` goes: before the closing brace of the most specific
+    /// (and then the last) prefix block that `key` extends, or at the end of the file.
+    fn insertion_point(&self, key: &str) -> InsertionPoint {
+        let block = self
+            .blocks()
+            .into_iter()
+            .filter(|info| key.len() > info.prefix.len() && key.starts_with(&info.prefix))
+            .filter(|info| info.block.close_brace().is_some())
+            .max_by_key(|info| info.prefix.len());
+        let Some(BlockInfo { block, prefix }) = block else {
+            return InsertionPoint {
+                offset: self.end(),
+                indent: String::new(),
+                prefix: String::new(),
+            };
+        };
+        let close_brace = block.close_brace().expect("filtered above");
+        let indent = self.item_indent(&block);
+        InsertionPoint { offset: self.line_start(close_brace.text_range().start()), indent, prefix }
+    }
+
+    /// Returns the indentation of the items in `block`: that of its last entry or block, or the
+    /// block's own with two more spaces.
+    fn item_indent(&self, block: &ast::PrefixBlock) -> String {
+        block
+            .items()
+            .filter(|item| !matches!(item, ast::Item::Directive(_)))
+            .last()
+            .map(|item| self.indent_text_of(item.syntax()).to_owned())
+            .unwrap_or_else(|| format!("{}  ", self.indent_text_of(block.syntax())))
+    }
+
+    fn end(&self) -> TextSize {
+        TextSize::of(self.text.as_str())
+    }
+
+    fn line_start(&self, offset: TextSize) -> TextSize {
+        let start = self.text[..usize::from(offset)].rfind('\n').map_or(0, |i| i + 1);
+        TextSize::from(start as u32)
+    }
+
+    fn indent_of(&self, node: &SyntaxNode) -> usize {
+        self.indent_text_of(node).len()
+    }
+
+    fn indent_text_of(&self, node: &SyntaxNode) -> &str {
+        let start = node.text_range().start();
+        &self.text[TextRange::new(self.line_start(start), start)]
+    }
+
+    /// The range of the lines `node` is on, including the line break. For the last line of a file
+    /// without a final line break, the preceding line break is taken instead.
+    fn line_range(&self, node: &SyntaxNode) -> TextRange {
+        self.lines_range(node.text_range())
+    }
+
+    /// Same as [`line_range`](Self::line_range), for the lines `range` is on.
+    fn lines_range(&self, range: TextRange) -> TextRange {
+        let start = self.line_start(range.start());
+        let mut end = range.end();
+        if !self.text[..usize::from(end)].ends_with('\n') {
+            match self.text[usize::from(end)..].find('\n') {
+                Some(i) => end += TextSize::from(i as u32 + 1),
+                None if start > 0.into() => {
+                    return TextRange::new(start - TextSize::from(1), self.end());
+                }
+                None => end = self.end(),
+            }
+        }
+        TextRange::new(start, end)
+    }
+}
+
+impl std::fmt::Display for Document {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Returns the first of the comment lines directly above `entry`, with no blank line between.
+fn attached_comments(entry: &SyntaxNode) -> Option<SyntaxNode> {
+    let mut first = None;
+    let mut element = entry.prev_sibling_or_token();
+    loop {
+        while element
+            .as_ref()
+            .is_some_and(|e| matches!(e.kind(), SyntaxKind::SPACE | SyntaxKind::TAB))
+        {
+            element = element.and_then(|e| e.prev_sibling_or_token());
+        }
+        if element.as_ref().is_none_or(|e| e.kind() != SyntaxKind::NEWLINE) {
+            return first;
+        }
+        element = element.and_then(|e| e.prev_sibling_or_token());
+        match element {
+            Some(SyntaxElement::Node(comment)) if comment.kind() == SyntaxKind::COMMENT => {
+                element = comment.prev_sibling_or_token();
+                first = Some(comment);
+            }
+            _ => return first,
+        }
+    }
+}
+
+fn select<'a>(entries: &'a [EntryInfo], key: &str, occurrences: Occurrences) -> Vec<&'a EntryInfo> {
+    let matches = entries.iter().filter(|info| info.key == key);
+    match occurrences {
+        Occurrences::All => matches.collect(),
+        Occurrences::First => matches.take(1).collect(),
+    }
+}
+
+/// Joins deletions that touch or overlap, which happens when removing adjacent last lines.
+fn merge_deletions(mut indels: Vec<Indel>) -> Vec<Indel> {
+    indels.sort_by_key(|indel| indel.delete.start());
+    let mut merged: Vec<Indel> = Vec::with_capacity(indels.len());
+    for indel in indels {
+        match merged.last_mut() {
+            Some(last) if indel.delete.start() <= last.delete.end() => {
+                last.delete = last.delete.cover(indel.delete);
+            }
+            _ => merged.push(indel),
+        }
+    }
+    merged
+}
+
+/// Shifts every line after the first by `delta` spaces, keeping a moved block string's content
+/// indented relative to its new key.
+fn reindent(text: &str, delta: isize) -> String {
+    if delta == 0 || !text.contains('\n') {
+        return text.to_owned();
+    }
+    let mut lines = text.split('\n');
+    let mut out = lines.next().unwrap_or_default().to_owned();
+    for line in lines {
+        out.push('\n');
+        if line.is_empty() {
+            continue;
+        }
+        if delta > 0 {
+            out.extend(std::iter::repeat_n(' ', delta as usize));
+            out.push_str(line);
+        } else {
+            let strip = line.len() - line.trim_start_matches(' ').len();
+            out.push_str(&line[strip.min(delta.unsigned_abs())..]);
+        }
+    }
+    out
+}
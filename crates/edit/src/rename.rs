@@ -0,0 +1,358 @@
+use crate::{Document, Occurrences, merge_deletions, reindent, render};
+use core::fmt;
+use mical_cli_config::{key_text, parse_source_file, walk_items};
+use mical_cli_syntax::{
+    Indel, SyntaxKind, TextEdit, TextRange, TextSize,
+    ast::{self, AstChildren, AstNode},
+};
+
+/// Which full keys [`Document::rename_keys`] renames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenameScope {
+    /// Only the key `from` itself.
+    #[default]
+    Key,
+    /// Every key starting with `from`, which keeps the rest of the key. Pass a prefix ending with
+    /// its separator, like `server.http.`, to rename a whole namespace.
+    Prefix,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenameError {
+    /// The document has syntax errors, so its structure cannot be trusted.
+    SyntaxErrors,
+    /// No entry has a key to rename.
+    NotFound,
+    /// Renaming would leave an entry with an empty key.
+    EmptyKey,
+    /// A renamed key already belongs to an entry that is not renamed.
+    Conflict { key: String },
+    /// The rewritten document would not evaluate to the renamed entries; it is left unchanged.
+    Unverified,
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameError::SyntaxErrors => f.write_str("the document has syntax errors"),
+            RenameError::NotFound => f.write_str("no key to rename"),
+            RenameError::EmptyKey => f.write_str("the new key would be empty"),
+            RenameError::Conflict { key } => write!(f, "key '{key}' already exists"),
+            RenameError::Unverified => {
+                f.write_str("the renamed document does not evaluate to the renamed entries")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+impl Document {
+    /// Renames the full key `from` to `to`, or with [`RenameScope::Prefix`] every key starting
+    /// with `from`, wherever the key is spelled across prefix blocks. With
+    /// [`Occurrences::First`], only the first entry of each renamed key is.
+    ///
+    /// Each affected prefix block is handled from the outside in. A block whose prefix still
+    /// starts every new key stays, and the keys inside it are rewritten; a block whose entries are
+    /// all renamed and still share a prefix gets a new header; any other block is split, moving
+    /// the renamed entries into a new block right after it. A block left without entries goes
+    /// away unless it holds comments or directives. Fails without editing if a new key is already
+    /// taken by an entry that is not renamed.
+    pub fn rename_keys(
+        &mut self,
+        from: &str,
+        to: &str,
+        scope: RenameScope,
+        occurrences: Occurrences,
+    ) -> Result<TextEdit, RenameError> {
+        if !self.errors.is_empty() {
+            return Err(RenameError::SyntaxErrors);
+        }
+        let rename_key = |key: &str| match scope {
+            RenameScope::Key => (key == from).then(|| to.to_owned()),
+            RenameScope::Prefix => key.strip_prefix(from).map(|rest| format!("{to}{rest}")),
+        };
+        let entries = self.entries();
+        let mut seen = Vec::new();
+        let selected = entries
+            .iter()
+            .filter(|info| rename_key(&info.key).is_some())
+            .filter(|info| match occurrences {
+                Occurrences::All => true,
+                Occurrences::First if seen.contains(&info.key) => false,
+                Occurrences::First => {
+                    seen.push(info.key.clone());
+                    true
+                }
+            })
+            .filter_map(|info| Some(info.entry.key()?.syntax().text_range()))
+            .collect::<Vec<_>>();
+        let rename = |entry: &ast::Entry, key: &str| {
+            let range = entry.key()?.syntax().text_range();
+            selected.contains(&range).then(|| rename_key(key)).flatten()
+        };
+        let renamed =
+            entries.iter().filter_map(|info| rename(&info.entry, &info.key)).collect::<Vec<_>>();
+        if renamed.is_empty() {
+            return Err(RenameError::NotFound);
+        }
+        if renamed.iter().any(String::is_empty) {
+            return Err(RenameError::EmptyKey);
+        }
+        if from == to {
+            return Ok(TextEdit::default());
+        }
+        for info in entries.iter().filter(|info| rename(&info.entry, &info.key).is_none()) {
+            if renamed.contains(&info.key) {
+                return Err(RenameError::Conflict { key: info.key.clone() });
+            }
+        }
+
+        let mut renamer = Renamer { doc: self, rename: &rename, indels: Vec::new() };
+        renamer.items(self.source_file.items(), "");
+        let indels = renamer.indels;
+        self.apply_renamed(indels, &|key, range| {
+            selected.contains(&range).then(|| rename_key(key)).flatten()
+        })
+    }
+
+    /// Applies `indels` only if the result evaluates to the current entries with their keys
+    /// renamed, and keeps every comment and directive. Entries may move, so only the order of
+    /// values under each key is compared; `rename` takes a key with the range it is spelled at.
+    fn apply_renamed(
+        &mut self,
+        indels: Vec<Indel>,
+        rename: &dyn Fn(&str, TextRange) -> Option<String>,
+    ) -> Result<TextEdit, RenameError> {
+        let edit = TextEdit::new(indels);
+        let mut text = self.text.clone();
+        edit.apply(&mut text);
+        let (source_file, errors) = parse_source_file(&text, &self.options);
+        let mut expected = self
+            .values(&self.text)
+            .into_iter()
+            .map(|(key, value, range)| (rename(&key, range).unwrap_or(key), value))
+            .collect::<Vec<_>>();
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        if !errors.is_empty()
+            || self.values_by_key(&text) != expected
+            || annotations(&source_file) != annotations(&self.source_file)
+        {
+            return Err(RenameError::Unverified);
+        }
+        (self.text, self.source_file, self.errors) = (text, source_file, errors);
+        Ok(edit)
+    }
+}
+
+/// The text of every comment and directive in `source_file`, sorted.
+fn annotations(source_file: &ast::SourceFile) -> Vec<String> {
+    let mut annotations = source_file
+        .syntax()
+        .descendants()
+        .filter(|node| matches!(node.kind(), SyntaxKind::COMMENT | SyntaxKind::DIRECTIVE))
+        .map(|node| node.to_string().trim_end().to_owned())
+        .collect::<Vec<_>>();
+    annotations.sort();
+    annotations
+}
+
+/// Whether `block` holds a comment or a directive, which deleting it would lose.
+fn annotated(block: &ast::PrefixBlock) -> bool {
+    block
+        .syntax()
+        .descendants()
+        .any(|node| matches!(node.kind(), SyntaxKind::COMMENT | SyntaxKind::DIRECTIVE))
+}
+
+struct Renamer<'a> {
+    doc: &'a Document,
+    rename: &'a dyn Fn(&ast::Entry, &str) -> Option<String>,
+    indels: Vec<Indel>,
+}
+
+/// An entry below a prefix block, with its full key and, if it is renamed, its new one.
+struct Nested {
+    entry: ast::Entry,
+    key: String,
+    new_key: Option<String>,
+}
+
+impl Renamer<'_> {
+    /// Renames within `items`, which stay below `prefix`: every new key in them starts with it.
+    fn items(&mut self, items: AstChildren<ast::Item>, prefix: &str) {
+        for item in items {
+            match &item {
+                ast::Item::Entry(entry) => {
+                    let Some(key) = entry.key() else { continue };
+                    let Some(text) = key_text(&key) else { continue };
+                    let full_key = format!("{prefix}{text}");
+                    let Some(new_key) = (self.rename)(entry, &full_key) else { continue };
+                    let new = render::key(&new_key[prefix.len()..]);
+                    self.indels.push(self.doc.replacement(key.syntax().text_range(), &new));
+                }
+                ast::Item::PrefixBlock(block) => self.block(block, prefix),
+                ast::Item::Directive(_) => {}
+            }
+        }
+    }
+
+    fn block(&mut self, block: &ast::PrefixBlock, prefix: &str) {
+        let Some(key) = block.key() else { return };
+        let block_prefix = block_prefix(block, prefix);
+        let nested = self.nested(block, prefix);
+        let renamed = nested.iter().filter(|n| n.new_key.is_some()).collect::<Vec<_>>();
+        if renamed.is_empty() {
+            return;
+        }
+        let stays = renamed.iter().all(|n| {
+            let new_key = n.new_key.as_deref().unwrap();
+            new_key.len() > block_prefix.len() && new_key.starts_with(&block_prefix)
+        });
+        if stays {
+            return self.items(block.items(), &block_prefix);
+        }
+        // A new header keeps the keys inside the block as they are, while a split block lists
+        // each moved entry by its own key.
+        let relative_to_block = |n: &Nested| n.key[block_prefix.len()..].to_owned();
+        match shared_prefix(&renamed, relative_to_block, prefix) {
+            Some(new_prefix) if renamed.len() == nested.len() => {
+                let new = render::key(&new_prefix[prefix.len()..]);
+                self.indels.push(self.doc.replacement(key.syntax().text_range(), &new));
+            }
+            _ => {
+                let own_key = |n: &Nested| n.entry.key().as_ref().and_then(key_text).unwrap();
+                let new_prefix = shared_prefix(&renamed, own_key, prefix);
+                let whole = renamed.len() == nested.len();
+                self.split(block, prefix, &renamed, new_prefix.as_deref(), whole)
+            }
+        }
+    }
+
+    /// Returns the entries below `block`, at any depth.
+    fn nested(&self, block: &ast::PrefixBlock, prefix: &str) -> Vec<Nested> {
+        let mut nested = Vec::new();
+        walk_items(block.items(), &block_prefix(block, prefix), &mut |item, _, _, key| {
+            if let ast::Item::Entry(entry) = item {
+                let key = key.to_owned();
+                let new_key = (self.rename)(entry, &key);
+                nested.push(Nested { entry: entry.clone(), key, new_key });
+            }
+        });
+        nested
+    }
+
+    /// Moves the `renamed` entries out of `block` into a new block right after it, or into the
+    /// enclosing one when they share no prefix below `prefix`. If they are the `whole` block, it
+    /// goes away unless it holds comments or directives.
+    fn split(
+        &mut self,
+        block: &ast::PrefixBlock,
+        prefix: &str,
+        renamed: &[&Nested],
+        new_prefix: Option<&str>,
+        whole: bool,
+    ) {
+        let doc = self.doc;
+        let mut deletions = Vec::new();
+        match whole && !annotated(block) {
+            true => deletions.push(Indel::delete(doc.line_range(block.syntax()))),
+            false => {
+                self.delete_renamed(block.items(), &block_prefix(block, prefix), &mut deletions)
+            }
+        }
+        self.indels.extend(merge_deletions(deletions));
+
+        let indent = doc.indent_text_of(block.syntax());
+        let (mut out, entry_indent, entry_prefix) = match new_prefix {
+            Some(new_prefix) => {
+                let header = render::key(&new_prefix[prefix.len()..]);
+                (format!("{indent}{header} {{\n"), doc.item_indent(block), new_prefix)
+            }
+            None => (String::new(), indent.to_owned(), prefix),
+        };
+        for nested in renamed {
+            let entry = nested.entry.syntax();
+            let key = nested.entry.key().expect("nested entries have keys");
+            let new_key = nested.new_key.as_deref().unwrap();
+            let tail = &doc.text
+                [TextRange::new(key.syntax().text_range().end(), entry.text_range().end())];
+            let delta = entry_indent.len() as isize - doc.indent_of(entry) as isize;
+            out.push_str(&entry_indent);
+            out.push_str(&render::key(&new_key[entry_prefix.len()..]));
+            out.push_str(&reindent(tail, delta));
+            if !tail.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+        if new_prefix.is_some() {
+            out.push_str(&format!("{indent}}}\n"));
+        }
+
+        let end = block.syntax().text_range().end();
+        let insert = match doc.text[..usize::from(end)].ends_with('\n') {
+            true => Indel::insert(end, out),
+            false => match doc.text[usize::from(end)..].find('\n') {
+                Some(i) => Indel::insert(end + TextSize::from(i as u32 + 1), out),
+                None => Indel::insert(doc.end(), format!("\n{}", out.trim_end_matches('\n'))),
+            },
+        };
+        self.indels.push(insert);
+    }
+
+    /// Deletes the lines of renamed entries below `items`, and whole blocks whose entries are all
+    /// renamed and that hold no comments or directives.
+    fn delete_renamed(
+        &self,
+        items: AstChildren<ast::Item>,
+        prefix: &str,
+        deletions: &mut Vec<Indel>,
+    ) {
+        for item in items {
+            match &item {
+                ast::Item::Entry(entry) => {
+                    let Some(key) = entry.key().as_ref().and_then(key_text) else { continue };
+                    if (self.rename)(entry, &format!("{prefix}{key}")).is_some() {
+                        deletions.push(Indel::delete(self.doc.line_range(entry.syntax())));
+                    }
+                }
+                ast::Item::PrefixBlock(block) => {
+                    let nested = self.nested(block, prefix);
+                    let renamed = nested.iter().filter(|n| n.new_key.is_some()).count();
+                    if renamed > 0 && renamed == nested.len() && !annotated(block) {
+                        deletions.push(Indel::delete(self.doc.line_range(block.syntax())));
+                    } else if renamed > 0 {
+                        self.delete_renamed(block.items(), &block_prefix(block, prefix), deletions);
+                    }
+                }
+                ast::Item::Directive(_) => {}
+            }
+        }
+    }
+}
+
+fn block_prefix(block: &ast::PrefixBlock, prefix: &str) -> String {
+    let text = block.key().as_ref().and_then(key_text).unwrap_or_default();
+    format!("{prefix}{text}")
+}
+
+/// Returns the prefix the new keys of `renamed` share once their `relative` key is taken off, if
+/// it is below the enclosing `prefix`.
+fn shared_prefix(
+    renamed: &[&Nested],
+    relative: impl Fn(&Nested) -> String,
+    prefix: &str,
+) -> Option<String> {
+    let mut shared: Option<&str> = None;
+    for nested in renamed {
+        let new_key = nested.new_key.as_deref().unwrap();
+        let new_prefix = new_key.strip_suffix(relative(nested).as_str())?;
+        if shared.is_some_and(|shared| shared != new_prefix) {
+            return None;
+        }
+        shared = Some(new_prefix);
+    }
+    shared
+        .filter(|shared| shared.len() > prefix.len() && shared.starts_with(prefix))
+        .map(str::to_owned)
+}
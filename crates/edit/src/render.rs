@@ -0,0 +1,100 @@
+use mical_cli_config::{Value, quoted};
+
+/// Renders `key` as a word key when it reads back unchanged, otherwise as a double-quoted key.
+pub(crate) fn key(key: &str) -> String {
+    let is_word = !key.is_empty()
+        && !key.starts_with(['"', '\'', '#', '{', '}'])
+        && !key.ends_with(['{', '}'])
+        && !key.contains([' ', '\t', '\n', '\r']);
+    if is_word { key.to_owned() } else { quoted(key, '"') }
+}
+
+/// Renders `value` so that it evaluates back to itself.
+///
+/// Strings are written as line strings when the parser would read them back verbatim, and quoted
+/// otherwise. `quote` forces a quoted string with that quote character.
+pub(crate) fn value(value: Value<'_>, quote: Option<char>) -> String {
+    match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Integer(text) => text.to_owned(),
+        Value::String(text) => match quote {
+            Some(quote) => quoted(text, quote),
+            None if is_line_string(text) => text.to_owned(),
+            None => quoted(text, '"'),
+        },
+    }
+}
+
+fn is_line_string(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with([' ', '\t', '"', '\''])
+        && !text.ends_with([' ', '\t'])
+        && !text.contains(['\n', '\r'])
+        && !is_block_string_header(text)
+        && !is_brace(text)
+        && Value::from_text(text) == Value::String(text)
+}
+
+/// Whether `text` would be read as the header of a block string, like `|`, `>-`, `|+2` or `|2+`.
+fn is_block_string_header(text: &str) -> bool {
+    let Some(rest) = text.strip_prefix(['|', '>']) else { return false };
+    matches!(
+        rest.as_bytes(),
+        [] | [b'+' | b'-']
+            | [b'1'..=b'9']
+            | [b'+' | b'-', b'1'..=b'9']
+            | [b'1'..=b'9', b'+' | b'-']
+    )
+}
+
+/// Whether `text` could be taken for a brace of a prefix block, like `{`, `a {` or `}x`.
+fn is_brace(text: &str) -> bool {
+    text.starts_with('}')
+        || text
+            .strip_suffix(['{', '}'])
+            .is_some_and(|rest| rest.is_empty() || rest.ends_with([' ', '\t']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings() {
+        assert_eq!(value(Value::String("hello world"), None), "hello world");
+        assert_eq!(value(Value::String("42"), None), r#""42""#);
+        assert_eq!(value(Value::String("true"), None), r#""true""#);
+        assert_eq!(value(Value::String("|-"), None), r#""|-""#);
+        assert_eq!(value(Value::String("|x"), None), "|x");
+        assert_eq!(value(Value::String(">-4"), None), r#"">-4""#);
+        assert_eq!(value(Value::String("|2-"), None), r#""|2-""#);
+        assert_eq!(value(Value::String("|2-2"), None), "|2-2");
+        assert_eq!(value(Value::String("|0"), None), "|0");
+        assert_eq!(value(Value::String(" pad"), None), r#"" pad""#);
+        assert_eq!(value(Value::String("a\nb"), None), r#""a\nb""#);
+        assert_eq!(value(Value::String("\0\x1b\x7f"), Some('"')), r#""\0\e\u{7F}""#);
+        assert_eq!(value(Value::String("it's"), Some('\'')), r"'it\'s'");
+        assert_eq!(value(Value::String(""), None), r#""""#);
+    }
+
+    #[test]
+    fn braces() {
+        assert_eq!(value(Value::String("{"), None), r#""{""#);
+        assert_eq!(value(Value::String("}"), None), r#""}""#);
+        assert_eq!(value(Value::String("a {"), None), r#""a {""#);
+        assert_eq!(value(Value::String("a }"), None), r#""a }""#);
+        assert_eq!(value(Value::String("}x"), None), r#""}x""#);
+        assert_eq!(value(Value::String("${HOME}"), None), "${HOME}");
+        assert_eq!(key("{"), r#""{""#);
+        assert_eq!(key("}x"), r#""}x""#);
+        assert_eq!(key("a{"), r#""a{""#);
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(key("server.port"), "server.port");
+        assert_eq!(key("a b"), r#""a b""#);
+        assert_eq!(key("#tag"), "\"#tag\"");
+        assert_eq!(key(""), r#""""#);
+    }
+}
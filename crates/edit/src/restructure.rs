@@ -0,0 +1,285 @@
+use crate::{Document, reindent, render};
+use core::fmt;
+use mical_cli_config::{Config, key_text, parse_source_file};
+use mical_cli_syntax::{
+    Indel, SyntaxElement, SyntaxKind, SyntaxNode, TextEdit, TextRange, TextSize,
+    ast::{self, AstNode},
+};
+
+const INDENT: usize = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RestructureError {
+    /// The document has syntax errors, so its structure cannot be trusted.
+    SyntaxErrors,
+    /// The rewritten document would evaluate differently; the document is left unchanged.
+    Unverified,
+    /// [`Document::group`] was given an empty separator, which splits no key into segments.
+    EmptySeparator,
+}
+
+impl fmt::Display for RestructureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestructureError::SyntaxErrors => f.write_str("the document has syntax errors"),
+            RestructureError::Unverified => {
+                f.write_str("the restructured document does not evaluate to the same entries")
+            }
+            RestructureError::EmptySeparator => f.write_str("the separator must not be empty"),
+        }
+    }
+}
+
+impl std::error::Error for RestructureError {}
+
+/// An entry of a run being grouped, with the comment lines directly above it.
+struct Unit {
+    entry: ast::Entry,
+    comments: Vec<SyntaxNode>,
+    /// The key relative to the block being generated.
+    key: String,
+}
+
+impl Document {
+    /// Expands every prefix block into fully-qualified entries.
+    ///
+    /// The lines of a block body are dedented to the level of the block, so comments stay next to
+    /// the entries they describe.
+    pub fn flatten(&mut self) -> Result<TextEdit, RestructureError> {
+        if !self.errors.is_empty() {
+            return Err(RestructureError::SyntaxErrors);
+        }
+        let line_starts = self.line_starts();
+        let line_of = |offset: TextSize| line_starts.partition_point(|&s| s <= offset) - 1;
+        let mut strip = vec![0; line_starts.len()];
+        let mut removed = vec![false; line_starts.len()];
+        let mut indels = Vec::new();
+
+        for info in self.blocks() {
+            let block = &info.block;
+            let open_line = line_of(block.syntax().text_range().start());
+            let close_line = match block.close_brace() {
+                Some(close_brace) => line_of(close_brace.text_range().start()),
+                None => return Err(RestructureError::SyntaxErrors),
+            };
+            removed[open_line] = true;
+            removed[close_line] = true;
+            let body = open_line + 1..close_line;
+            let min_indent = body
+                .clone()
+                .filter_map(|line| {
+                    let text = self.line_text(&line_starts, line);
+                    let trimmed = text.trim_start_matches(' ');
+                    (!trimmed.trim().is_empty()).then_some(text.len() - trimmed.len())
+                })
+                .min();
+            let dedent = min_indent.map_or(0, |min| min - self.indent_of(block.syntax()));
+            for line in body {
+                strip[line] += dedent;
+            }
+        }
+        for line in (0..line_starts.len()).filter(|&line| removed[line]) {
+            let end = line_starts.get(line + 1).copied().unwrap_or(self.end());
+            indels.push(Indel::delete(TextRange::new(line_starts[line], end)));
+        }
+        for line in (0..line_starts.len()).filter(|&line| !removed[line] && strip[line] > 0) {
+            let text = self.line_text(&line_starts, line);
+            let spaces = text.len() - text.trim_start_matches(' ').len();
+            let len = TextSize::from(strip[line].min(spaces) as u32);
+            indels.push(Indel::delete(TextRange::at(line_starts[line], len)));
+        }
+        for info in self.entries().into_iter().filter(|info| !info.prefix.is_empty()) {
+            if let Some(key) = info.entry.key() {
+                indels.push(Indel::replace(key.syntax().text_range(), render::key(&info.key)));
+            }
+        }
+        self.apply_verified(indels)
+    }
+
+    /// Rewrites runs of adjacent entries whose keys share a first segment into prefix blocks.
+    ///
+    /// The block key is that segment with its trailing `separator`, so no separator has to be
+    /// inserted between the block key and the entry keys. Runs nest: entries sharing a second
+    /// segment get a block of their own. Blank lines, directives and existing blocks end a run;
+    /// comment lines directly above an entry move with it.
+    pub fn group(&mut self, separator: &str) -> Result<TextEdit, RestructureError> {
+        if separator.is_empty() {
+            return Err(RestructureError::EmptySeparator);
+        }
+        if !self.errors.is_empty() {
+            return Err(RestructureError::SyntaxErrors);
+        }
+        let mut indels = Vec::new();
+        let mut containers = vec![self.source_file.syntax().clone()];
+        containers.extend(self.blocks().into_iter().map(|info| info.block.syntax().clone()));
+        for container in containers {
+            for run in self.runs(&container) {
+                let mut start = 0;
+                while start < run.len() {
+                    let segment = first_segment(&run[start].key, separator);
+                    let len = run[start..]
+                        .iter()
+                        .take_while(|unit| {
+                            segment.is_some() && first_segment(&unit.key, separator) == segment
+                        })
+                        .count()
+                        .max(1);
+                    if len > 1 {
+                        let units = &run[start..start + len];
+                        let first =
+                            units[0].comments.first().map_or(units[0].entry.syntax(), |c| c);
+                        let last = units[len - 1].entry.syntax();
+                        let range = TextRange::new(
+                            self.line_start(first.text_range().start()),
+                            last.text_range().end(),
+                        );
+                        let indent = self.indent_of(units[0].entry.syntax());
+                        let mut text = String::new();
+                        self.render_group(&mut text, units, 0, indent, separator);
+                        if !self.text[range].ends_with('\n') {
+                            text.pop();
+                        }
+                        indels.push(Indel::replace(range, text));
+                    }
+                    start += len;
+                }
+            }
+        }
+        self.apply_verified(indels)
+    }
+
+    /// Splits the items of `container` into runs of entries that nothing but comments separates.
+    fn runs(&self, container: &SyntaxNode) -> Vec<Vec<Unit>> {
+        let mut runs = Vec::new();
+        let mut run = Vec::new();
+        let mut comments = Vec::new();
+        let mut line_breaks = 0;
+        for element in container.children_with_tokens() {
+            match element.kind() {
+                SyntaxKind::SPACE => {}
+                SyntaxKind::NEWLINE => {
+                    line_breaks += 1;
+                    if line_breaks > 1 {
+                        comments.clear();
+                        runs.push(std::mem::take(&mut run));
+                    }
+                }
+                SyntaxKind::COMMENT => {
+                    let SyntaxElement::Node(node) = element else { unreachable!() };
+                    comments.push(node);
+                    line_breaks = 0;
+                }
+                SyntaxKind::ENTRY => {
+                    let entry = element.into_node().and_then(ast::Entry::cast).unwrap();
+                    match entry.key().as_ref().and_then(key_text) {
+                        Some(key) => {
+                            run.push(Unit { entry, comments: std::mem::take(&mut comments), key })
+                        }
+                        None => {
+                            comments.clear();
+                            runs.push(std::mem::take(&mut run));
+                        }
+                    }
+                    // An entry ends with its own line break.
+                    line_breaks = 1;
+                }
+                _ => {
+                    comments.clear();
+                    runs.push(std::mem::take(&mut run));
+                    line_breaks = 0;
+                }
+            }
+        }
+        runs.push(run);
+        runs.retain(|run| run.len() > 1);
+        runs
+    }
+
+    /// Renders `units`, whose keys start with `strip` bytes already covered by enclosing blocks,
+    /// grouping the ones that share a segment.
+    fn render_group(
+        &self,
+        out: &mut String,
+        units: &[Unit],
+        strip: usize,
+        indent: usize,
+        separator: &str,
+    ) {
+        let mut start = 0;
+        while start < units.len() {
+            let segment = first_segment(&units[start].key[strip..], separator);
+            let len = units[start..]
+                .iter()
+                .take_while(|unit| {
+                    segment.is_some() && first_segment(&unit.key[strip..], separator) == segment
+                })
+                .count();
+            if let Some(segment) = segment
+                && len > 1
+            {
+                let pad = " ".repeat(indent);
+                out.push_str(&format!("{pad}{} {{\n", render::key(segment)));
+                let group = &units[start..start + len];
+                self.render_group(out, group, strip + segment.len(), indent + INDENT, separator);
+                out.push_str(&format!("{pad}}}\n"));
+                start += len;
+            } else {
+                self.render_unit(out, &units[start], strip, indent);
+                start += 1;
+            }
+        }
+    }
+
+    fn render_unit(&self, out: &mut String, unit: &Unit, strip: usize, indent: usize) {
+        let pad = " ".repeat(indent);
+        for comment in &unit.comments {
+            out.push_str(&format!("{pad}{comment}\n"));
+        }
+        let entry = unit.entry.syntax();
+        let key = unit.entry.key().expect("units have keys");
+        let tail =
+            &self.text[TextRange::new(key.syntax().text_range().end(), entry.text_range().end())];
+        let delta = indent as isize - self.indent_of(entry) as isize;
+        out.push_str(&format!("{pad}{}{}", render::key(&unit.key[strip..]), reindent(tail, delta)));
+        if !tail.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+
+    /// Applies `indels` only if the result evaluates to the same entries as the current text.
+    fn apply_verified(&mut self, indels: Vec<Indel>) -> Result<TextEdit, RestructureError> {
+        let edit = TextEdit::new(indels);
+        let mut text = self.text.clone();
+        edit.apply(&mut text);
+        let (source_file, errors) = parse_source_file(&text, &self.options);
+        let (before, _) = Config::from_source_file(self.source_file.clone());
+        let (after, _) = Config::from_source_file(source_file.clone());
+        if !errors.is_empty() || !before.entries().eq(after.entries()) {
+            return Err(RestructureError::Unverified);
+        }
+        (self.text, self.source_file, self.errors) = (text, source_file, errors);
+        Ok(edit)
+    }
+
+    fn line_starts(&self) -> Vec<TextSize> {
+        let mut starts = vec![TextSize::from(0)];
+        starts.extend(self.text.match_indices('\n').map(|(i, _)| TextSize::from(i as u32 + 1)));
+        if starts.len() > 1 && usize::from(*starts.last().unwrap()) == self.text.len() {
+            starts.pop();
+        }
+        starts
+    }
+
+    fn line_text(&self, line_starts: &[TextSize], line: usize) -> &str {
+        let end = line_starts.get(line + 1).copied().unwrap_or(self.end());
+        self.text[TextRange::new(line_starts[line], end)].trim_end_matches('\n')
+    }
+}
+
+/// The first segment of `key` with its separator, if it has a non-empty one.
+fn first_segment<'k>(key: &'k str, separator: &str) -> Option<&'k str> {
+    match key.find(separator) {
+        Some(i) if i > 0 => Some(&key[..i + separator.len()]),
+        _ => None,
+    }
+}
@@ -0,0 +1,218 @@
+use mical_cli_config::{Config, Value};
+use mical_cli_edit::{Document, Occurrences, RenameError, RenameScope, SetError};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{TextEdit, TextRange};
+use pretty_assertions::assert_eq;
+use std::num::NonZeroU32;
+
+const SOURCE: &str = "\
+# Service configuration
+#duplicates keep-all
+name   web
+server. {
+  host   localhost
+  port   8080
+
+  # TLS settings
+  tls. {
+    cert  \"a.pem\"
+  }
+}
+motd |
+  hello
+    world
+";
+
+fn eval(text: &str) -> Config<'static> {
+    let doc = Document::parse(text);
+    assert!(doc.syntax_errors().is_empty(), "{:?}", doc.syntax_errors());
+    let (config, errors) = Config::from_source_file(doc.source_file().clone());
+    assert!(errors.is_empty(), "{errors:?}");
+    config
+}
+
+fn at(text: &str, needle: &str) -> u32 {
+    text.find(needle).unwrap() as u32
+}
+
+fn edited(edit: &TextEdit) -> Vec<(TextRange, &str)> {
+    edit.indels().iter().map(|indel| (indel.delete, indel.insert.as_str())).collect()
+}
+
+#[test]
+fn set_updates_entry_inside_block() {
+    let mut doc = Document::parse(SOURCE);
+    let edit = doc.set("server.port", Value::Integer("8081"), Occurrences::All).unwrap();
+    let at = at(SOURCE, "8080") + 3;
+    assert_eq!(edited(&edit), [(TextRange::new(at.into(), (at + 1).into()), "1")]);
+    assert_eq!(doc.text(), SOURCE.replace("8080", "8081"));
+    assert_eq!(eval(doc.text()).query("server.port").collect::<Vec<_>>(), [Value::Integer("8081")]);
+}
+
+#[test]
+fn set_keeps_quote_style() {
+    let mut doc = Document::parse(SOURCE);
+    doc.set("server.tls.cert", Value::String("b.pem"), Occurrences::All).unwrap();
+    assert_eq!(doc.text(), SOURCE.replace("\"a.pem\"", "\"b.pem\""));
+}
+
+#[test]
+fn set_replaces_block_string() {
+    let mut doc = Document::parse(SOURCE);
+    doc.set("motd", Value::String("bye"), Occurrences::All).unwrap();
+    assert_eq!(doc.text(), SOURCE.replace("|\n  hello\n    world\n", "bye\n"));
+}
+
+#[test]
+fn set_inserts_into_most_specific_block() {
+    let mut doc = Document::parse(SOURCE);
+    doc.set("server.tls.key", Value::String("a.key"), Occurrences::All).unwrap();
+    assert_eq!(doc.text(), SOURCE.replace("\"a.pem\"\n", "\"a.pem\"\n    key a.key\n"));
+
+    let mut doc = Document::parse(SOURCE);
+    doc.set("server.debug", Value::Bool(true), Occurrences::All).unwrap();
+    assert_eq!(doc.text(), SOURCE.replace("  }\n}\n", "  }\n  debug true\n}\n"));
+
+    let mut doc = Document::parse(SOURCE);
+    doc.set("client.port", Value::String("80"), Occurrences::All).unwrap();
+    assert_eq!(doc.text(), format!("{SOURCE}client.port \"80\"\n"));
+    assert_eq!(eval(doc.text()).query("client.port").collect::<Vec<_>>(), [Value::String("80")]);
+}
+
+#[test]
+fn set_appends_after_last_line_without_newline() {
+    let mut doc = Document::parse("a 1");
+    doc.set("b key", Value::String("two words"), Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "a 1\n\"b key\" two words");
+}
+
+#[test]
+fn set_values_and_keys_that_look_like_braces() {
+    for text in ["{", "}", "a {", "}x"] {
+        let mut doc = Document::parse("x. {\n  y 1\n}\n");
+        doc.set("x.y", Value::String(text), Occurrences::All).unwrap();
+        doc.set("x.z", Value::String(text), Occurrences::All).unwrap();
+        doc.set(&format!("x.{text}"), Value::Integer("2"), Occurrences::All).unwrap();
+        let config = eval(doc.text());
+        assert_eq!(config.query("x.y").collect::<Vec<_>>(), [Value::String(text)]);
+        assert_eq!(config.query("x.z").collect::<Vec<_>>(), [Value::String(text)]);
+        assert_eq!(config.query(&format!("x.{text}")).count(), 1);
+    }
+}
+
+#[test]
+fn set_refuses_what_does_not_read_back() {
+    let mut doc = Document::parse(SOURCE);
+    let result = doc.set("server.port", Value::Integer("80 80"), Occurrences::All);
+    assert_eq!(result, Err(SetError::Unverified));
+    assert_eq!(doc.text(), SOURCE);
+}
+
+#[test]
+fn set_with_parse_options() {
+    let options = ParseOptions { tab_width: NonZeroU32::new(4), ..Default::default() };
+    let mut doc = Document::parse_with_options("a. {\n\tb 1\n}\n", &options);
+    assert_eq!(doc.syntax_errors(), []);
+    doc.set("a.c", Value::Integer("2"), Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "a. {\n\tb 1\n\tc 2\n}\n");
+    assert_eq!(doc.syntax_errors(), []);
+}
+
+#[test]
+fn duplicates() {
+    let source = "tag a\nx. {\n  y 1\n}\ntag b\n";
+    let mut doc = Document::parse(source);
+    doc.set("tag", Value::String("c"), Occurrences::First).unwrap();
+    assert_eq!(doc.text(), "tag c\nx. {\n  y 1\n}\ntag b\n");
+    doc.set("tag", Value::String("d"), Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "tag d\nx. {\n  y 1\n}\ntag d\n");
+    doc.remove("tag", Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "x. {\n  y 1\n}\n");
+}
+
+#[test]
+fn remove_takes_the_whole_line() {
+    let mut doc = Document::parse(SOURCE);
+    doc.remove("server.host", Occurrences::All).unwrap();
+    assert_eq!(doc.text(), SOURCE.replace("  host   localhost\n", ""));
+
+    let mut doc = Document::parse(SOURCE);
+    doc.remove("motd", Occurrences::All).unwrap();
+    assert_eq!(doc.text(), SOURCE.replace("motd |\n  hello\n    world\n", ""));
+
+    let mut doc = Document::parse("a 1\nb 2");
+    doc.remove("b", Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "a 1");
+
+    let mut doc = Document::parse("a 1\n");
+    assert!(doc.remove("missing", Occurrences::All).unwrap().is_empty());
+    assert_eq!(doc.text(), "a 1\n");
+}
+
+#[test]
+fn remove_takes_the_comments_above() {
+    let source = "# about a\na 1\n\n# about the file\n\n# about b\n# more about b\nb 2\nc. {\n  # about d\n  d 3\n}\n";
+    let mut doc = Document::parse(source);
+    doc.remove("b", Occurrences::All).unwrap();
+    assert_eq!(doc.text(), source.replace("# about b\n# more about b\nb 2\n", ""));
+    doc.remove("c.d", Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "# about a\na 1\n\n# about the file\n\nc. {\n}\n");
+
+    let mut doc = Document::parse("a 1\n# about b\nb 2");
+    doc.remove("b", Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "a 1");
+}
+
+#[test]
+fn rename_within_block() {
+    let mut doc = Document::parse(SOURCE);
+    let edit = doc
+        .rename_keys("server.host", "server.hostname", RenameScope::Key, Occurrences::All)
+        .unwrap();
+    let at = at(SOURCE, "host ") + 4;
+    assert_eq!(edited(&edit), [(TextRange::empty(at.into()), "name")]);
+    assert_eq!(doc.text(), SOURCE.replace("  host   ", "  hostname   "));
+}
+
+#[test]
+fn rename_moves_entry_out_of_block() {
+    let mut doc = Document::parse(SOURCE);
+    doc.rename_keys("server.tls.cert", "cert", RenameScope::Key, Occurrences::All).unwrap();
+    let moved = SOURCE.replace("  tls. {\n    cert  \"a.pem\"\n  }\n}\n", "}\ncert  \"a.pem\"\n");
+    assert_eq!(doc.text(), moved);
+
+    let mut doc = Document::parse("a. {\n  text |\n    x\n      y\n}\n");
+    doc.rename_keys("a.text", "text", RenameScope::Key, Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "text |\n  x\n    y\n");
+    assert_eq!(eval(doc.text()).query("text").collect::<Vec<_>>(), [Value::String("x\n  y\n")]);
+}
+
+#[test]
+fn rename_refuses_a_taken_key() {
+    let source = "tag a\ntag b\nlabel c\n";
+    let mut doc = Document::parse(source);
+    let conflict = RenameError::Conflict { key: "label".to_owned() };
+    assert_eq!(
+        doc.rename_keys("tag", "label", RenameScope::Key, Occurrences::First),
+        Err(conflict)
+    );
+    assert_eq!(doc.text(), source);
+    doc.rename_keys("tag", "tag", RenameScope::Key, Occurrences::First).unwrap();
+    assert_eq!(doc.text(), source);
+}
+
+#[test]
+fn rename_keeps_position_when_possible() {
+    let mut doc = Document::parse(SOURCE);
+    doc.rename_keys("motd", "server.tls.motd", RenameScope::Key, Occurrences::All).unwrap();
+    assert_eq!(doc.text(), SOURCE.replace("motd |", "server.tls.motd |"));
+}
+
+#[test]
+fn edits_apply_to_the_previous_text() {
+    let mut doc = Document::parse(SOURCE);
+    let edit = doc.set("server.tls.key", Value::String("a.key"), Occurrences::All).unwrap();
+    let mut text = SOURCE.to_owned();
+    edit.apply(&mut text);
+    assert_eq!(text, doc.text());
+}
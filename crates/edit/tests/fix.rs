@@ -0,0 +1,18 @@
+use mical_cli_edit::Document;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn fixes_syntax_and_config_errors() {
+    let mut doc = Document::parse("a\t1\nb. {\n\tc \"x\"  \n  d 'it\\q\nb \"y\" z");
+    assert_eq!(doc.fix_errors(), 7);
+    assert_eq!(doc.text(), "a 1\nb. {\n  c \"x\"\n  d 'it\\\\q'\nb \"\\\"y\\\" z\"\n}\n");
+    assert!(doc.syntax_errors().is_empty());
+    assert!(doc.error_fixes().is_empty());
+}
+
+#[test]
+fn nothing_to_fix() {
+    let mut doc = Document::parse("a 1\n");
+    assert_eq!(doc.fix_errors(), 0);
+    assert_eq!(doc.text(), "a 1\n");
+}
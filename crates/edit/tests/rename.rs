@@ -0,0 +1,166 @@
+use mical_cli_edit::{Document, Occurrences, RenameError, RenameScope};
+use mical_cli_parser::ParseOptions;
+use pretty_assertions::assert_eq;
+use std::num::NonZeroU32;
+
+const SOURCE: &str = "\
+server.http.port 80
+server. {
+  host localhost
+  http. {
+    port 8080
+    tls true
+  }
+}
+";
+
+fn rename(text: &str, from: &str, to: &str, scope: RenameScope) -> Result<String, RenameError> {
+    let mut doc = Document::parse(text);
+    doc.rename_keys(from, to, scope, Occurrences::All)?;
+    Ok(doc.text().to_owned())
+}
+
+#[test]
+fn rename_key_in_place() {
+    assert_eq!(
+        rename(SOURCE, "server.http.port", "server.http.listen", RenameScope::Key).unwrap(),
+        SOURCE.replace("http.port 80", "http.listen 80").replace("port 8080", "listen 8080")
+    );
+}
+
+#[test]
+fn rename_prefix_rewrites_block_header() {
+    assert_eq!(
+        rename(SOURCE, "server.http.", "server.web.", RenameScope::Prefix).unwrap(),
+        SOURCE.replace("server.http.port", "server.web.port").replace("http. {", "web. {")
+    );
+    assert_eq!(
+        rename(SOURCE, "server.", "api.", RenameScope::Prefix).unwrap(),
+        SOURCE.replace("server.", "api.")
+    );
+}
+
+#[test]
+fn rename_splits_block_when_only_some_entries_move() {
+    assert_eq!(
+        rename(SOURCE, "server.http.", "api.", RenameScope::Prefix).unwrap(),
+        "\
+api.port 80
+server. {
+  host localhost
+}
+api. {
+  port 8080
+  tls true
+}
+"
+    );
+    assert_eq!(
+        rename(SOURCE, "server.http.tls", "client.tls", RenameScope::Key).unwrap(),
+        "\
+server.http.port 80
+server. {
+  host localhost
+  http. {
+    port 8080
+  }
+}
+client. {
+  tls true
+}
+"
+    );
+}
+
+#[test]
+fn rename_moves_entries_without_shared_prefix_into_enclosing_block() {
+    let source = "\
+app. {
+  x. {
+    a 1
+    b 2
+  }
+}
+";
+    assert_eq!(
+        rename(source, "app.x.", "app.", RenameScope::Prefix).unwrap(),
+        "\
+app. {
+  a 1
+  b 2
+}
+"
+    );
+    assert_eq!(
+        rename(SOURCE, "server.http.", "server.", RenameScope::Prefix).unwrap(),
+        SOURCE
+            .replace("server.http.port", "server.port")
+            .replace("  http. {\n    port 8080\n    tls true\n  }\n", "  port 8080\n  tls true\n")
+    );
+}
+
+#[test]
+fn rename_reports_conflicts() {
+    assert_eq!(
+        rename(SOURCE, "server.http.port", "server.host", RenameScope::Key),
+        Err(RenameError::Conflict { key: "server.host".into() })
+    );
+    assert_eq!(rename(SOURCE, "client.", "api.", RenameScope::Prefix), Err(RenameError::NotFound));
+    assert_eq!(rename(SOURCE, "server.host", "", RenameScope::Key), Err(RenameError::EmptyKey));
+    assert_eq!(rename("a {\n", "a", "b", RenameScope::Key), Err(RenameError::SyntaxErrors));
+}
+
+#[test]
+fn rename_keeps_block_strings_aligned() {
+    let source = "\
+server. {
+  host localhost
+  motd |
+    hello
+}
+";
+    assert_eq!(
+        rename(source, "server.motd", "banner", RenameScope::Key).unwrap(),
+        "\
+server. {
+  host localhost
+}
+banner |
+  hello
+"
+    );
+}
+
+#[test]
+fn rename_keeps_comments_and_directives_of_an_emptied_block() {
+    let source = "a. {\n  # important note\n  #version 2\n  b 1\n}\nz 0";
+    assert_eq!(
+        rename(source, "a.b", "c", RenameScope::Key).unwrap(),
+        "a. {\n  # important note\n  #version 2\n}\nc 1\nz 0"
+    );
+    let source = "x. {\n  a. {\n    # note\n    b 1\n  }\n  y 2\n}\n";
+    assert_eq!(
+        rename(source, "x.a.b", "c", RenameScope::Key).unwrap(),
+        "x. {\n  a. {\n    # note\n  }\n  y 2\n}\nc 1\n"
+    );
+}
+
+#[test]
+fn rename_indents_a_split_block_like_its_source() {
+    let source = "server. {\n\thost localhost\n\thttp. {\n\t\tport 8080\n\t}\n}\n";
+    let options = ParseOptions { tab_width: NonZeroU32::new(4), ..ParseOptions::default() };
+    let mut doc = Document::parse_with_options(source, &options);
+    doc.rename_keys("server.http.port", "client.port", RenameScope::Key, Occurrences::All).unwrap();
+    assert_eq!(doc.text(), "server. {\n\thost localhost\n}\nclient. {\n\tport 8080\n}\n");
+}
+
+#[test]
+fn rename_first_moves_only_the_first_entry() {
+    let mut doc = Document::parse("a. {\n  b 1\n}\na.b 2\n");
+    doc.rename_keys("a.b", "c", RenameScope::Key, Occurrences::First).unwrap();
+    assert_eq!(doc.text(), "c 1\na.b 2\n");
+
+    let mut doc = Document::parse("tag x\ntag y\n");
+    doc.rename_keys("tag", "label", RenameScope::Key, Occurrences::First).unwrap();
+    assert_eq!(doc.text(), "label x\ntag y\n");
+}
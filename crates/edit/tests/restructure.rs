@@ -0,0 +1,121 @@
+use mical_cli_edit::{Document, RestructureError};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn flatten_nested_blocks() {
+    let mut doc = Document::parse(
+        "\
+name web
+server. {
+  # where to listen
+  host localhost
+  port 8080
+
+  tls. {
+    cert a.pem
+  }
+  motd |
+    hello
+      world
+}
+",
+    );
+    doc.flatten().unwrap();
+    assert_eq!(
+        doc.text(),
+        "\
+name web
+# where to listen
+server.host localhost
+server.port 8080
+
+server.tls.cert a.pem
+server.motd |
+  hello
+    world
+"
+    );
+}
+
+#[test]
+fn flatten_keeps_indented_blocks_in_place() {
+    let mut doc = Document::parse("a. {\n    b {\n        c 1\n    }\n}\n");
+    doc.flatten().unwrap();
+    assert_eq!(doc.text(), "a.bc 1\n");
+}
+
+#[test]
+fn group_runs() {
+    let mut doc = Document::parse(
+        "\
+name web
+server.host localhost
+# TLS
+server.tls.cert a.pem
+server.tls.key a.key
+server.port 8080
+
+server.motd |
+  hi
+client.port 80
+",
+    );
+    doc.group(".").unwrap();
+    assert_eq!(
+        doc.text(),
+        "\
+name web
+server. {
+  host localhost
+  tls. {
+    # TLS
+    cert a.pem
+    key a.key
+  }
+  port 8080
+}
+
+server.motd |
+  hi
+client.port 80
+"
+    );
+}
+
+#[test]
+fn group_inside_existing_block() {
+    let mut doc = Document::parse("app. {\n  db.host h\n  db.port 1\n}\n");
+    doc.group(".").unwrap();
+    assert_eq!(doc.text(), "app. {\n  db. {\n    host h\n    port 1\n  }\n}\n");
+}
+
+#[test]
+fn group_moves_block_strings() {
+    let mut doc = Document::parse("a.x |\n  one\n   two\na.y 1");
+    doc.group(".").unwrap();
+    assert_eq!(doc.text(), "a. {\n  x |\n    one\n     two\n  y 1\n}");
+}
+
+#[test]
+fn group_then_flatten_round_trips() {
+    let source = "a.b 1\na.c 2\nd 3\n";
+    let mut doc = Document::parse(source);
+    doc.group(".").unwrap();
+    doc.flatten().unwrap();
+    assert_eq!(doc.text(), source);
+}
+
+#[test]
+fn refuses_documents_with_syntax_errors() {
+    let mut doc = Document::parse("a. {\n  b 1\n");
+    assert_eq!(doc.flatten(), Err(RestructureError::SyntaxErrors));
+    assert_eq!(doc.group("."), Err(RestructureError::SyntaxErrors));
+    assert_eq!(doc.text(), "a. {\n  b 1\n");
+}
+
+#[test]
+fn group_refuses_an_empty_separator() {
+    let mut doc = Document::parse("a.b 1\na.c 2\n");
+    assert_eq!(doc.group(""), Err(RestructureError::EmptySeparator));
+    assert_eq!(doc.text(), "a.b 1\na.c 2\n");
+}
@@ -1 +1,56 @@
+use core::fmt;
+use mical_cli_config::parse_source_file;
+use mical_cli_parser::ParseOptions;
 
+mod sort;
+
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Sort entries and prefix blocks by key, see [`sort`].
+    pub sort_entries: bool,
+    /// The extensions of the language to accept.
+    pub parse: ParseOptions,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// The source has syntax errors, so its structure cannot be trusted.
+    SyntaxErrors,
+    /// Sorting would change the order of the values of these duplicated keys.
+    ReordersDuplicates(Vec<String>),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::SyntaxErrors => f.write_str("the source has syntax errors"),
+            FormatError::ReordersDuplicates(keys) => {
+                write!(f, "sorting would reorder the values of duplicated key")?;
+                if keys.len() > 1 {
+                    f.write_str("s")?;
+                }
+                for (i, key) in keys.iter().enumerate() {
+                    f.write_str(if i == 0 { " " } else { ", " })?;
+                    write!(f, "'{key}'")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Formats `text` according to `options`.
+pub fn format(text: &str, options: &Options) -> Result<String, FormatError> {
+    let (source_file, errors) = parse_source_file(text, &options.parse);
+    if !errors.is_empty() {
+        return Err(FormatError::SyntaxErrors);
+    }
+    if !options.sort_entries {
+        return Ok(text.to_owned());
+    }
+    sort::sort(&source_file, &options.parse)
+}
+
+pub use sort::sort;
@@ -0,0 +1,111 @@
+use crate::FormatError;
+use mical_cli_config::{Config, key_text, parse_source_file};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{
+    SyntaxElement, SyntaxKind, SyntaxNode,
+    ast::{self, AstNode},
+};
+use std::collections::HashSet;
+
+/// Sorts the entries and prefix blocks of `source_file` by key, at the top level and within each
+/// prefix block.
+///
+/// Comment lines directly above an item move with it. Blank lines and directives are barriers:
+/// items are only sorted among the neighbours they are not separated from by one. The sort is
+/// stable, so entries of the same key keep their relative order; if the resulting file would still
+/// evaluate the values of a duplicated key in another order (say, `a.b` both inside an `a.` block
+/// and next to it), nothing is sorted and the keys are reported instead. The sorted text is parsed
+/// again with `options`, which should be the ones `source_file` was parsed with.
+pub fn sort(source_file: &ast::SourceFile, options: &ParseOptions) -> Result<String, FormatError> {
+    let mut sorted = String::new();
+    let mut missing_final_newline = false;
+    write_sorted(source_file.syntax(), &mut sorted, &mut missing_final_newline);
+    if missing_final_newline {
+        sorted.pop();
+    }
+
+    let (before, _) = Config::from_source_file(source_file.clone());
+    let (after, _) = Config::from_source_file(parse_source_file(&sorted, options).0);
+    let mut seen = HashSet::new();
+    let reordered = before
+        .entries()
+        .map(|(key, _)| key)
+        .filter(|key| seen.insert(*key) && !before.query(key).eq(after.query(key)))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    if !reordered.is_empty() {
+        return Err(FormatError::ReordersDuplicates(reordered));
+    }
+    Ok(sorted)
+}
+
+/// An entry or prefix block with its indentation and the comment lines directly above it.
+struct Unit {
+    key: String,
+    text: String,
+}
+
+fn write_sorted(node: &SyntaxNode, out: &mut String, missing_final_newline: &mut bool) {
+    let mut units = Vec::new();
+    // Indentation and comment lines not yet claimed by an item.
+    let mut lead = String::new();
+    let mut line_has_comment = false;
+    for element in node.children_with_tokens() {
+        match element {
+            SyntaxElement::Token(token) => match token.kind() {
+                SyntaxKind::SPACE | SyntaxKind::TAB => lead.push_str(token.text()),
+                SyntaxKind::NEWLINE if line_has_comment => {
+                    lead.push_str(token.text());
+                    line_has_comment = false;
+                }
+                _ => {
+                    flush(&mut units, out);
+                    out.push_str(&lead);
+                    out.push_str(token.text());
+                    lead.clear();
+                }
+            },
+            SyntaxElement::Node(child) => {
+                let key = match ast::Item::cast(child.clone()) {
+                    Some(ast::Item::Entry(entry)) => entry.key().as_ref().and_then(key_text),
+                    Some(ast::Item::PrefixBlock(block)) => block.key().as_ref().and_then(key_text),
+                    _ => None,
+                };
+                match key {
+                    _ if child.kind() == SyntaxKind::COMMENT => {
+                        lead.push_str(&child.to_string());
+                        line_has_comment = true;
+                    }
+                    Some(key) => {
+                        let mut text = std::mem::take(&mut lead);
+                        if child.kind() == SyntaxKind::PREFIX_BLOCK {
+                            write_sorted(&child, &mut text, missing_final_newline);
+                        } else {
+                            text.push_str(&child.to_string());
+                        }
+                        if !text.ends_with('\n') {
+                            text.push('\n');
+                            *missing_final_newline = true;
+                        }
+                        units.push(Unit { key, text });
+                    }
+                    None => {
+                        flush(&mut units, out);
+                        out.push_str(&lead);
+                        out.push_str(&child.to_string());
+                        lead.clear();
+                    }
+                }
+            }
+        }
+    }
+    flush(&mut units, out);
+    out.push_str(&lead);
+}
+
+fn flush(units: &mut Vec<Unit>, out: &mut String) {
+    units.sort_by(|a, b| a.key.cmp(&b.key));
+    for unit in units.drain(..) {
+        out.push_str(&unit.text);
+    }
+}
@@ -0,0 +1,69 @@
+use mical_cli_formatter::{FormatError, Options, format};
+use pretty_assertions::assert_eq;
+
+fn sort(text: &str) -> Result<String, FormatError> {
+    format(text, &Options { sort_entries: true, ..Default::default() })
+}
+
+#[test]
+fn sorts_top_level_and_blocks() {
+    let source = "\
+zeta 1
+# about alpha
+# (two lines)
+alpha 2
+server. {
+  port 80
+  host h
+}
+beta 3
+";
+    assert_eq!(
+        sort(source).unwrap(),
+        "\
+# about alpha
+# (two lines)
+alpha 2
+beta 3
+server. {
+  host h
+  port 80
+}
+zeta 1
+"
+    );
+}
+
+#[test]
+fn blank_lines_and_directives_are_barriers() {
+    let source = "c 1\nb 2\n\n# free comment\n\nz 1\ny 2\n#directive x\nb 1\na 2\n";
+    assert_eq!(
+        sort(source).unwrap(),
+        "b 2\nc 1\n\n# free comment\n\ny 2\nz 1\n#directive x\na 2\nb 1\n"
+    );
+}
+
+#[test]
+fn duplicates_keep_their_order() {
+    assert_eq!(sort("tag x\nb 1\ntag a\na 1\n").unwrap(), "a 1\nb 1\ntag x\ntag a\n");
+}
+
+#[test]
+fn refuses_to_reorder_duplicate_values() {
+    let source = "a.b 1\na. {\n  b 2\n}\n";
+    assert_eq!(sort(source), Err(FormatError::ReordersDuplicates(vec!["a.b".to_owned()])));
+}
+
+#[test]
+fn keeps_missing_final_newline() {
+    assert_eq!(sort("b |\n  text\na 1").unwrap(), "a 1\nb |\n  text");
+}
+
+#[test]
+fn sorting_is_idempotent_and_optional() {
+    let source = "b 1\na. {\n  d 1\n  c 2\n}\n";
+    let sorted = sort(source).unwrap();
+    assert_eq!(sort(&sorted).unwrap(), sorted);
+    assert_eq!(format(source, &Options::default()).unwrap(), source);
+    assert_eq!(sort("a {\n"), Err(FormatError::SyntaxErrors));
+}
@@ -0,0 +1,77 @@
+//! Compares the lexer against the char-by-char reference lexer: `cargo bench -p mical-cli-lexer`.
+
+#[path = "../tests/reference/mod.rs"]
+mod reference;
+
+use std::{fmt::Write, hint::black_box, time::Instant};
+
+/// A generated config of about `size` bytes, shaped like the large machine-written files the
+/// lexer is tuned for: long keys, quoted strings with escapes, numbers, comments and non-ASCII text.
+fn corpus(size: usize) -> String {
+    let mut text = String::with_capacity(size + 256);
+    let mut i = 0u64;
+    while text.len() < size {
+        let service = i % 97;
+        writeln!(text, "# service {service}, generated").unwrap();
+        writeln!(text, "services.svc-{service}. {{").unwrap();
+        writeln!(text, "  name \"service number {i}\\twith a tab\"").unwrap();
+        writeln!(text, "  enabled {}", i.is_multiple_of(3)).unwrap();
+        writeln!(text, "  port {}", 1024 + i % 50000).unwrap();
+        writeln!(text, "  mask 0x{:08x}", i.wrapping_mul(0x9e37_79b9)).unwrap();
+        writeln!(text, "  description a plain line string for entry {i} — with ünïcödé").unwrap();
+        writeln!(text, "  'quoted key {i}' 'single \\'quoted\\' value'").unwrap();
+        writeln!(text, "  script |").unwrap();
+        writeln!(text, "    echo start {i}").unwrap();
+        writeln!(text, "    exec /usr/bin/service-{service} --flag").unwrap();
+        writeln!(text, "}}").unwrap();
+        i += 1;
+    }
+    text
+}
+
+/// A generated config of about `size` bytes whose values are long: the case byte-level searches
+/// speed up most.
+fn long_values_corpus(size: usize) -> String {
+    let mut text = String::with_capacity(size + 1024);
+    let blob =
+        "QUJDREVGR0hJSktMTU5PUFFSU1RVVldYWVo0NTY3ODkwYWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXo".repeat(8);
+    let mut i = 0u64;
+    while text.len() < size {
+        writeln!(text, "assets.a{i}.data {blob}").unwrap();
+        writeln!(
+            text,
+            "assets.a{i}.note \"{}\\n{}\"",
+            "lorem ipsum dolor ".repeat(12),
+            "ü".repeat(40)
+        )
+        .unwrap();
+        i += 1;
+    }
+    text
+}
+
+fn bench(name: &str, text: &str, lex: impl Fn(&str) -> usize) {
+    const RUNS: u32 = 30;
+    let tokens = lex(text); // warm up
+    // The fastest run is the one least disturbed by the rest of the machine.
+    let elapsed = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(lex(black_box(text)));
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+    let throughput = text.len() as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{name:<10} {tokens:>9} tokens {elapsed:>12.2?} {throughput:>9.1} MiB/s");
+}
+
+fn main() {
+    for (name, text) in
+        [("mixed", corpus(32 * 1024 * 1024)), ("long values", long_values_corpus(32 * 1024 * 1024))]
+    {
+        println!("{name} corpus: {} bytes", text.len());
+        bench("reference", &text, |text| reference::tokenize(text).count());
+        bench("lexer", &text, |text| mical_cli_lexer::tokenize(text).count());
+    }
+}
@@ -1,28 +1,30 @@
-use core::str::Chars;
 use mical_cli_syntax::token::{Token, TokenKind};
 
+/// Walks the source byte by byte. Every byte the lexer looks for is ASCII, and UTF-8 never uses
+/// ASCII bytes inside a multi-byte character, so tokens always end on character boundaries
+/// without decoding anything.
 #[derive(Debug)]
 pub(crate) struct Cursor<'src> {
-    chars: Chars<'src>,
+    rest: &'src [u8],
     remaining_len: usize,
     #[cfg(debug_assertions)]
-    prev: char,
+    prev: u8,
 }
 
 impl<'src> Cursor<'src> {
+    /// Token lengths are `u32`, so a source longer than `u32::MAX` bytes yields no tokens; the
+    /// parser reports it.
     pub(crate) fn new(source: &'src str) -> Self {
-        if source.len() > u32::MAX as usize {
-            panic!("Source code is too large");
-        }
+        let source = if source.len() > u32::MAX as usize { "" } else { source };
         Cursor {
-            chars: source.chars(),
+            rest: source.as_bytes(),
             remaining_len: source.len(),
             #[cfg(debug_assertions)]
-            prev: '\0',
+            prev: b'\0',
         }
     }
 
-    pub(crate) fn prev(&self) -> char {
+    pub(crate) fn prev(&self) -> u8 {
         #[cfg(debug_assertions)]
         {
             self.prev
@@ -33,38 +35,45 @@ impl<'src> Cursor<'src> {
         }
     }
 
-    pub(crate) fn eat_while(&mut self, pred: impl Fn(char) -> bool) {
-        while let Some(c) = self.peek() {
-            if pred(c) {
-                self.next();
-            } else {
-                break;
-            }
+    pub(crate) fn eat_while(&mut self, pred: impl Fn(u8) -> bool) {
+        while let [b, rest @ ..] = self.rest
+            && pred(*b)
+        {
+            self.rest = rest;
         }
     }
 
-    pub(crate) fn peek(&self) -> Option<char> {
-        // `.next()` optimizes better than `.nth(0)`
-        self.chars.clone().next()
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.rest.first().copied()
+    }
+
+    /// The bytes not consumed yet.
+    pub(crate) fn rest(&self) -> &'src [u8] {
+        self.rest
+    }
+
+    /// Consumes `n` bytes of [`Cursor::rest`].
+    pub(crate) fn advance(&mut self, n: usize) {
+        self.rest = &self.rest[n..];
     }
 
     pub(crate) fn bump(&mut self, kind: TokenKind) -> Token {
-        let current_len = self.chars.as_str().len();
-        let len = (self.remaining_len - current_len) as u32;
-        self.remaining_len = current_len;
+        let len = (self.remaining_len - self.rest.len()) as u32;
+        self.remaining_len = self.rest.len();
         Token { kind, len }
     }
 }
 
 impl Iterator for Cursor<'_> {
-    type Item = char;
+    type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let c = self.chars.next()?;
+        let (&b, rest) = self.rest.split_first()?;
+        self.rest = rest;
         #[cfg(debug_assertions)]
         {
-            self.prev = c;
+            self.prev = b;
         }
-        Some(c)
+        Some(b)
     }
 }
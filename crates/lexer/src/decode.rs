@@ -0,0 +1,52 @@
+use mical_cli_syntax::{SyntaxError, TextRange, TextSize};
+use std::{borrow::Cow, fmt::Write};
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Text decoded from the bytes of a file, ready for [`tokenize`](crate::tokenize).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decoded<'b> {
+    /// The text without its byte order mark, with every invalid UTF-8 sequence replaced by
+    /// U+FFFD.
+    pub text: Cow<'b, str>,
+    /// Whether the bytes started with a UTF-8 byte order mark.
+    pub has_bom: bool,
+    /// One error per invalid sequence, at its replacement character in `text`.
+    pub errors: Vec<SyntaxError>,
+}
+
+/// Decodes `bytes` as UTF-8, leaving out a leading byte order mark.
+///
+/// Invalid sequences are decoded lossily, so the rest of the text can still be read, and are
+/// reported in [`Decoded::errors`].
+pub fn decode(bytes: &[u8]) -> Decoded<'_> {
+    let (bytes, has_bom) = match bytes.strip_prefix(BOM) {
+        Some(rest) => (rest, true),
+        None => (bytes, false),
+    };
+    if let Ok(text) = str::from_utf8(bytes) {
+        return Decoded { text: Cow::Borrowed(text), has_bom, errors: Vec::new() };
+    }
+
+    let mut text = String::with_capacity(bytes.len());
+    let mut errors = Vec::new();
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        let invalid = chunk.invalid();
+        if invalid.is_empty() {
+            continue;
+        }
+        let mut sequence = String::new();
+        for b in invalid {
+            write!(sequence, "\\x{b:02X}").unwrap();
+        }
+        let start = TextSize::new(text.len() as u32);
+        text.push(char::REPLACEMENT_CHARACTER);
+        let range = TextRange::at(start, TextSize::of(char::REPLACEMENT_CHARACTER));
+        errors.push(SyntaxError::new(
+            format!("invalid UTF-8 sequence '{sequence}', read as U+FFFD"),
+            range,
+        ));
+    }
+    Decoded { text: Cow::Owned(text), has_bom, errors }
+}
@@ -4,6 +4,9 @@ use std::iter;
 mod cursor;
 use cursor::Cursor;
 
+mod decode;
+pub use decode::{Decoded, decode};
+
 struct TokenStreamImpl<'src, I: Iterator<Item = Token>> {
     source: &'src str,
     iter: I,
@@ -28,33 +31,33 @@ pub fn tokenize(source: &str) -> impl TokenStream<'_> {
 
 fn advance_token(cursor: &mut Cursor) -> Option<Token> {
     let kind = match cursor.next()? {
-        't' => true_or_word(cursor),
-        'f' => false_or_word(cursor),
-        '\t' => {
-            cursor.eat_while(|c| c == '\t');
+        b't' => true_or_word(cursor),
+        b'f' => false_or_word(cursor),
+        b'\t' => {
+            cursor.eat_while(|b| b == b'\t');
             Tab
         }
-        '\n' => Newline,
-        '\r' => {
-            if let Some('\n') = cursor.peek() {
+        b'\n' => Newline,
+        b'\r' => {
+            if let Some(b'\n') = cursor.peek() {
                 cursor.next();
             }
             Newline
         }
-        ' ' => {
-            cursor.eat_while(|c| c == ' ');
+        b' ' => {
+            cursor.eat_while(|b| b == b' ');
             Space
         }
-        '}' => CloseBrace,
-        '>' => Greater,
-        '-' => Minus,
-        '{' => OpenBrace,
-        '|' => Pipe,
-        '+' => Plus,
-        '#' => Sharp,
-        '"' => string::<'"'>(cursor),
-        '\'' => string::<'\''>(cursor),
-        c @ '0'..='9' => integer_or_word(cursor, c),
+        b'}' => CloseBrace,
+        b'>' => Greater,
+        b'-' => Minus,
+        b'{' => OpenBrace,
+        b'|' => Pipe,
+        b'+' => Plus,
+        b'#' => Sharp,
+        b'"' => string::<b'"'>(cursor),
+        b'\'' => string::<b'\''>(cursor),
+        b @ b'0'..=b'9' => integer_or_word(cursor, b),
         _ => word(cursor),
     };
     let token = cursor.bump(kind);
@@ -62,85 +65,72 @@ fn advance_token(cursor: &mut Cursor) -> Option<Token> {
 }
 
 fn true_or_word(cursor: &mut Cursor) -> TokenKind {
-    debug_assert!(cursor.prev() == 't');
-    if let Some('r') = cursor.peek() {
-        cursor.next();
-        if let Some('u') = cursor.peek() {
-            cursor.next();
-            if let Some('e') = cursor.peek() {
-                cursor.next();
-                return True;
-            }
-        }
+    debug_assert!(cursor.prev() == b't');
+    if cursor.rest().starts_with(b"rue") {
+        cursor.advance(3);
+        return True;
     }
     word(cursor)
 }
 
 fn false_or_word(cursor: &mut Cursor) -> TokenKind {
-    debug_assert!(cursor.prev() == 'f');
-    if let Some('a') = cursor.peek() {
-        cursor.next();
-        if let Some('l') = cursor.peek() {
-            cursor.next();
-            if let Some('s') = cursor.peek() {
-                cursor.next();
-                if let Some('e') = cursor.peek() {
-                    cursor.next();
-                    return False;
-                }
-            }
-        }
+    debug_assert!(cursor.prev() == b'f');
+    if cursor.rest().starts_with(b"alse") {
+        cursor.advance(4);
+        return False;
     }
     word(cursor)
 }
 
-fn string<const Q: char>(cursor: &mut Cursor) -> TokenKind {
-    const { assert!(Q == '"' || Q == '\'') };
+fn string<const Q: u8>(cursor: &mut Cursor) -> TokenKind {
+    const { assert!(Q == b'"' || Q == b'\'') };
     debug_assert!(cursor.prev() == Q);
 
     let mut terminated = false;
-    while let Some(c) = cursor.peek() {
-        match c {
-            '\\' => {
+    loop {
+        let Some(i) = find_line_end_or(cursor.rest(), Q, b'\\') else {
+            cursor.advance(cursor.rest().len());
+            break;
+        };
+        cursor.advance(i);
+        match cursor.peek() {
+            Some(b'\\') => {
                 cursor.next();
                 let peek = cursor.peek();
-                if peek == Some(Q) || peek == Some('\\') {
+                if peek == Some(Q) || peek == Some(b'\\') {
                     cursor.next();
                 }
             }
-            '\n' | '\r' => {
+            Some(b'\n' | b'\r') => {
                 break;
             }
-            q if q == Q => {
+            _ => {
                 terminated = true;
                 cursor.next();
                 break;
             }
-            _ => {
-                cursor.next();
-            }
         }
     }
     String {
         is_terminated: terminated,
         quote: const {
             match Q {
-                '"' => Quote::Double,
-                '\'' => Quote::Single,
+                b'"' => Quote::Double,
+                b'\'' => Quote::Single,
                 _ => unreachable!(),
             }
         },
     }
 }
 
-fn integer_or_word(cursor: &mut Cursor, first_digit: char) -> TokenKind {
+fn integer_or_word(cursor: &mut Cursor, first_digit: u8) -> TokenKind {
     debug_assert!(first_digit.is_ascii_digit()); // 0..=9
     fn eat_decimal_digits(cursor: &mut Cursor) -> bool {
         let mut has_digits = false;
-        while let Some(c) = cursor.peek() {
-            match c {
-                '_' => (),
-                '0'..='9' => has_digits = true,
+        while let Some(b) = cursor.peek() {
+            match b {
+                b'_' => (),
+                b'0'..=b'9' => has_digits = true,
                 _ => break,
             };
             cursor.next();
@@ -149,10 +139,10 @@ fn integer_or_word(cursor: &mut Cursor, first_digit: char) -> TokenKind {
     }
     fn eat_hexadecimal_digits(cursor: &mut Cursor) -> bool {
         let mut has_digits = false;
-        while let Some(c) = cursor.peek() {
-            match c {
-                '_' => (),
-                '0'..='9' | 'a'..='f' | 'A'..='F' => has_digits = true,
+        while let Some(b) = cursor.peek() {
+            match b {
+                b'_' => (),
+                b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' => has_digits = true,
                 _ => break,
             };
             cursor.next();
@@ -160,24 +150,24 @@ fn integer_or_word(cursor: &mut Cursor, first_digit: char) -> TokenKind {
         has_digits
     }
     let mut radix = Radix::Decimal;
-    let has_digits = if first_digit == '0' {
+    let has_digits = if first_digit == b'0' {
         match cursor.peek() {
-            Some('b') => {
+            Some(b'b') => {
                 radix = Radix::Binary;
                 cursor.next();
                 eat_decimal_digits(cursor)
             }
-            Some('o') => {
+            Some(b'o') => {
                 radix = Radix::Octal;
                 cursor.next();
                 eat_decimal_digits(cursor)
             }
-            Some('x') => {
+            Some(b'x') => {
                 radix = Radix::Hexadecimal;
                 cursor.next();
                 eat_hexadecimal_digits(cursor)
             }
-            Some('0'..='9' | '_') => eat_decimal_digits(cursor),
+            Some(b'0'..=b'9' | b'_') => eat_decimal_digits(cursor),
             _ => true, // single '0'
         }
     } else {
@@ -185,12 +175,34 @@ fn integer_or_word(cursor: &mut Cursor, first_digit: char) -> TokenKind {
         true // first_digit itself is always a valid digit
     };
     match cursor.peek() {
-        Some('\t' | '\n' | '\r' | ' ') | None => Numeral { radix, is_empty: !has_digits },
+        Some(b'\t' | b'\n' | b'\r' | b' ') | None => Numeral { radix, is_empty: !has_digits },
         _ => word(cursor),
     }
 }
 
 fn word(cursor: &mut Cursor) -> TokenKind {
-    cursor.eat_while(|c| !matches!(c, '\t' | '\n' | '\r' | ' '));
+    let rest = cursor.rest();
+    cursor.advance(find_line_end_or(rest, b' ', b'\t').unwrap_or(rest.len()));
     Word
 }
+
+/// Finds the first line break, `a` or `b` in `bytes`.
+#[inline(always)]
+fn find_line_end_or(bytes: &[u8], a: u8, b: u8) -> Option<usize> {
+    // Most tokens are short, and a plain loop finds their end before a vectorized search would
+    // have started.
+    const SHORT: usize = 32;
+    let head = &bytes[..bytes.len().min(SHORT)];
+    match head.iter().position(|&c| matches!(c, b'\n' | b'\r') || c == a || c == b) {
+        Some(i) => Some(i),
+        None => search_line_end_or(&bytes[head.len()..], a, b).map(|i| head.len() + i),
+    }
+}
+
+#[inline(never)]
+fn search_line_end_or(bytes: &[u8], a: u8, b: u8) -> Option<usize> {
+    let found = memchr::memchr3(a, b, b'\n', bytes);
+    // `\r` gets a second search, over what precedes the first match only.
+    let end = found.unwrap_or(bytes.len());
+    memchr::memchr(b'\r', &bytes[..end]).or(found)
+}
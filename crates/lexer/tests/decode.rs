@@ -0,0 +1,55 @@
+use mical_cli_lexer::decode;
+use mical_cli_syntax::{TextRange, TextSize};
+use pretty_assertions::assert_eq;
+
+fn range(start: u32, end: u32) -> TextRange {
+    TextRange::new(TextSize::new(start), TextSize::new(end))
+}
+
+#[test]
+fn valid_text_is_borrowed() {
+    let decoded = decode(b"a 1\n");
+    assert_eq!(decoded.text, "a 1\n");
+    assert!(matches!(decoded.text, std::borrow::Cow::Borrowed(_)));
+    assert!(!decoded.has_bom);
+    assert_eq!(decoded.errors, []);
+}
+
+#[test]
+fn byte_order_mark_is_left_out() {
+    let decoded = decode(b"\xEF\xBB\xBFa 1\n");
+    assert_eq!(decoded.text, "a 1\n");
+    assert!(decoded.has_bom);
+    assert_eq!(decoded.errors, []);
+
+    // Only a leading one is a byte order mark.
+    let decoded = decode("a \u{feff}\n".as_bytes());
+    assert_eq!(decoded.text, "a \u{feff}\n");
+    assert!(!decoded.has_bom);
+}
+
+#[test]
+fn invalid_sequences_are_replaced_and_reported() {
+    let decoded = decode(b"\xEF\xBB\xBFa x\xFFy\nb \xE3\x81\n");
+    assert_eq!(decoded.text, "a x\u{fffd}y\nb \u{fffd}\n");
+    assert!(decoded.has_bom);
+    let errors = decoded.errors.iter().map(|err| (err.message(), err.range())).collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            ("invalid UTF-8 sequence '\\xFF', read as U+FFFD", range(3, 6)),
+            ("invalid UTF-8 sequence '\\xE3\\x81', read as U+FFFD", range(10, 13)),
+        ]
+    );
+    for err in &decoded.errors {
+        assert_eq!(&decoded.text[err.range()], "\u{fffd}");
+    }
+}
+
+#[test]
+fn truncated_bom_is_invalid() {
+    let decoded = decode(b"\xEF\xBBa 1\n");
+    assert_eq!(decoded.text, "\u{fffd}a 1\n");
+    assert!(!decoded.has_bom);
+    assert_eq!(decoded.errors.len(), 1);
+}
@@ -0,0 +1,60 @@
+mod reference;
+
+use proptest::property_test;
+use std::fs;
+
+fn assert_same_tokens(source: &str) {
+    let actual = mical_cli_lexer::tokenize(source).collect::<Vec<_>>();
+    let expected = reference::tokenize(source).collect::<Vec<_>>();
+    pretty_assertions::assert_eq!(actual, expected, "source: {source:?}");
+}
+
+#[test]
+fn test_suite() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../test-suite");
+    let mut count = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path().join("input.mical");
+        if let Ok(source) = fs::read_to_string(&path) {
+            assert_same_tokens(&source);
+            assert_same_tokens(&source.replace('\n', "\r\n"));
+            count += 1;
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn edge_cases() {
+    for source in [
+        "",
+        "t",
+        "tr\r",
+        "true\r\n",
+        "falsee",
+        "fals\te",
+        "'\\",
+        "\"\\\"",
+        "'a\\'b'",
+        "\"é\\é\"",
+        "\"unterminated\r",
+        "é ü\tß",
+        "0x\r",
+        "0b1_\u{a0}",
+        "\u{feff}key value",
+    ] {
+        assert_same_tokens(source);
+    }
+}
+
+#[property_test]
+fn matches_reference(
+    #[strategy = r#"[ \t\r\n"'\\{}|>+#\-_0-9abefilorstuxé\u{3042}]{0,64}"#] source: String,
+) {
+    assert_same_tokens(&source);
+}
+
+#[property_test]
+fn matches_reference_on_any_text(source: String) {
+    assert_same_tokens(&source);
+}
@@ -0,0 +1,249 @@
+//! Lint rules over the syntax tree and the evaluated [`Config`] of a MICAL source.
+//!
+//! Every [`Rule`] reports [`Diagnostic`]s at the [`Severity`] configured in [`Options`]. A
+//! `#lint-ignore` directive suppresses diagnostics on the line below it, either all of them or
+//! only those of the rules it lists (`#lint-ignore duplicate-key trailing-whitespace`).
+
+use core::fmt;
+use mical_cli_config::{Config, Pattern, parse_source_file, walk_items};
+use mical_cli_parser::ParseOptions;
+pub use mical_cli_syntax::Fix;
+use mical_cli_syntax::{
+    SyntaxError, SyntaxKind, TextEdit, TextRange, TextSize,
+    ast::{self, AstNode},
+};
+use std::collections::HashMap;
+
+mod rules;
+pub use rules::{Naming, QuoteStyle};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Off,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Off => "off",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub range: TextRange,
+    pub message: String,
+    /// An edit that resolves the diagnostic without changing what the source evaluates to.
+    pub fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(rule: &'static str, range: TextRange, message: impl Into<String>) -> Self {
+        Diagnostic { rule, severity: Severity::Warning, range, message: message.into(), fix: None }
+    }
+
+    pub(crate) fn with_fix(mut self, message: impl Into<String>, edit: TextEdit) -> Self {
+        self.fix = Some(Fix::new(message, edit));
+        self
+    }
+}
+
+pub trait Rule: Sync {
+    /// The kebab-case name used in options and `#lint-ignore` directives.
+    fn name(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, ctx: &Context<'_>, diagnostics: &mut Vec<Diagnostic>);
+}
+
+/// Returns every built-in rule.
+pub fn rules() -> &'static [&'static dyn Rule] {
+    rules::ALL
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Key segment separator.
+    pub separator: String,
+    /// Keys that may occur more than once.
+    pub allowed_duplicates: Vec<Pattern>,
+    /// Naming convention for key segments; `naming-convention` reports nothing without one.
+    pub naming: Option<Naming>,
+    pub quote_style: QuoteStyle,
+    /// The extensions of the language the source uses.
+    pub parse: ParseOptions,
+    severities: HashMap<&'static str, Severity>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            separator: ".".to_owned(),
+            allowed_duplicates: Vec::new(),
+            naming: None,
+            quote_style: QuoteStyle::Consistent,
+            parse: ParseOptions::default(),
+            severities: HashMap::new(),
+        }
+    }
+}
+
+impl Options {
+    /// Sets the severity of the rule named `rule`, returning `false` if there is no such rule.
+    pub fn set_severity(&mut self, rule: &str, severity: Severity) -> bool {
+        match rules().iter().find(|r| r.name() == rule) {
+            Some(r) => {
+                self.severities.insert(r.name(), severity);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn severity(&self, rule: &dyn Rule) -> Severity {
+        self.severities.get(rule.name()).copied().unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// What a [`Rule`] gets to look at.
+pub struct Context<'a> {
+    pub text: &'a str,
+    pub source_file: &'a ast::SourceFile,
+    pub config: &'a Config<'a>,
+    pub options: &'a Options,
+    entries: Vec<(ast::Entry, String)>,
+}
+
+impl Context<'_> {
+    /// Returns every entry in document order with its full key.
+    pub fn entries(&self) -> &[(ast::Entry, String)] {
+        &self.entries
+    }
+}
+
+/// The result of [`lint`].
+pub struct Report {
+    pub syntax_errors: Vec<SyntaxError>,
+    /// Sorted by position.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub fn lint(text: &str, options: &Options) -> Report {
+    let (source_file, syntax_errors) = parse_source_file(text, &options.parse);
+    let (config, _) = Config::from_source_file(source_file.clone());
+    let mut entries = Vec::new();
+    walk_items(source_file.items(), "", &mut |item, _, _, key| {
+        if let ast::Item::Entry(entry) = item {
+            entries.push((entry.clone(), key.to_owned()));
+        }
+    });
+    let ctx = Context { text, source_file: &source_file, config: &config, options, entries };
+
+    let suppressions = suppressions(&ctx);
+    let mut diagnostics = Vec::new();
+    for rule in rules() {
+        let severity = options.severity(*rule);
+        if severity == Severity::Off {
+            continue;
+        }
+        let start = diagnostics.len();
+        rule.check(&ctx, &mut diagnostics);
+        for diagnostic in &mut diagnostics[start..] {
+            diagnostic.severity = severity;
+        }
+    }
+    diagnostics.retain(|d| {
+        let line = line_col(text, d.range.start()).0;
+        !suppressions
+            .iter()
+            .any(|(l, rules)| *l == line && (rules.is_empty() || rules.iter().any(|r| r == d.rule)))
+    });
+    for diagnostic in &mut diagnostics {
+        if diagnostic
+            .fix
+            .as_ref()
+            .is_some_and(|fix| !preserves_config(&ctx, syntax_errors.len(), &fix.edit))
+        {
+            diagnostic.fix = None;
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.range.start(), d.range.end()));
+    Report { syntax_errors, diagnostics }
+}
+
+/// Applies every available fix, repeating until none is left, and returns the fixed text with the
+/// number of fixes applied.
+pub fn fix(text: &str, options: &Options) -> (String, usize) {
+    let mut text = text.to_owned();
+    let mut applied = 0;
+    // Fixes never overlap by much, so this settles in a couple of rounds.
+    for _ in 0..8 {
+        let report = lint(&text, options);
+        let mut indels = Vec::new();
+        let mut end = TextSize::from(0);
+        let mut count = 0;
+        for fix in report.diagnostics.iter().filter_map(|d| d.fix.as_ref()) {
+            let indels_of = fix.edit.indels();
+            let Some(first) = indels_of.first() else { continue };
+            if first.delete.start() < end || (count > 0 && first.delete.start() == end) {
+                continue;
+            }
+            end = indels_of.last().unwrap().delete.end();
+            indels.extend(indels_of.iter().cloned());
+            count += 1;
+        }
+        if count == 0 {
+            break;
+        }
+        TextEdit::new(indels).apply(&mut text);
+        applied += count;
+    }
+    (text, applied)
+}
+
+/// Returns the 1-based line and column (in characters) of `offset`.
+pub fn line_col(text: &str, offset: TextSize) -> (usize, usize) {
+    let before = &text[..usize::from(offset)];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// Returns the lines (1-based) suppressed by `#lint-ignore` directives, with the rules they name.
+fn suppressions(ctx: &Context<'_>) -> Vec<(usize, Vec<String>)> {
+    ctx.source_file
+        .syntax()
+        .descendants()
+        .filter(|node| node.kind() == SyntaxKind::DIRECTIVE)
+        .filter_map(ast::Directive::cast)
+        .filter(|directive| directive.name().is_some_and(|name| name.text() == "lint-ignore"))
+        .map(|directive| {
+            let line = line_col(ctx.text, directive.syntax().text_range().start()).0;
+            let rules = directive
+                .args()
+                .and_then(|args| args.string())
+                .map(|args| args.text().split_whitespace().map(str::to_owned).collect())
+                .unwrap_or_default();
+            (line + 1, rules)
+        })
+        .collect()
+}
+
+/// Whether applying `edit` keeps the entries of the config of `ctx` and adds no syntax errors to
+/// the `syntax_errors` already in its text.
+fn preserves_config(ctx: &Context<'_>, syntax_errors: usize, edit: &TextEdit) -> bool {
+    let mut fixed = ctx.text.to_owned();
+    edit.apply(&mut fixed);
+    let (source_file, errors) = parse_source_file(&fixed, &ctx.options.parse);
+    let (fixed_config, _) = Config::from_source_file(source_file);
+    errors.len() <= syntax_errors && ctx.config.entries().eq(fixed_config.entries())
+}
@@ -0,0 +1,20 @@
+use crate::Rule;
+
+mod duplicate_key;
+mod naming_convention;
+mod prefix_separator;
+mod quote_style;
+mod suspicious_string;
+mod trailing_whitespace;
+
+pub use naming_convention::Naming;
+pub use quote_style::QuoteStyle;
+
+pub(crate) static ALL: &[&dyn Rule] = &[
+    &duplicate_key::DuplicateKey,
+    &prefix_separator::PrefixSeparator,
+    &trailing_whitespace::TrailingWhitespace,
+    &suspicious_string::SuspiciousString,
+    &quote_style::QuoteStyleRule,
+    &naming_convention::NamingConvention,
+];
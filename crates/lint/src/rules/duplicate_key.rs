@@ -0,0 +1,35 @@
+use crate::{Context, Diagnostic, Rule, line_col};
+use mical_cli_syntax::ast::AstNode;
+use std::collections::HashMap;
+
+/// Reports every entry of a key after the first, unless the key is in
+/// [`Options::allowed_duplicates`](crate::Options::allowed_duplicates).
+pub(crate) struct DuplicateKey;
+
+impl Rule for DuplicateKey {
+    fn name(&self) -> &'static str {
+        "duplicate-key"
+    }
+
+    fn check(&self, ctx: &Context<'_>, diagnostics: &mut Vec<Diagnostic>) {
+        let mut first_line = HashMap::new();
+        for (entry, key) in ctx.entries() {
+            let Some(key_node) = entry.key() else { continue };
+            let range = key_node.syntax().text_range();
+            let Some(&line) = first_line.get(key.as_str()) else {
+                first_line.insert(key.as_str(), line_col(ctx.text, range.start()).0);
+                continue;
+            };
+            if ctx.config.query(key).nth(1).is_none()
+                || ctx.options.allowed_duplicates.iter().any(|pattern| pattern.matches(key))
+            {
+                continue;
+            }
+            diagnostics.push(Diagnostic::new(
+                self.name(),
+                range,
+                format!("duplicate key '{key}' (first defined on line {line})"),
+            ));
+        }
+    }
+}
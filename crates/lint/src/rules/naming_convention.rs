@@ -0,0 +1,88 @@
+use crate::{Context, Diagnostic, Rule};
+use core::{fmt, str::FromStr};
+use mical_cli_config::key_text;
+use mical_cli_syntax::ast::{self, AstNode};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Naming {
+    /// `max-connections`
+    KebabCase,
+    /// `max_connections`
+    SnakeCase,
+    /// `maxConnections`
+    CamelCase,
+}
+
+impl Naming {
+    fn matches(self, segment: &str) -> bool {
+        let lower_words = |sep: char| {
+            segment.split(sep).all(|word| {
+                !word.is_empty()
+                    && word.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+            })
+        };
+        match self {
+            Naming::KebabCase => lower_words('-'),
+            Naming::SnakeCase => lower_words('_'),
+            Naming::CamelCase => {
+                segment.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                    && segment.chars().all(|c| c.is_ascii_alphanumeric())
+            }
+        }
+    }
+}
+
+impl FromStr for Naming {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kebab-case" => Ok(Naming::KebabCase),
+            "snake_case" => Ok(Naming::SnakeCase),
+            "camelCase" => Ok(Naming::CamelCase),
+            _ => Err(format!(
+                "unknown naming convention: '{s}' (supported: kebab-case, snake_case, camelCase)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Naming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Naming::KebabCase => "kebab-case",
+            Naming::SnakeCase => "snake_case",
+            Naming::CamelCase => "camelCase",
+        })
+    }
+}
+
+/// Reports key segments that do not follow [`Options::naming`](crate::Options::naming). Each key
+/// is only checked for the text it spells out itself, so a badly named prefix block is reported
+/// once rather than for every entry in it.
+pub(crate) struct NamingConvention;
+
+impl Rule for NamingConvention {
+    fn name(&self) -> &'static str {
+        "naming-convention"
+    }
+
+    fn check(&self, ctx: &Context<'_>, diagnostics: &mut Vec<Diagnostic>) {
+        let Some(naming) = ctx.options.naming else { return };
+        let separator = ctx.options.separator.as_str();
+        let keys = ctx.source_file.syntax().descendants().filter_map(ast::Key::cast);
+        for key_node in keys {
+            let Some(key) = key_text(&key_node) else { continue };
+            let segments: Vec<&str> =
+                if separator.is_empty() { vec![&key] } else { key.split(separator).collect() };
+            if let Some(segment) =
+                segments.into_iter().find(|s| !s.is_empty() && !naming.matches(s))
+            {
+                diagnostics.push(Diagnostic::new(
+                    self.name(),
+                    key_node.syntax().text_range(),
+                    format!("key segment '{segment}' is not {naming}"),
+                ));
+            }
+        }
+    }
+}
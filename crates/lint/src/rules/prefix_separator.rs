@@ -0,0 +1,45 @@
+use crate::{Context, Diagnostic, Rule};
+use mical_cli_config::key_text;
+use mical_cli_syntax::ast::{self, AstNode};
+
+/// Reports prefix blocks whose key does not end with the separator while some inner key does not
+/// start with it: `http_ {` followed by `port 80` yields `http_port`, which is rarely intended.
+pub(crate) struct PrefixSeparator;
+
+impl Rule for PrefixSeparator {
+    fn name(&self) -> &'static str {
+        "prefix-separator"
+    }
+
+    fn check(&self, ctx: &Context<'_>, diagnostics: &mut Vec<Diagnostic>) {
+        let separator = ctx.options.separator.as_str();
+        if separator.is_empty() {
+            return;
+        }
+        for block in ctx.source_file.syntax().descendants().filter_map(ast::PrefixBlock::cast) {
+            let Some(key_node) = block.key() else { continue };
+            let Some(key) = key_text(&key_node) else { continue };
+            if key.is_empty() || key.ends_with(separator) {
+                continue;
+            }
+            let joined = block.items().find_map(|item| {
+                let inner = match item {
+                    ast::Item::Entry(entry) => entry.key(),
+                    ast::Item::PrefixBlock(block) => block.key(),
+                    ast::Item::Directive(_) => None,
+                };
+                inner.as_ref().and_then(key_text).filter(|inner| !inner.starts_with(separator))
+            });
+            if let Some(inner) = joined {
+                diagnostics.push(Diagnostic::new(
+                    self.name(),
+                    key_node.syntax().text_range(),
+                    format!(
+                        "prefix block key '{key}' does not end with '{separator}', so inner keys \
+                         are joined without it (e.g. '{key}{inner}')"
+                    ),
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{Context, Diagnostic, Rule};
+use mical_cli_syntax::{Indel, SyntaxKind, SyntaxNode, TextEdit, ast::AstNode};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// Whichever quote the file uses most, double quotes on a tie.
+    #[default]
+    Consistent,
+    Double,
+    Single,
+}
+
+impl core::str::FromStr for QuoteStyle {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "consistent" => Ok(QuoteStyle::Consistent),
+            "double" => Ok(QuoteStyle::Double),
+            "single" => Ok(QuoteStyle::Single),
+            _ => Err(format!("unknown quote style: '{s}' (supported: consistent, double, single)")),
+        }
+    }
+}
+
+/// Reports quoted keys and strings that do not use the expected quote character.
+pub(crate) struct QuoteStyleRule;
+
+impl Rule for QuoteStyleRule {
+    fn name(&self) -> &'static str {
+        "quote-style"
+    }
+
+    fn check(&self, ctx: &Context<'_>, diagnostics: &mut Vec<Diagnostic>) {
+        let quoted = ctx
+            .source_file
+            .syntax()
+            .descendants()
+            .filter(|node| {
+                matches!(node.kind(), SyntaxKind::QUOTED_KEY | SyntaxKind::QUOTED_STRING)
+            })
+            .filter_map(|node| Some((quote_of(&node)?, node)))
+            .collect::<Vec<_>>();
+        let expected = match ctx.options.quote_style {
+            QuoteStyle::Double => '"',
+            QuoteStyle::Single => '\'',
+            QuoteStyle::Consistent => {
+                let singles = quoted.iter().filter(|(q, _)| *q == '\'').count();
+                if singles * 2 > quoted.len() { '\'' } else { '"' }
+            }
+        };
+        for (quote, node) in quoted {
+            if quote == expected {
+                continue;
+            }
+            let range = node.text_range();
+            let (found, wanted) = match expected {
+                '"' => ("single", "double"),
+                _ => ("double", "single"),
+            };
+            let mut diagnostic = Diagnostic::new(
+                self.name(),
+                range,
+                format!("{found} quotes, expected {wanted} quotes"),
+            );
+            if let Some(requoted) = requote(&node.to_string(), quote, expected) {
+                let edit = TextEdit::new(vec![Indel::replace(range, requoted)]);
+                diagnostic = diagnostic.with_fix(format!("use {wanted} quotes"), edit);
+            }
+            diagnostics.push(diagnostic);
+        }
+    }
+}
+
+fn quote_of(node: &SyntaxNode) -> Option<char> {
+    match node.first_token()?.kind() {
+        SyntaxKind::DOUBLE_QUOTE => Some('"'),
+        SyntaxKind::SINGLE_QUOTE => Some('\''),
+        _ => None,
+    }
+}
+
+/// Rewrites a closed quoted literal from `from` to `to` quotes, moving escapes along.
+fn requote(text: &str, from: char, to: char) -> Option<String> {
+    let inner = text.strip_prefix(from)?.strip_suffix(from)?;
+    let mut out = String::with_capacity(text.len());
+    out.push(to);
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                c if c == from => out.push(c),
+                c => {
+                    out.push('\\');
+                    out.push(c);
+                }
+            },
+            c if c == to => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(to);
+    Some(out)
+}
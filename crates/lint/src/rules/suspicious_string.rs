@@ -0,0 +1,78 @@
+use crate::{Context, Diagnostic, Rule};
+use mical_cli_config::quoted;
+use mical_cli_syntax::{Indel, SyntaxKind, TextEdit, TextRange, ast};
+
+/// Reports line string values that look like a typed value gone wrong: `tru` or `False` for a
+/// boolean, `0x` or `10 items` for an integer, `"abc` for a quoted string. Quoting the value keeps
+/// it a string while making that explicit.
+pub(crate) struct SuspiciousString;
+
+impl Rule for SuspiciousString {
+    fn name(&self) -> &'static str {
+        "suspicious-string"
+    }
+
+    fn check(&self, ctx: &Context<'_>, diagnostics: &mut Vec<Diagnostic>) {
+        for (entry, _) in ctx.entries() {
+            let Some(ast::Value::LineString(line_string)) = entry.value() else { continue };
+            let Some(token) = line_string.string() else { continue };
+            let Some(looks_like) = looks_like(token.text()) else { continue };
+            let range = token.text_range();
+            // A quoted value cannot be followed by spaces, so the fix takes them along.
+            let mut fix_end = range.end();
+            let mut next = token.next_token();
+            while let Some(space) =
+                next.filter(|t| matches!(t.kind(), SyntaxKind::SPACE | SyntaxKind::TAB))
+            {
+                fix_end = space.text_range().end();
+                next = space.next_token();
+            }
+            let fix_range = TextRange::new(range.start(), fix_end);
+            diagnostics.push(
+                Diagnostic::new(
+                    self.name(),
+                    range,
+                    format!("'{}' is a string but looks like {looks_like}", token.text()),
+                )
+                .with_fix(
+                    "quote the string",
+                    TextEdit::new(vec![Indel::replace(fix_range, quoted(token.text(), '"'))]),
+                ),
+            );
+        }
+    }
+}
+
+fn looks_like(text: &str) -> Option<&'static str> {
+    let lower = text.to_ascii_lowercase();
+    if ["true", "false"].iter().any(|b| lower == *b || near(&lower, b)) {
+        return Some("a boolean");
+    }
+    if text.starts_with(['"', '\'']) {
+        return Some("an unterminated quoted string");
+    }
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if !unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let word = unsigned.split([' ', '\t']).next().unwrap_or_default();
+    // Versions, dates, times, sizes with units and paths are common and fine as strings.
+    let alphanumeric = word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    (alphanumeric || word.len() < unsigned.len()).then_some("an integer")
+}
+
+/// Whether `a` is one edit (insertion, deletion, substitution or adjacent transposition) from `b`.
+fn near(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    match (a.len(), b.len()) {
+        (0, 0) => false,
+        (x, y) if x == y => {
+            a[1..] == b[1..] || (x >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..])
+        }
+        (x, y) if x + 1 == y => a == &b[1..],
+        (x, y) if x == y + 1 => &a[1..] == b,
+        _ => false,
+    }
+}
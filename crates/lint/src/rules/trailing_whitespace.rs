@@ -0,0 +1,35 @@
+use crate::{Context, Diagnostic, Rule};
+use mical_cli_syntax::{Indel, SyntaxKind, TextEdit, ast::AstNode};
+
+/// Reports spaces and tabs at the end of a line. Trailing whitespace inside block string content
+/// is part of the value and is not a separate token, so it is never reported.
+pub(crate) struct TrailingWhitespace;
+
+impl Rule for TrailingWhitespace {
+    fn name(&self) -> &'static str {
+        "trailing-whitespace"
+    }
+
+    fn check(&self, ctx: &Context<'_>, diagnostics: &mut Vec<Diagnostic>) {
+        let tokens =
+            ctx.source_file.syntax().descendants_with_tokens().filter_map(|e| e.into_token());
+        for token in tokens {
+            if !matches!(token.kind(), SyntaxKind::SPACE | SyntaxKind::TAB) {
+                continue;
+            }
+            let at_line_end = match token.next_token() {
+                None => true,
+                Some(next) => next.kind() == SyntaxKind::NEWLINE,
+            };
+            if at_line_end {
+                let range = token.text_range();
+                diagnostics.push(
+                    Diagnostic::new(self.name(), range, "trailing whitespace").with_fix(
+                        "remove trailing whitespace",
+                        TextEdit::new(vec![Indel::delete(range)]),
+                    ),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use mical_cli_config::Pattern;
+use mical_cli_lint::{Naming, Options, QuoteStyle, Severity, fix, line_col, lint};
+use mical_cli_parser::ParseOptions;
+use pretty_assertions::assert_eq;
+use std::num::NonZeroU32;
+
+/// Returns `(line, rule, message)` for every diagnostic.
+fn check(text: &str, options: &Options) -> Vec<(usize, &'static str, String)> {
+    let report = lint(text, options);
+    assert!(report.syntax_errors.is_empty(), "{:?}", report.syntax_errors);
+    report
+        .diagnostics
+        .into_iter()
+        .map(|d| (line_col(text, d.range.start()).0, d.rule, d.message))
+        .collect()
+}
+
+fn rules(text: &str, options: &Options) -> Vec<(usize, &'static str)> {
+    check(text, options).into_iter().map(|(line, rule, _)| (line, rule)).collect()
+}
+
+#[test]
+fn duplicate_keys() {
+    let text = "tag a\nport 1\nx. {\n  y 1\n}\ntag b\nx.y 2\n";
+    assert_eq!(
+        check(text, &Options::default()),
+        [
+            (6, "duplicate-key", "duplicate key 'tag' (first defined on line 1)".to_owned()),
+            (7, "duplicate-key", "duplicate key 'x.y' (first defined on line 4)".to_owned()),
+        ]
+    );
+    let mut options = Options::default();
+    options.allowed_duplicates.push(Pattern::new("tag").unwrap());
+    assert_eq!(rules(text, &options), [(7, "duplicate-key")]);
+}
+
+#[test]
+fn prefix_block_without_separator() {
+    let text = "http_ {\n  port 80\n}\nserver {\n  .host h\n}\nok. {\n  a 1\n}\n";
+    assert_eq!(rules(text, &Options::default()), [(1, "prefix-separator")]);
+}
+
+#[test]
+fn trailing_whitespace_outside_block_strings() {
+    let text = "a 1  \nb |\n  text  \n# note \n";
+    assert_eq!(
+        rules(text, &Options::default()),
+        [(1, "trailing-whitespace"), (4, "trailing-whitespace")]
+    );
+}
+
+#[test]
+fn suspicious_strings() {
+    let text =
+        "a tru\nb False\nc 0x\nd 10 items\ne 1.2.3\nf 2024-01-01\ng 10ms\nh hello\ni 'single'\n";
+    assert_eq!(
+        rules(text, &Options::default()),
+        [
+            (1, "suspicious-string"),
+            (2, "suspicious-string"),
+            (3, "suspicious-string"),
+            (4, "suspicious-string"),
+            (7, "suspicious-string"),
+        ]
+    );
+}
+
+#[test]
+fn quote_style() {
+    let text = "a \"x\"\nb 'y'\n\"c\" \"z\"\n";
+    assert_eq!(
+        check(text, &Options::default()),
+        [(2, "quote-style", "single quotes, expected double quotes".to_owned())]
+    );
+    let mut options = Options::default();
+    options.quote_style = QuoteStyle::Single;
+    assert_eq!(rules(text, &options), [(1, "quote-style"), (3, "quote-style"), (3, "quote-style")]);
+}
+
+#[test]
+fn naming_convention() {
+    let text = "server. {\n  maxConn 1\n  max-idle 2\n}\nBad_Block. {\n  x 1\n}\n";
+    assert!(rules(text, &Options::default()).is_empty());
+    let mut options = Options::default();
+    options.naming = Some(Naming::KebabCase);
+    assert_eq!(
+        check(text, &options),
+        [
+            (2, "naming-convention", "key segment 'maxConn' is not kebab-case".to_owned()),
+            (5, "naming-convention", "key segment 'Bad_Block' is not kebab-case".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn severities() {
+    let mut options = Options::default();
+    assert!(options.set_severity("trailing-whitespace", Severity::Error));
+    assert!(options.set_severity("duplicate-key", Severity::Off));
+    assert!(!options.set_severity("no-such-rule", Severity::Error));
+    let report = lint("a 1 \na 2\n", &options);
+    let severities = report.diagnostics.iter().map(|d| (d.rule, d.severity)).collect::<Vec<_>>();
+    assert_eq!(severities, [("trailing-whitespace", Severity::Error)]);
+}
+
+#[test]
+fn suppression_directive() {
+    let text = "a 1\n#lint-ignore duplicate-key\na 2\n#lint-ignore\na 3 \na 4\n";
+    assert_eq!(rules(text, &Options::default()), [(6, "duplicate-key")]);
+    let text = "a 1\n#lint-ignore trailing-whitespace\na 2\n";
+    assert_eq!(rules(text, &Options::default()), [(3, "duplicate-key")]);
+}
+
+#[test]
+fn extensions_from_the_options() {
+    let text = "a. {\n\tb one \\\n\t  two # note\n}\n";
+    assert!(!lint(text, &Options::default()).syntax_errors.is_empty());
+    let mut options = Options::default();
+    options.parse = ParseOptions {
+        inline_comments: true,
+        tab_width: NonZeroU32::new(4),
+        line_continuation: true,
+        ..Default::default()
+    };
+    assert_eq!(check(text, &options), []);
+    assert_eq!(fix(text, &options), (text.to_owned(), 0));
+}
+
+#[test]
+fn fixes_keep_the_config() {
+    let text = "a 'x' \nb 10 items\nc \"it's\"\nd 'say \"hi\"'\n";
+    let mut options = Options::default();
+    options.quote_style = QuoteStyle::Double;
+    let (fixed, count) = fix(text, &options);
+    assert_eq!(fixed, "a \"x\"\nb \"10 items\"\nc \"it's\"\nd \"say \\\"hi\\\"\"\n");
+    assert_eq!(count, 4);
+    assert!(lint(&fixed, &options).diagnostics.is_empty());
+    // Quoting a value also drops the trailing whitespace a quoted value cannot have.
+    assert_eq!(fix("a tru \n", &options), ("a \"tru\"\n".to_owned(), 1));
+    // Whichever quote is in the majority wins by default.
+    let (fixed, _) = fix("a 'x'\nb 'y'\nc \"it's\"\n", &Options::default());
+    assert_eq!(fixed, "a 'x'\nb 'y'\nc 'it\\'s'\n");
+}
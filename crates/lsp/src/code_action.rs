@@ -0,0 +1,35 @@
+use crate::{Document, diagnostics};
+use lsp_types::{CodeAction, CodeActionKind, CodeActionOrCommand, TextEdit, Uri, WorkspaceEdit};
+use mical_cli_syntax::TextRange;
+use std::collections::HashMap;
+
+/// Returns a quick fix for every fixable diagnostic touching `range`.
+pub(crate) fn quick_fixes(uri: &Uri, doc: &Document, range: TextRange) -> Vec<CodeActionOrCommand> {
+    let mut actions = Vec::new();
+    for diagnostic in diagnostics::collect(doc) {
+        if diagnostic.range.intersect(range).is_none() {
+            continue;
+        }
+        let Some(fix) = &diagnostic.fix else { continue };
+        let edits = fix
+            .edit
+            .indels()
+            .iter()
+            .map(|indel| TextEdit {
+                range: doc.line_index.range(&doc.text, indel.delete),
+                new_text: indel.insert.clone(),
+            })
+            .collect();
+        actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+            title: fix.message.clone(),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.to_lsp(&doc.text, &doc.line_index)]),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), edits)])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }));
+    }
+    actions
+}
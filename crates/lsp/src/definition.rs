@@ -0,0 +1,26 @@
+use crate::{Document, keys};
+use lsp_types::{Location, Position, Uri};
+use mical_cli_config::walk_items;
+use mical_cli_syntax::ast::{self, AstNode};
+
+/// Finds every entry defining the full key under the cursor, or every prefix block opening the
+/// same prefix, wherever it sits in the document's prefix blocks.
+pub(crate) fn definitions(uri: &Uri, doc: &Document, position: Position) -> Vec<Location> {
+    let offset = doc.line_index.offset(&doc.text, position);
+    let source_file = doc.source_file();
+    let Some((item, _, target)) = keys::key_at(&source_file, offset) else { return Vec::new() };
+    let block = is_block(&item);
+
+    let mut locations = Vec::new();
+    walk_items(source_file.items(), "", &mut |item, item_key, _, full_key| {
+        if is_block(item) == block && full_key == target {
+            let range = doc.line_index.range(&doc.text, item_key.syntax().text_range());
+            locations.push(Location::new(uri.clone(), range));
+        }
+    });
+    locations
+}
+
+fn is_block(item: &ast::Item) -> bool {
+    matches!(item, ast::Item::PrefixBlock(_))
+}
@@ -0,0 +1,108 @@
+use crate::{Document, line_index::LineIndex};
+use lsp_types::{DiagnosticSeverity, NumberOrString};
+use mical_cli_config::Config;
+use mical_cli_lint::Severity;
+use mical_cli_syntax::{Fix, Indel, TextEdit, TextRange};
+
+/// A diagnostic of a document together with the fix that resolves it, if any.
+pub(crate) struct Diagnostic {
+    pub(crate) range: TextRange,
+    pub(crate) severity: DiagnosticSeverity,
+    /// The lint rule that reported the diagnostic.
+    pub(crate) rule: Option<&'static str>,
+    pub(crate) message: String,
+    pub(crate) fix: Option<Fix>,
+}
+
+/// Collects the syntax errors, evaluation errors and lint diagnostics of `doc`.
+pub(crate) fn collect(doc: &Document) -> Vec<Diagnostic> {
+    let error = DiagnosticSeverity::ERROR;
+    let width = indent_width(&doc.text);
+    let mut options = mical_cli_lint::Options::default();
+    options.parse = doc.options;
+    let report = mical_cli_lint::lint(&doc.text, &options);
+    let mut diagnostics = report
+        .syntax_errors
+        .iter()
+        .map(|e| Diagnostic {
+            range: e.range(),
+            severity: error,
+            rule: None,
+            message: e.message().to_owned(),
+            fix: e.fix().map(|fix| retab(fix, &doc.text, width)),
+        })
+        .collect::<Vec<_>>();
+
+    let (_, config_errors) = Config::from_source_file(doc.source_file());
+    diagnostics.extend(config_errors.iter().map(|e| Diagnostic {
+        range: e.range(),
+        severity: error,
+        rule: None,
+        message: e.message(),
+        fix: e.fix(),
+    }));
+
+    diagnostics.extend(report.diagnostics.into_iter().map(|d| Diagnostic {
+        range: d.range,
+        severity: match d.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning | Severity::Off => DiagnosticSeverity::WARNING,
+        },
+        rule: Some(d.rule),
+        message: d.message,
+        fix: d.fix,
+    }));
+    diagnostics
+}
+
+/// The width of one level of indentation in `text`: the narrowest indent of spaces before
+/// anything on a line, or 2 if no line is indented with spaces.
+fn indent_width(text: &str) -> usize {
+    text.lines()
+        .filter_map(|line| {
+            let rest = line.trim_start_matches(' ');
+            let width = line.len() - rest.len();
+            (width > 0 && !rest.trim().is_empty()).then_some(width)
+        })
+        .min()
+        .unwrap_or(2)
+}
+
+/// Widens or narrows the spaces a fix puts for each tab of a tab indent, which the parser cannot
+/// know, to `width`.
+fn retab(fix: &Fix, text: &str, width: usize) -> Fix {
+    let indels = fix
+        .edit
+        .indels()
+        .iter()
+        .map(|indel| {
+            let deleted = &text[indel.delete];
+            let before = &text[..usize::from(indel.delete.start())];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            let tabs = deleted.len();
+            let is_retab = tabs > 0
+                && before[line_start..].bytes().all(|b| b == b' ')
+                && deleted.bytes().all(|b| b == b'\t')
+                && indel.insert.bytes().all(|b| b == b' ');
+            if is_retab {
+                Indel::replace(indel.delete, " ".repeat(tabs * width))
+            } else {
+                indel.clone()
+            }
+        })
+        .collect();
+    Fix::new(fix.message.clone(), TextEdit::new(indels))
+}
+
+impl Diagnostic {
+    pub(crate) fn to_lsp(&self, text: &str, line_index: &LineIndex) -> lsp_types::Diagnostic {
+        lsp_types::Diagnostic {
+            range: line_index.range(text, self.range),
+            severity: Some(self.severity),
+            code: self.rule.map(|rule| NumberOrString::String(rule.to_owned())),
+            source: Some("mical".to_owned()),
+            message: self.message.clone(),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::Document;
+use lsp_types::FoldingRange;
+use mical_cli_syntax::{
+    SyntaxKind, TextSize,
+    ast::{self, AstNode},
+};
+
+/// Folds the body of prefix blocks, keeping the closing brace visible, and block strings from the
+/// entry line to their last line.
+pub(crate) fn folding_ranges(doc: &Document) -> Vec<FoldingRange> {
+    let line = |offset: TextSize| doc.line_index.position(&doc.text, offset).line;
+    let mut ranges = Vec::new();
+    for node in doc.source_file().syntax().descendants() {
+        let (start_line, end_line) = match node.kind() {
+            SyntaxKind::PREFIX_BLOCK => {
+                let block = ast::PrefixBlock::cast(node).unwrap();
+                let Some(close) = block.close_brace() else { continue };
+                let start = line(block.syntax().text_range().start());
+                (start, line(close.text_range().start()).saturating_sub(1))
+            }
+            SyntaxKind::BLOCK_STRING => {
+                let Some(entry) = node.parent() else { continue };
+                let end = node.text_range().end();
+                // The last line ends before its newline.
+                let end = match doc.text[..usize::from(end)].ends_with('\n') {
+                    true => end - TextSize::from(1),
+                    false => end,
+                };
+                (line(entry.text_range().start()), line(end))
+            }
+            _ => continue,
+        };
+        if end_line > start_line {
+            ranges.push(FoldingRange { start_line, end_line, ..Default::default() });
+        }
+    }
+    ranges
+}
@@ -0,0 +1,23 @@
+use mical_cli_config::walk_items;
+use mical_cli_syntax::{
+    TextSize,
+    ast::{self, AstNode},
+};
+
+/// Returns the key under `offset`, with the entry or prefix block it belongs to and its full key.
+pub(crate) fn key_at(
+    source_file: &ast::SourceFile,
+    offset: TextSize,
+) -> Option<(ast::Item, ast::Key, String)> {
+    let key = source_file
+        .syntax()
+        .token_at_offset(offset)
+        .find_map(|token| token.parent_ancestors().find_map(ast::Key::cast))?;
+    let mut found = None;
+    walk_items(source_file.items(), "", &mut |item, item_key, _, full_key| {
+        if item_key.syntax() == key.syntax() {
+            found = Some((item.clone(), item_key.clone(), full_key.to_owned()));
+        }
+    });
+    found
+}
@@ -0,0 +1,437 @@
+//! Language server for MICAL.
+//!
+//! The server keeps the text of every open document and answers each request from a fresh parse;
+//! MICAL files are small enough that only the symbols of the workspace files, which would be read
+//! from the disk again, are worth caching. Documents are synced in full and positions use the
+//! default UTF-16 encoding.
+
+use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    CodeActionParams, CodeActionProviderCapability, DidChangeWatchedFilesRegistrationOptions,
+    DocumentLinkOptions, FileSystemWatcher, FoldingRangeProviderCapability, GlobPattern,
+    InitializeParams, LogMessageParams, MessageType, OneOf, PublishDiagnosticsParams, Registration,
+    RegistrationParams, RenameOptions, SelectionRangeProviderCapability, SemanticToken,
+    SemanticTokensDelta, SemanticTokensFullDeltaResult, SemanticTokensFullOptions,
+    SemanticTokensOptions, SemanticTokensResult, SemanticTokensServerCapabilities,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+    WorkspaceSymbolResponse,
+    notification::{self, Notification as _},
+    request::{self, Request as _},
+};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::ast;
+use std::{collections::HashMap, fmt, path::PathBuf};
+
+mod code_action;
+mod definition;
+mod diagnostics;
+mod folding;
+mod keys;
+mod line_index;
+mod links;
+mod rename;
+mod selection;
+mod semantic_tokens;
+mod symbols;
+mod uri;
+use line_index::LineIndex;
+use symbols::WorkspaceSymbols;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Serves the language server protocol over stdin and stdout until the client exits, reading
+/// documents with the extensions `options` turn on.
+pub fn run_stdio(options: &ParseOptions) -> Result<(), Error> {
+    let (connection, io_threads) = Connection::stdio();
+    run_with_options(connection, options)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Serves the language server protocol over `connection` until the client exits.
+pub fn run(connection: Connection) -> Result<(), Error> {
+    run_with_options(connection, &ParseOptions::default())
+}
+
+/// Same as [`run`], reading documents with the extensions `options` turn on.
+pub fn run_with_options(connection: Connection, options: &ParseOptions) -> Result<(), Error> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
+        document_link_provider: Some(DocumentLinkOptions {
+            resolve_provider: Some(false),
+            work_done_progress_options: Default::default(),
+        }),
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Right(RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: Default::default(),
+        })),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: semantic_tokens::legend(),
+                full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    };
+    let params = connection.initialize(serde_json::to_value(capabilities)?)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    let mut server = Server {
+        connection: &connection,
+        roots: workspace_roots(&params),
+        options: *options,
+        documents: HashMap::new(),
+        semantic_tokens: HashMap::new(),
+        next_result_id: 0,
+        workspace_symbols: WorkspaceSymbols::default(),
+    };
+    server.watch_files(&params)?;
+    for message in &connection.receiver {
+        match message {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                server.on_request(req)?;
+            }
+            Message::Notification(not) => server.on_notification(not)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+pub(crate) struct Document {
+    pub(crate) text: String,
+    pub(crate) line_index: LineIndex,
+    pub(crate) options: ParseOptions,
+}
+
+impl Document {
+    fn new(text: String, options: ParseOptions) -> Self {
+        let line_index = LineIndex::new(&text);
+        Document { text, line_index, options }
+    }
+
+    pub(crate) fn source_file(&self) -> ast::SourceFile {
+        mical_cli_config::parse_source_file(&self.text, &self.options).0
+    }
+}
+
+/// Returns the directories searched for workspace symbols.
+fn workspace_roots(params: &InitializeParams) -> Vec<PathBuf> {
+    match &params.workspace_folders {
+        Some(folders) => folders.iter().filter_map(|folder| uri::to_path(&folder.uri)).collect(),
+        #[allow(deprecated)]
+        None => params.root_uri.iter().filter_map(uri::to_path).collect(),
+    }
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    roots: Vec<PathBuf>,
+    options: ParseOptions,
+    documents: HashMap<Uri, Document>,
+    /// The semantic tokens last sent for each document, with their result id, for delta requests.
+    semantic_tokens: HashMap<Uri, (String, Vec<SemanticToken>)>,
+    next_result_id: u64,
+    workspace_symbols: WorkspaceSymbols,
+}
+
+impl Server<'_> {
+    fn on_request(&mut self, req: Request) -> Result<(), Error> {
+        match req.method.as_str() {
+            request::CodeActionRequest::METHOD => self
+                .handle::<request::CodeActionRequest>(req, |s, params| {
+                    Some(s.code_actions(params))
+                }),
+            request::FoldingRangeRequest::METHOD => {
+                self.handle::<request::FoldingRangeRequest>(req, |s, params| {
+                    let doc = s.documents.get(&params.text_document.uri)?;
+                    Some(folding::folding_ranges(doc))
+                })
+            }
+            request::DocumentSymbolRequest::METHOD => self
+                .handle::<request::DocumentSymbolRequest>(req, |s, params| {
+                    let doc = s.documents.get(&params.text_document.uri)?;
+                    Some(symbols::document_symbols(doc).into())
+                }),
+            request::WorkspaceSymbolRequest::METHOD => self
+                .handle::<request::WorkspaceSymbolRequest>(req, |s, params| {
+                    let symbols = s.workspace_symbols.search(
+                        &s.roots,
+                        &s.documents,
+                        &s.options,
+                        &params.query,
+                    );
+                    Some(WorkspaceSymbolResponse::Nested(symbols))
+                }),
+            request::DocumentLinkRequest::METHOD => {
+                self.handle::<request::DocumentLinkRequest>(req, |s, params| {
+                    let uri = params.text_document.uri;
+                    Some(links::document_links(&uri, s.documents.get(&uri)?))
+                })
+            }
+            request::SelectionRangeRequest::METHOD => self
+                .handle::<request::SelectionRangeRequest>(req, |s, params| {
+                    let doc = s.documents.get(&params.text_document.uri)?;
+                    Some(selection::selection_ranges(doc, &params.positions))
+                }),
+            request::GotoDefinition::METHOD => {
+                self.handle::<request::GotoDefinition>(req, |s, params| {
+                    let params = params.text_document_position_params;
+                    let uri = params.text_document.uri;
+                    let doc = s.documents.get(&uri)?;
+                    let locations = definition::definitions(&uri, doc, params.position);
+                    Some(locations.into())
+                })
+            }
+            request::PrepareRenameRequest::METHOD => {
+                self.handle::<request::PrepareRenameRequest>(req, |s, params| {
+                    let doc = s.documents.get(&params.text_document.uri)?;
+                    rename::prepare(doc, params.position)
+                })
+            }
+            request::Rename::METHOD => {
+                let Some((id, params)) = self.extract::<request::Rename>(req)? else {
+                    return Ok(());
+                };
+                let position = params.text_document_position;
+                let uri = position.text_document.uri;
+                let Some(doc) = self.documents.get(&uri) else { return self.respond(id, ()) };
+                match rename::rename(&uri, doc, position.position, &params.new_name) {
+                    Ok(edit) => self.respond(id, edit),
+                    Err(e) => {
+                        let code = lsp_server::ErrorCode::RequestFailed as i32;
+                        let message = format!("cannot rename: {e}");
+                        self.connection.sender.send(Response::new_err(id, code, message).into())?;
+                        Ok(())
+                    }
+                }
+            }
+            request::SemanticTokensFullRequest::METHOD => self
+                .handle::<request::SemanticTokensFullRequest>(req, |s, params| {
+                    let uri = params.text_document.uri;
+                    let tokens = semantic_tokens::semantic_tokens(s.documents.get(&uri)?);
+                    let result_id = s.remember_semantic_tokens(uri, &tokens);
+                    Some(SemanticTokensResult::Tokens(semantic_tokens::response(result_id, tokens)))
+                }),
+            request::SemanticTokensFullDeltaRequest::METHOD => {
+                self.handle::<request::SemanticTokensFullDeltaRequest>(req, |s, params| {
+                    let uri = params.text_document.uri;
+                    let tokens = semantic_tokens::semantic_tokens(s.documents.get(&uri)?);
+                    let edits = match s.semantic_tokens.get(&uri) {
+                        Some((id, old)) if *id == params.previous_result_id => {
+                            Some(semantic_tokens::diff(old, &tokens))
+                        }
+                        _ => None,
+                    };
+                    let result_id = s.remember_semantic_tokens(uri, &tokens);
+                    Some(match edits {
+                        Some(edits) => {
+                            SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+                                result_id: Some(result_id),
+                                edits,
+                            })
+                        }
+                        None => semantic_tokens::response(result_id, tokens).into(),
+                    })
+                })
+            }
+            _ => {
+                let message = format!("unsupported request: {}", req.method);
+                let code = lsp_server::ErrorCode::MethodNotFound as i32;
+                self.connection.sender.send(Response::new_err(req.id, code, message).into())?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Answers `req` with the result of `f`.
+    fn handle<R: request::Request>(
+        &mut self,
+        req: Request,
+        f: impl FnOnce(&mut Self, R::Params) -> R::Result,
+    ) -> Result<(), Error> {
+        let Some((id, params)) = self.extract::<R>(req)? else { return Ok(()) };
+        let result = f(self, params);
+        self.respond(id, result)
+    }
+
+    /// Takes the params of `req`, or answers it with an error if they are not valid.
+    fn extract<R: request::Request>(
+        &self,
+        req: Request,
+    ) -> Result<Option<(RequestId, R::Params)>, Error> {
+        let id = req.id.clone();
+        match req.extract(R::METHOD) {
+            Ok(extracted) => Ok(Some(extracted)),
+            Err(e) => {
+                let code = lsp_server::ErrorCode::InvalidParams as i32;
+                let response = Response::new_err(id, code, extract_error(e));
+                self.connection.sender.send(response.into())?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Takes the params of `not`, or logs an error if they are not valid.
+    fn extract_notification<N: notification::Notification>(
+        &self,
+        not: Notification,
+    ) -> Result<Option<N::Params>, Error> {
+        match not.extract(N::METHOD) {
+            Ok(params) => Ok(Some(params)),
+            Err(e) => {
+                self.notify::<notification::LogMessage>(LogMessageParams {
+                    typ: MessageType::ERROR,
+                    message: extract_error(e),
+                })?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn on_notification(&mut self, not: Notification) -> Result<(), Error> {
+        match not.method.as_str() {
+            notification::DidOpenTextDocument::METHOD => {
+                let Some(params) =
+                    self.extract_notification::<notification::DidOpenTextDocument>(not)?
+                else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+                self.set_document(&uri, params.text_document.text);
+                self.publish_diagnostics(uri)
+            }
+            notification::DidChangeTextDocument::METHOD => {
+                let Some(params) =
+                    self.extract_notification::<notification::DidChangeTextDocument>(not)?
+                else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+                // Full sync: the last change holds the whole text.
+                let Some(change) = params.content_changes.into_iter().last() else { return Ok(()) };
+                self.set_document(&uri, change.text);
+                self.publish_diagnostics(uri)
+            }
+            notification::DidCloseTextDocument::METHOD => {
+                let Some(params) =
+                    self.extract_notification::<notification::DidCloseTextDocument>(not)?
+                else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.semantic_tokens.remove(&uri);
+                // Unsaved edits are gone, so the file on disk counts again.
+                self.workspace_symbols.file_changed(&self.roots, &uri, &self.options);
+                self.notify::<notification::PublishDiagnostics>(PublishDiagnosticsParams::new(
+                    uri,
+                    Vec::new(),
+                    None,
+                ))
+            }
+            notification::DidChangeWatchedFiles::METHOD => {
+                let Some(params) =
+                    self.extract_notification::<notification::DidChangeWatchedFiles>(not)?
+                else {
+                    return Ok(());
+                };
+                // Open documents are searched as edited, whatever happens to their files.
+                for change in params.changes.iter().filter(|c| !self.documents.contains_key(&c.uri))
+                {
+                    self.workspace_symbols.file_changed(&self.roots, &change.uri, &self.options);
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn set_document(&mut self, uri: &Uri, text: String) {
+        let doc = Document::new(text, self.options);
+        self.workspace_symbols.document_changed(&self.roots, uri, &doc);
+        self.documents.insert(uri.clone(), doc);
+    }
+
+    /// Asks the client to report changes to `.mical` files, if it can register for them, so
+    /// that the workspace symbols follow edits made outside the editor.
+    fn watch_files(&self, params: &InitializeParams) -> Result<(), Error> {
+        let watched_files =
+            params.capabilities.workspace.as_ref().and_then(|w| w.did_change_watched_files);
+        if !watched_files.and_then(|c| c.dynamic_registration).unwrap_or(false) {
+            return Ok(());
+        }
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*.mical".to_owned()),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: notification::DidChangeWatchedFiles::METHOD.to_owned(),
+            method: notification::DidChangeWatchedFiles::METHOD.to_owned(),
+            register_options: Some(serde_json::to_value(options)?),
+        };
+        let params = RegistrationParams { registrations: vec![registration] };
+        let id = RequestId::from(request::RegisterCapability::METHOD.to_owned());
+        let req = Request::new(id, request::RegisterCapability::METHOD.to_owned(), params);
+        self.connection.sender.send(req.into())?;
+        Ok(())
+    }
+
+    fn publish_diagnostics(&self, uri: Uri) -> Result<(), Error> {
+        let Some(doc) = self.documents.get(&uri) else { return Ok(()) };
+        let diagnostics = diagnostics::collect(doc)
+            .iter()
+            .map(|d| d.to_lsp(&doc.text, &doc.line_index))
+            .collect();
+        self.notify::<notification::PublishDiagnostics>(PublishDiagnosticsParams::new(
+            uri,
+            diagnostics,
+            None,
+        ))
+    }
+
+    fn code_actions(&self, params: CodeActionParams) -> Vec<lsp_types::CodeActionOrCommand> {
+        let uri = params.text_document.uri;
+        let Some(doc) = self.documents.get(&uri) else { return Vec::new() };
+        let range = doc.line_index.text_range(&doc.text, params.range);
+        code_action::quick_fixes(&uri, doc, range)
+    }
+
+    /// Stores `tokens` as the last ones sent for `uri` and returns their new result id.
+    fn remember_semantic_tokens(&mut self, uri: Uri, tokens: &[SemanticToken]) -> String {
+        self.next_result_id += 1;
+        let result_id = self.next_result_id.to_string();
+        self.semantic_tokens.insert(uri, (result_id.clone(), tokens.to_vec()));
+        result_id
+    }
+
+    fn respond(&self, id: RequestId, result: impl serde::Serialize) -> Result<(), Error> {
+        self.connection.sender.send(Response::new_ok(id, result).into())?;
+        Ok(())
+    }
+
+    fn notify<N: notification::Notification>(&self, params: N::Params) -> Result<(), Error> {
+        self.connection.sender.send(Notification::new(N::METHOD.to_owned(), params).into())?;
+        Ok(())
+    }
+}
+
+fn extract_error<T: fmt::Debug>(e: ExtractError<T>) -> String {
+    match e {
+        ExtractError::MethodMismatch(message) => format!("unexpected message: {message:?}"),
+        ExtractError::JsonError { method, error } => {
+            format!("invalid params for {method}: {error}")
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use lsp_types::{Position, Range};
+use mical_cli_syntax::{TextRange, TextSize};
+
+/// Converts between byte offsets and LSP positions, whose columns count UTF-16 code units.
+pub(crate) struct LineIndex {
+    /// Offset of the first character of every line.
+    line_starts: Vec<TextSize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(text: &str) -> Self {
+        let newlines = text.match_indices('\n').map(|(i, _)| TextSize::from(i as u32 + 1));
+        LineIndex { line_starts: std::iter::once(0.into()).chain(newlines).collect() }
+    }
+
+    pub(crate) fn position(&self, text: &str, offset: TextSize) -> Position {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_text = &text[TextRange::new(self.line_starts[line], offset)];
+        Position::new(line as u32, line_text.encode_utf16().count() as u32)
+    }
+
+    pub(crate) fn range(&self, text: &str, range: TextRange) -> Range {
+        Range::new(self.position(text, range.start()), self.position(text, range.end()))
+    }
+
+    /// Returns the offset of `position`, clamped to the end of its line and of the text.
+    pub(crate) fn offset(&self, text: &str, position: Position) -> TextSize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return TextSize::of(text);
+        };
+        let mut units = 0;
+        let mut offset = start;
+        for c in text[usize::from(start)..].chars() {
+            if c == '\n' || units >= position.character {
+                break;
+            }
+            units += c.len_utf16() as u32;
+            offset += TextSize::of(c);
+        }
+        offset
+    }
+
+    /// Returns the range between the offsets of the ends of `range`, which a client may send
+    /// reversed.
+    pub(crate) fn text_range(&self, text: &str, range: Range) -> TextRange {
+        let (start, end) = (self.offset(text, range.start), self.offset(text, range.end));
+        TextRange::new(start.min(end), start.max(end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_columns() {
+        let text = "a é\n🦀 x\n";
+        let index = LineIndex::new(text);
+        let x = TextSize::from(text.find('x').unwrap() as u32);
+        assert_eq!(index.position(text, x), Position::new(1, 3));
+        assert_eq!(index.offset(text, Position::new(1, 3)), x);
+        assert_eq!(index.offset(text, Position::new(0, 99)), TextSize::from(4));
+        assert_eq!(index.offset(text, Position::new(9, 0)), TextSize::of(text));
+    }
+
+    #[test]
+    fn reversed_range() {
+        let text = "a 1\nb 2\n";
+        let index = LineIndex::new(text);
+        let range = Range::new(Position::new(1, 3), Position::new(0, 0));
+        assert_eq!(index.text_range(text, range), TextRange::new(0.into(), 7.into()));
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{Document, uri};
+use lsp_types::{DocumentLink, Uri};
+use mical_cli_syntax::{
+    TextRange, TextSize,
+    ast::{self, AstNode},
+};
+
+/// Directives whose argument names another file.
+const LINK_DIRECTIVES: &[&str] = &["include", "schema"];
+
+/// Links the targets of `#include` and `#schema` directives. Relative paths resolve against the
+/// directory of the document, so they only link in documents that are files themselves.
+pub(crate) fn document_links(uri: &Uri, doc: &Document) -> Vec<DocumentLink> {
+    let base = uri::to_path(uri);
+    let mut links = Vec::new();
+    for node in doc.source_file().syntax().descendants() {
+        let Some(directive) = ast::Directive::cast(node) else { continue };
+        let Some(name) = directive.name() else { continue };
+        if !LINK_DIRECTIVES.contains(&name.text()) {
+            continue;
+        }
+        let Some(args) = directive.args().and_then(|args| args.string()) else { continue };
+        let text = args.text().trim_start();
+        let start = args.text_range().end() - TextSize::of(text);
+        let text = text.trim_end();
+        if text.is_empty() {
+            continue;
+        }
+        let target = match text.contains("://") {
+            true => text.parse().ok(),
+            false => base.as_ref().and_then(|base| {
+                let dir = base.parent()?;
+                uri::from_path(&dir.join(text))
+            }),
+        };
+        let Some(target) = target else { continue };
+        let range = TextRange::at(start, TextSize::of(text));
+        links.push(DocumentLink {
+            range: doc.line_index.range(&doc.text, range),
+            target: Some(target),
+            tooltip: None,
+            data: None,
+        });
+    }
+    links
+}
@@ -0,0 +1,48 @@
+use crate::{Document, keys};
+use lsp_types::{Position, PrepareRenameResponse, TextEdit, Uri, WorkspaceEdit};
+use mical_cli_config::key_text;
+use mical_cli_edit::{Occurrences, RenameError, RenameScope};
+use mical_cli_syntax::ast::{self, AstNode};
+use std::collections::HashMap;
+
+/// Returns the range and text of the key under the cursor, if there is one to rename.
+pub(crate) fn prepare(doc: &Document, position: Position) -> Option<PrepareRenameResponse> {
+    let offset = doc.line_index.offset(&doc.text, position);
+    let (_, key, _) = keys::key_at(&doc.source_file(), offset)?;
+    Some(PrepareRenameResponse::RangeWithPlaceholder {
+        range: doc.line_index.range(&doc.text, key.syntax().text_range()),
+        placeholder: key_text(&key)?,
+    })
+}
+
+/// Renames the key under the cursor to `new_name` wherever its full key is spelled. An entry key
+/// renames that full key; a prefix block key renames every key below the block's prefix.
+pub(crate) fn rename(
+    uri: &Uri,
+    doc: &Document,
+    position: Position,
+    new_name: &str,
+) -> Result<Option<WorkspaceEdit>, RenameError> {
+    let offset = doc.line_index.offset(&doc.text, position);
+    let Some((item, key, from)) = keys::key_at(&doc.source_file(), offset) else { return Ok(None) };
+    let text = key_text(&key).unwrap_or_default();
+    let to = format!("{}{new_name}", &from[..from.len() - text.len()]);
+    let scope = match item {
+        ast::Item::PrefixBlock(_) => RenameScope::Prefix,
+        _ => RenameScope::Key,
+    };
+    let mut edited = mical_cli_edit::Document::parse_with_options(doc.text.as_str(), &doc.options);
+    let edit = edited.rename_keys(&from, &to, scope, Occurrences::All)?;
+    let edits = edit
+        .indels()
+        .iter()
+        .map(|indel| TextEdit {
+            range: doc.line_index.range(&doc.text, indel.delete),
+            new_text: indel.insert.clone(),
+        })
+        .collect();
+    Ok(Some(WorkspaceEdit {
+        changes: Some(HashMap::from([(uri.clone(), edits)])),
+        ..Default::default()
+    }))
+}
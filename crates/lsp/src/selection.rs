@@ -0,0 +1,42 @@
+use crate::Document;
+use lsp_types::{Position, SelectionRange};
+use mical_cli_syntax::{SyntaxKind, TextRange, ast::AstNode};
+
+/// Expands the selection at each position from the token under it through its ancestors, one
+/// syntax node at a time.
+pub(crate) fn selection_ranges(doc: &Document, positions: &[Position]) -> Vec<SelectionRange> {
+    let root = doc.source_file().syntax().clone();
+    positions
+        .iter()
+        .map(|&position| {
+            let offset = doc.line_index.offset(&doc.text, position);
+            // Between two tokens, prefer the one that is not trivia.
+            let token = root.token_at_offset(offset).max_by_key(|token| {
+                !matches!(token.kind(), SyntaxKind::SPACE | SyntaxKind::TAB | SyntaxKind::NEWLINE)
+            });
+            let mut ranges: Vec<TextRange> = Vec::new();
+            let mut push = |range: TextRange| {
+                if ranges.last() != Some(&range) {
+                    ranges.push(range);
+                }
+            };
+            match token {
+                Some(token) => {
+                    push(token.text_range());
+                    token.parent_ancestors().for_each(|node| push(node.text_range()));
+                }
+                None => push(root.text_range()),
+            }
+            ranges
+                .into_iter()
+                .rev()
+                .fold(None, |parent, range| {
+                    Some(SelectionRange {
+                        range: doc.line_index.range(&doc.text, range),
+                        parent: parent.map(Box::new),
+                    })
+                })
+                .expect("there is always at least one range")
+        })
+        .collect()
+}
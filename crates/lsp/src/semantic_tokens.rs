@@ -0,0 +1,192 @@
+//! Semantic tokens from the syntax tree.
+//!
+//! A value's type depends on its whole line (`true` is a boolean, `true story` a string), which
+//! regex-based grammars cannot see. The tokens here come from the node kinds the parser assigned.
+
+use crate::Document;
+use lsp_types::{
+    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensEdit,
+    SemanticTokensLegend,
+};
+use mical_cli_config::escape_sequences;
+use mical_cli_syntax::{SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize, ast::AstNode};
+
+/// The token types, indexed by `TokenType`.
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::NAMESPACE,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::new("escapeSequence"),
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::COMMENT,
+];
+
+#[derive(Clone, Copy)]
+enum TokenType {
+    /// Keys of entries.
+    Property,
+    /// Keys of prefix blocks.
+    Namespace,
+    /// Booleans.
+    Keyword,
+    /// Integers.
+    Number,
+    String,
+    /// Escape sequences in quoted strings and keys.
+    Escape,
+    /// Block string headers.
+    Operator,
+    /// Directive names.
+    Macro,
+    Comment,
+}
+
+/// The token modifiers, one bit each.
+const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[SemanticTokenModifier::new("invalid")];
+
+/// Marks escape sequences the evaluator rejects.
+const INVALID: u32 = 1 << 0;
+
+pub(crate) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// Returns the tokens of `doc`, each on a single line and in document order.
+pub(crate) fn semantic_tokens(doc: &Document) -> Vec<SemanticToken> {
+    let mut builder = Builder { doc, tokens: Vec::new(), line: 0, start: 0 };
+    builder.node(doc.source_file().syntax());
+    builder.tokens
+}
+
+/// Returns the edits turning `old` into `new`: a single replacement of everything between their
+/// common prefix and suffix, or nothing if they are equal.
+pub(crate) fn diff(old: &[SemanticToken], new: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (deleted, inserted) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    if deleted.is_empty() && inserted.is_empty() {
+        return Vec::new();
+    }
+    // Edits count the integers of the encoded array, five per token.
+    vec![SemanticTokensEdit {
+        start: 5 * prefix as u32,
+        delete_count: 5 * deleted.len() as u32,
+        data: Some(inserted.to_vec()),
+    }]
+}
+
+/// Wraps `tokens` for a response, under `result_id`.
+pub(crate) fn response(result_id: String, tokens: Vec<SemanticToken>) -> SemanticTokens {
+    SemanticTokens { result_id: Some(result_id), data: tokens }
+}
+
+struct Builder<'a> {
+    doc: &'a Document,
+    tokens: Vec<SemanticToken>,
+    /// Position of the previous token, which the next one is encoded relative to.
+    line: u32,
+    start: u32,
+}
+
+impl Builder<'_> {
+    fn node(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            SyntaxKind::COMMENT => self.push(node.text_range(), TokenType::Comment, 0),
+            SyntaxKind::DIRECTIVE => self.directive(node),
+            SyntaxKind::WORD_KEY | SyntaxKind::QUOTED_KEY => {
+                let ty = match node.parent().map(|p| p.kind()) {
+                    Some(SyntaxKind::PREFIX_BLOCK) => TokenType::Namespace,
+                    _ => TokenType::Property,
+                };
+                self.quoted(node, ty);
+            }
+            SyntaxKind::BOOLEAN => self.push(node.text_range(), TokenType::Keyword, 0),
+            SyntaxKind::INTEGER => self.push(node.text_range(), TokenType::Number, 0),
+            SyntaxKind::QUOTED_STRING => self.quoted(node, TokenType::String),
+            SyntaxKind::LINE_STRING => self.push(node.text_range(), TokenType::String, 0),
+            SyntaxKind::BLOCK_STRING_HEADER => {
+                for token in node.children_with_tokens().filter_map(|it| it.into_token()) {
+                    if token.kind() != SyntaxKind::NEWLINE {
+                        self.push(token.text_range(), TokenType::Operator, 0);
+                    }
+                }
+            }
+            _ => node.children().for_each(|child| self.node(&child)),
+        }
+    }
+
+    /// Pushes `#name` as a macro and its argument, without surrounding spaces, as a string.
+    fn directive(&mut self, node: &SyntaxNode) {
+        let Some(name) = child_token(node, SyntaxKind::WORD) else { return };
+        self.push(
+            TextRange::new(node.text_range().start(), name.text_range().end()),
+            TokenType::Macro,
+            0,
+        );
+        let Some(args) = node.children().find(|n| n.kind() == SyntaxKind::LINE_STRING) else {
+            return;
+        };
+        let text = args.text().to_string();
+        let trimmed = text.trim_start();
+        let start = args.text_range().end() - TextSize::of(trimmed);
+        self.push(TextRange::at(start, TextSize::of(trimmed.trim_end())), TokenType::String, 0);
+    }
+
+    /// Pushes a key or string, splitting out the escape sequences of its quoted text.
+    fn quoted(&mut self, node: &SyntaxNode, ty: TokenType) {
+        let mut start = node.text_range().start();
+        if let Some(string) = child_token(node, SyntaxKind::STRING) {
+            let base = string.text_range().start();
+            for (range, valid) in escape_sequences(string.text()) {
+                let range = range + base;
+                self.push(TextRange::new(start, range.start()), ty, 0);
+                self.push(range, TokenType::Escape, if valid { 0 } else { INVALID });
+                start = range.end();
+            }
+        }
+        self.push(TextRange::new(start, node.text_range().end()), ty, 0);
+    }
+
+    /// Pushes a token per line of `range`, skipping empty ones.
+    fn push(&mut self, range: TextRange, ty: TokenType, modifiers: u32) {
+        let text = &self.doc.text[range];
+        let mut offset = range.start();
+        for line in text.split_inclusive('\n') {
+            let line_range =
+                TextRange::at(offset, TextSize::of(line.trim_end_matches(['\n', '\r'])));
+            offset += TextSize::of(line);
+            if line_range.is_empty() {
+                continue;
+            }
+            let start = self.doc.line_index.position(&self.doc.text, line_range.start());
+            let end = self.doc.line_index.position(&self.doc.text, line_range.end());
+            let delta_start = match start.line == self.line {
+                true => start.character - self.start,
+                false => start.character,
+            };
+            self.tokens.push(SemanticToken {
+                delta_line: start.line - self.line,
+                delta_start,
+                length: end.character - start.character,
+                token_type: ty as u32,
+                token_modifiers_bitset: modifiers,
+            });
+            (self.line, self.start) = (start.line, start.character);
+        }
+    }
+}
+
+fn child_token(node: &SyntaxNode, kind: SyntaxKind) -> Option<SyntaxToken> {
+    node.children_with_tokens().filter_map(|it| it.into_token()).find(|it| it.kind() == kind)
+}
@@ -0,0 +1,211 @@
+use crate::{Document, uri};
+use lsp_types::{DocumentSymbol, Location, OneOf, Range, SymbolKind, Uri, WorkspaceSymbol};
+use mical_cli_config::{key_text, walk_items};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{
+    TextRange,
+    ast::{self, AstChildren, AstNode},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Returns the entries and prefix blocks of `doc` as a tree, each named by its full key.
+pub(crate) fn document_symbols(doc: &Document) -> Vec<DocumentSymbol> {
+    symbols(doc, doc.source_file().items(), "")
+}
+
+fn symbols(doc: &Document, items: AstChildren<ast::Item>, prefix: &str) -> Vec<DocumentSymbol> {
+    let mut result = Vec::new();
+    for item in items {
+        let (key, kind, detail, children) = match &item {
+            ast::Item::Entry(entry) => {
+                let Some(key) = entry.key() else { continue };
+                let kind = match entry.value() {
+                    Some(ast::Value::Boolean(_)) => SymbolKind::BOOLEAN,
+                    Some(ast::Value::Integer(_)) => SymbolKind::NUMBER,
+                    _ => SymbolKind::STRING,
+                };
+                let detail = entry.value().map(|value| {
+                    let text = value.syntax().to_string();
+                    text.lines().next().unwrap_or_default().to_owned()
+                });
+                (key, kind, detail, None)
+            }
+            ast::Item::PrefixBlock(block) => {
+                let Some(key) = block.key() else { continue };
+                (key, SymbolKind::NAMESPACE, None, Some(block))
+            }
+            ast::Item::Directive(_) => continue,
+        };
+        let Some(text) = key_text(&key) else { continue };
+        let full_key = format!("{prefix}{text}");
+        let children = children.map(|block| symbols(doc, block.items(), &full_key));
+        #[allow(deprecated)]
+        result.push(DocumentSymbol {
+            name: full_key,
+            detail,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: doc
+                .line_index
+                .range(&doc.text, trim_newline(&doc.text, item.syntax().text_range())),
+            selection_range: doc.line_index.range(&doc.text, key.syntax().text_range()),
+            children,
+        });
+    }
+    result
+}
+
+/// Files longer than this, in bytes, are left out of the workspace symbols.
+const MAX_FILE_LEN: u64 = 4 << 20;
+
+/// Directories that hold build output or dependencies rather than sources, left out of the
+/// workspace symbols like hidden ones.
+const IGNORED_DIRS: [&str; 2] = ["target", "node_modules"];
+
+/// The symbols of the `.mical` files below the workspace roots, read on the first search and then
+/// kept up to date from the documents the client edits and the file changes it reports.
+#[derive(Default)]
+pub(crate) struct WorkspaceSymbols {
+    /// The URI and symbols of each file, or `None` before the first search.
+    files: Option<BTreeMap<PathBuf, (Uri, Vec<Symbol>)>>,
+}
+
+/// An entry or prefix block of a workspace file, named by its full key.
+struct Symbol {
+    name: String,
+    kind: SymbolKind,
+    range: Range,
+}
+
+impl WorkspaceSymbols {
+    /// Searches for full keys containing `query`, ignoring case. Open documents are searched as
+    /// edited, the other files are read with `options`.
+    // `Uri` caches its parsed parts in cells that never affect hashing.
+    #[allow(clippy::mutable_key_type)]
+    pub(crate) fn search(
+        &mut self,
+        roots: &[PathBuf],
+        open: &HashMap<Uri, Document>,
+        options: &ParseOptions,
+        query: &str,
+    ) -> Vec<WorkspaceSymbol> {
+        let files = self.files.get_or_insert_with(|| {
+            let mut paths = Vec::new();
+            for root in roots {
+                collect_files(root, &mut paths);
+            }
+            let mut files = BTreeMap::new();
+            for path in paths {
+                let Some(uri) = uri::from_path(&path) else { continue };
+                let symbols = match open.get(&uri) {
+                    Some(doc) => file_symbols(doc),
+                    None => match read(&path, options) {
+                        Some(doc) => file_symbols(&doc),
+                        None => continue,
+                    },
+                };
+                files.insert(path, (uri, symbols));
+            }
+            files
+        });
+        let query = query.to_lowercase();
+        let mut found = Vec::new();
+        for (uri, symbols) in files.values() {
+            for symbol in symbols.iter().filter(|s| s.name.to_lowercase().contains(&query)) {
+                found.push(WorkspaceSymbol {
+                    name: symbol.name.clone(),
+                    kind: symbol.kind,
+                    tags: None,
+                    container_name: None,
+                    location: OneOf::Left(Location::new(uri.clone(), symbol.range)),
+                    data: None,
+                });
+            }
+        }
+        found
+    }
+
+    /// Takes the symbols of `doc`, the open document at `uri`, if it is a workspace file.
+    pub(crate) fn document_changed(&mut self, roots: &[PathBuf], uri: &Uri, doc: &Document) {
+        let Some(files) = &mut self.files else { return };
+        let Some(path) = uri::to_path(uri).filter(|path| in_workspace(roots, path)) else { return };
+        files.insert(path, (uri.clone(), file_symbols(doc)));
+    }
+
+    /// Reads the file at `uri` again with `options`, or forgets it if it is gone or no longer fits.
+    pub(crate) fn file_changed(&mut self, roots: &[PathBuf], uri: &Uri, options: &ParseOptions) {
+        let Some(files) = &mut self.files else { return };
+        let Some(path) = uri::to_path(uri).filter(|path| in_workspace(roots, path)) else { return };
+        match read(&path, options) {
+            Some(doc) => files.insert(path, (uri.clone(), file_symbols(&doc))),
+            None => files.remove(&path),
+        };
+    }
+}
+
+fn file_symbols(doc: &Document) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    walk_items(doc.source_file().items(), "", &mut |item, key, _, full_key| {
+        let kind = match item {
+            ast::Item::PrefixBlock(_) => SymbolKind::NAMESPACE,
+            _ => SymbolKind::FIELD,
+        };
+        let range = doc.line_index.range(&doc.text, key.syntax().text_range());
+        symbols.push(Symbol { name: full_key.to_owned(), kind, range });
+    });
+    symbols
+}
+
+/// Reads the file at `path` with `options`, unless it is longer than [`MAX_FILE_LEN`].
+fn read(path: &Path, options: &ParseOptions) -> Option<Document> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_LEN {
+        return None;
+    }
+    // Decoded the way editors show it: without a byte order mark.
+    let bytes = fs::read(path).ok()?;
+    Some(Document::new(mical_cli_lexer::decode(&bytes).text.into_owned(), *options))
+}
+
+/// Whether `path` is a `.mical` file that [`collect_files`] finds below one of `roots`.
+fn in_workspace(roots: &[PathBuf], path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "mical")
+        && roots.iter().any(|root| {
+            let Ok(relative) = path.strip_prefix(root) else { return false };
+            let dirs = relative.parent().into_iter().flat_map(Path::components);
+            dirs.into_iter().all(|dir| !ignored(&dir.as_os_str().to_string_lossy()))
+        })
+}
+
+/// Whether the directory `name` is hidden or holds no sources.
+fn ignored(name: &str) -> bool {
+    name.starts_with('.') || IGNORED_DIRS.contains(&name)
+}
+
+/// Collects the `.mical` files below `dir`, skipping hidden and ignored directories.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else { continue };
+        if file_type.is_dir() {
+            if !ignored(&entry.file_name().to_string_lossy()) {
+                collect_files(&path, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "mical") {
+            files.push(path);
+        }
+    }
+}
+
+/// Items include their trailing newline; symbols end with the line.
+pub(crate) fn trim_newline(text: &str, range: TextRange) -> TextRange {
+    match text[range].ends_with('\n') {
+        true => TextRange::new(range.start(), range.end() - mical_cli_syntax::TextSize::from(1)),
+        false => range,
+    }
+}
@@ -0,0 +1,54 @@
+use lsp_types::Uri;
+use std::path::{Path, PathBuf};
+
+/// Returns the path of a `file:` URI.
+pub(crate) fn to_path(uri: &Uri) -> Option<PathBuf> {
+    let path = uri.as_str().strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        match (b, tail) {
+            (b'%', [hi, lo, tail @ ..]) => {
+                let hex = std::str::from_utf8(&[*hi, *lo])
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())?;
+                bytes.push(hex);
+                rest = tail;
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Returns the `file:` URI of an absolute path.
+pub(crate) fn from_path(path: &Path) -> Option<Uri> {
+    let path = path.to_str()?;
+    let mut uri = String::from("file://");
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{b:02X}")),
+        }
+    }
+    uri.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let path = Path::new("/home/me/my config/app é.mical");
+        let uri = from_path(path).unwrap();
+        assert_eq!(uri.as_str(), "file:///home/me/my%20config/app%20%C3%A9.mical");
+        assert_eq!(to_path(&uri).unwrap(), path);
+        assert_eq!(to_path(&"untitled:Untitled-1".parse().unwrap()), None);
+    }
+}
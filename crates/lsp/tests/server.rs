@@ -0,0 +1,671 @@
+use lsp_server::{Connection, Message, Notification, Request, RequestId, ResponseError};
+use lsp_types::{
+    CodeActionContext, CodeActionOrCommand, CodeActionParams, DiagnosticSeverity,
+    DidChangeTextDocumentParams, DidChangeWatchedFilesParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentLinkParams, DocumentSymbolParams, DocumentSymbolResponse,
+    FileChangeType, FileEvent, FoldingRangeParams, GotoDefinitionParams, GotoDefinitionResponse,
+    InitializeParams, OneOf, Position, PublishDiagnosticsParams, Range, SelectionRange,
+    SelectionRangeParams, SemanticToken, SemanticTokensDeltaParams, SemanticTokensFullDeltaResult,
+    SemanticTokensParams, SemanticTokensResult, SemanticTokensServerCapabilities,
+    ServerCapabilities, TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem,
+    TextDocumentPositionParams, Uri, VersionedTextDocumentIdentifier, WorkspaceFolder,
+    WorkspaceSymbolParams, WorkspaceSymbolResponse,
+    notification::{self, Notification as _},
+    request::{self, Request as _},
+};
+use mical_cli_parser::ParseOptions;
+use pretty_assertions::assert_eq;
+use std::{fs, num::NonZeroU32, thread};
+
+struct Client {
+    connection: Connection,
+    server: Option<thread::JoinHandle<()>>,
+    next_id: i32,
+    capabilities: ServerCapabilities,
+}
+
+impl Client {
+    fn start() -> Self {
+        Client::start_with(InitializeParams::default())
+    }
+
+    fn start_with(params: InitializeParams) -> Self {
+        Client::start_with_options(params, ParseOptions::default())
+    }
+
+    fn start_with_options(params: InitializeParams, options: ParseOptions) -> Self {
+        let (client, server) = Connection::memory();
+        let server =
+            thread::spawn(move || mical_cli_lsp::run_with_options(server, &options).unwrap());
+        let mut client = Client {
+            connection: client,
+            server: Some(server),
+            next_id: 0,
+            capabilities: ServerCapabilities::default(),
+        };
+        client.capabilities = client.request::<request::Initialize>(params).capabilities;
+        client.notify::<notification::Initialized>(lsp_types::InitializedParams {});
+        client
+    }
+
+    fn request<R: request::Request>(&mut self, params: R::Params) -> R::Result {
+        self.try_request::<R>(params).unwrap()
+    }
+
+    fn try_request<R: request::Request>(
+        &mut self,
+        params: R::Params,
+    ) -> Result<R::Result, ResponseError> {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let req = Request::new(id.clone(), R::METHOD.to_owned(), params);
+        self.connection.sender.send(req.into()).unwrap();
+        loop {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(resp) if resp.id == id => {
+                    return match resp.error {
+                        Some(error) => Err(error),
+                        None => Ok(serde_json::from_value(resp.result.unwrap()).unwrap()),
+                    };
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    fn notify<N: notification::Notification>(&self, params: N::Params) {
+        let not = Notification::new(N::METHOD.to_owned(), params);
+        self.connection.sender.send(not.into()).unwrap();
+    }
+
+    fn diagnostics(&self) -> PublishDiagnosticsParams {
+        loop {
+            if let Message::Notification(not) = self.connection.receiver.recv().unwrap()
+                && not.method == notification::PublishDiagnostics::METHOD
+            {
+                return serde_json::from_value(not.params).unwrap();
+            }
+        }
+    }
+
+    fn open(&self, uri: &Uri, text: &str) {
+        self.notify::<notification::DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(uri.clone(), "mical".into(), 1, text.into()),
+        });
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.request::<request::Shutdown>(());
+        self.notify::<notification::Exit>(());
+        self.server.take().unwrap().join().unwrap();
+    }
+}
+
+fn uri() -> Uri {
+    "file:///config.mical".parse().unwrap()
+}
+
+#[test]
+fn publishes_syntax_config_and_lint_diagnostics() {
+    let client = Client::start();
+    client.open(&uri(), "a\t1\nb 'x\\q'\nc tru\n");
+    let params = client.diagnostics();
+    assert_eq!(params.uri, uri());
+    let summary = params
+        .diagnostics
+        .iter()
+        .map(|d| (d.range.start.line, d.severity.unwrap(), d.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            (0, DiagnosticSeverity::ERROR, "tab separating is not allowed"),
+            (1, DiagnosticSeverity::ERROR, "invalid escape sequence '\\q'"),
+            (2, DiagnosticSeverity::WARNING, "'tru' is a string but looks like a boolean"),
+        ]
+    );
+}
+
+#[test]
+fn reads_documents_with_the_parse_options() {
+    let options = ParseOptions { tab_width: NonZeroU32::new(4), ..Default::default() };
+    let client = Client::start_with_options(InitializeParams::default(), options);
+    client.open(&uri(), "a. {\n\tb 1\n}\n");
+    assert_eq!(client.diagnostics().diagnostics, []);
+}
+
+#[test]
+fn quick_fixes_for_errors_and_lints() {
+    let mut client = Client::start();
+    client.open(&uri(), "a\t1\nb 10 items\n");
+    client.diagnostics();
+    let actions = client
+        .request::<request::CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier::new(uri()),
+            range: Range::new(Position::new(0, 0), Position::new(1, 3)),
+            context: CodeActionContext::default(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let actions = actions
+        .into_iter()
+        .map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => {
+                let edits = action.edit.unwrap().changes.unwrap().remove(&uri()).unwrap();
+                let edits = edits.into_iter().map(|e| (e.range, e.new_text)).collect::<Vec<_>>();
+                (action.title, edits)
+            }
+            CodeActionOrCommand::Command(_) => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        [
+            (
+                "replace the tab with a space".to_owned(),
+                vec![(Range::new(Position::new(0, 1), Position::new(0, 2)), " ".to_owned())]
+            ),
+            (
+                "quote the string".to_owned(),
+                vec![(
+                    Range::new(Position::new(1, 2), Position::new(1, 10)),
+                    "\"10 items\"".to_owned()
+                )]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn code_actions_accept_a_reversed_range() {
+    let mut client = Client::start();
+    client.open(&uri(), "a\t1\nb 2\n");
+    client.diagnostics();
+    let actions = client
+        .request::<request::CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier::new(uri()),
+            range: Range::new(Position::new(1, 3), Position::new(0, 0)),
+            context: CodeActionContext::default(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let [CodeActionOrCommand::CodeAction(action)] = &actions[..] else { panic!("{actions:?}") };
+    assert_eq!(action.title, "replace the tab with a space");
+}
+
+#[test]
+fn tab_indent_fix_follows_the_document_indentation() {
+    let mut client = Client::start();
+    client.open(&uri(), "a. {\n    b 1\n\tc 2\n}\n");
+    client.diagnostics();
+    let actions = client
+        .request::<request::CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier::new(uri()),
+            range: Range::new(Position::new(2, 0), Position::new(2, 1)),
+            context: CodeActionContext::default(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let [CodeActionOrCommand::CodeAction(action)] = &actions[..] else { panic!("{actions:?}") };
+    assert_eq!(action.title, "replace the tab indent with spaces");
+    let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri()];
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].range, Range::new(Position::new(2, 0), Position::new(2, 1)));
+    assert_eq!(edits[0].new_text, "    ");
+}
+
+#[test]
+fn answers_invalid_params_and_keeps_running() {
+    let mut client = Client::start();
+    client
+        .connection
+        .sender
+        .send(
+            Notification::new(
+                notification::DidOpenTextDocument::METHOD.to_owned(),
+                serde_json::json!({ "textDocument": 1 }),
+            )
+            .into(),
+        )
+        .unwrap();
+    loop {
+        if let Message::Notification(not) = client.connection.receiver.recv().unwrap()
+            && not.method == notification::LogMessage::METHOD
+        {
+            break;
+        }
+    }
+
+    client.next_id += 1;
+    let id = RequestId::from(client.next_id);
+    let req = Request::new(id.clone(), request::FoldingRangeRequest::METHOD.to_owned(), 1);
+    client.connection.sender.send(req.into()).unwrap();
+    let error = loop {
+        if let Message::Response(resp) = client.connection.receiver.recv().unwrap()
+            && resp.id == id
+        {
+            break resp.error.unwrap();
+        }
+    };
+    assert_eq!(error.code, lsp_server::ErrorCode::InvalidParams as i32);
+
+    client.open(&uri(), "a 1\n");
+    assert_eq!(client.diagnostics().diagnostics, []);
+}
+
+fn doc() -> TextDocumentIdentifier {
+    TextDocumentIdentifier::new(uri())
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+    Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+}
+
+#[test]
+fn folds_prefix_blocks_and_block_strings() {
+    let mut client = Client::start();
+    client.open(
+        &uri(),
+        "server. {\n  host localhost\n  motd |\n    hello\n    world\n}\nempty. {\n}\n",
+    );
+    client.diagnostics();
+    let ranges = client
+        .request::<request::FoldingRangeRequest>(FoldingRangeParams {
+            text_document: doc(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let ranges = ranges.iter().map(|r| (r.start_line, r.end_line)).collect::<Vec<_>>();
+    assert_eq!(ranges, [(0, 4), (2, 4)]);
+}
+
+#[test]
+fn document_symbols_use_full_keys() {
+    let mut client = Client::start();
+    client.open(&uri(), "name app\nserver. {\n  port 80\n  tls true\n}\n");
+    client.diagnostics();
+    let response = client
+        .request::<request::DocumentSymbolRequest>(DocumentSymbolParams {
+            text_document: doc(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let DocumentSymbolResponse::Nested(symbols) = response else { panic!("flat symbols") };
+    let names = |symbols: &[lsp_types::DocumentSymbol]| {
+        symbols.iter().map(|s| (s.name.clone(), s.kind, s.detail.clone())).collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(&symbols),
+        [
+            ("name".to_owned(), lsp_types::SymbolKind::STRING, Some("app".to_owned())),
+            ("server.".to_owned(), lsp_types::SymbolKind::NAMESPACE, None),
+        ]
+    );
+    assert_eq!(symbols[1].range, range((1, 0), (4, 1)));
+    assert_eq!(symbols[1].selection_range, range((1, 0), (1, 7)));
+    assert_eq!(
+        names(symbols[1].children.as_ref().unwrap()),
+        [
+            ("server.port".to_owned(), lsp_types::SymbolKind::NUMBER, Some("80".to_owned())),
+            ("server.tls".to_owned(), lsp_types::SymbolKind::BOOLEAN, Some("true".to_owned())),
+        ]
+    );
+}
+
+/// Searches the workspace for `query`, returning the name, file name and line of each symbol.
+fn workspace_symbols(client: &mut Client, query: &str) -> Vec<(String, String, u32)> {
+    let response = client
+        .request::<request::WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            query: query.into(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    // Symbols with a full location deserialize as either variant; the untagged enum picks the first.
+    let symbols = match response {
+        WorkspaceSymbolResponse::Flat(symbols) => {
+            symbols.into_iter().map(|s| (s.name, s.location)).collect::<Vec<_>>()
+        }
+        WorkspaceSymbolResponse::Nested(symbols) => symbols
+            .into_iter()
+            .map(|s| match s.location {
+                OneOf::Left(location) => (s.name, location),
+                OneOf::Right(_) => panic!("location without range"),
+            })
+            .collect(),
+    };
+    symbols
+        .into_iter()
+        .map(|(name, location)| {
+            let file = location.uri.as_str().rsplit('/').next().unwrap().to_owned();
+            (name, file, location.range.start.line)
+        })
+        .collect()
+}
+
+#[test]
+fn workspace_symbols_search_files_and_open_documents() {
+    let dir = std::env::temp_dir().join(format!("mical-lsp-workspace-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::create_dir_all(dir.join(".hidden")).unwrap();
+    fs::write(dir.join("a.mical"), "server.port 80\n").unwrap();
+    fs::write(dir.join("nested/b.mical"), "db. {\n  port 5432\n}\n").unwrap();
+    fs::write(dir.join(".hidden/c.mical"), "cache.port 6379\n").unwrap();
+    fs::write(dir.join("notes.txt"), "port 1\n").unwrap();
+    let root: Uri = format!("file://{}", dir.display()).parse().unwrap();
+
+    let mut client = Client::start_with(InitializeParams {
+        workspace_folders: Some(vec![WorkspaceFolder { uri: root, name: "test".into() }]),
+        ..Default::default()
+    });
+    // The open document wins over its contents on disk.
+    let a: Uri = format!("file://{}/a.mical", dir.display()).parse().unwrap();
+    client.open(&a, "client.PORT 8080\n");
+    client.diagnostics();
+    let symbols = workspace_symbols(&mut client, "port");
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        symbols,
+        [
+            ("client.PORT".to_owned(), "a.mical".to_owned(), 0),
+            ("db.port".to_owned(), "b.mical".to_owned(), 1),
+        ]
+    );
+}
+
+#[test]
+fn workspace_symbols_follow_edits_and_file_changes() {
+    let dir = std::env::temp_dir().join(format!("mical-lsp-changes-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("target")).unwrap();
+    fs::write(dir.join("a.mical"), "a.x 1\n").unwrap();
+    fs::write(dir.join("target/t.mical"), "t.x 1\n").unwrap();
+    let root: Uri = format!("file://{}", dir.display()).parse().unwrap();
+    let file = |name: &str| -> Uri { format!("file://{}/{name}", dir.display()).parse().unwrap() };
+    let found = |symbols: Vec<(String, String, u32)>| {
+        symbols.into_iter().map(|(name, file, _)| format!("{file}: {name}")).collect::<Vec<_>>()
+    };
+
+    let mut client = Client::start_with(InitializeParams {
+        workspace_folders: Some(vec![WorkspaceFolder { uri: root, name: "test".into() }]),
+        ..Default::default()
+    });
+    assert_eq!(found(workspace_symbols(&mut client, "x")), ["a.mical: a.x"]);
+
+    fs::write(dir.join("b.mical"), "b.x 1\n").unwrap();
+    client.notify::<notification::DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent::new(file("b.mical"), FileChangeType::CREATED)],
+    });
+    assert_eq!(found(workspace_symbols(&mut client, "x")), ["a.mical: a.x", "b.mical: b.x"]);
+
+    client.open(&file("a.mical"), "c.x 1\n");
+    client.diagnostics();
+    client.notify::<notification::DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier::new(file("a.mical"), 2),
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "d.x 1\n".into(),
+        }],
+    });
+    client.diagnostics();
+    assert_eq!(found(workspace_symbols(&mut client, "x")), ["a.mical: d.x", "b.mical: b.x"]);
+
+    client.notify::<notification::DidCloseTextDocument>(DidCloseTextDocumentParams {
+        text_document: TextDocumentIdentifier::new(file("a.mical")),
+    });
+    fs::remove_file(dir.join("b.mical")).unwrap();
+    client.notify::<notification::DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent::new(file("b.mical"), FileChangeType::DELETED)],
+    });
+    let symbols = workspace_symbols(&mut client, "x");
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(found(symbols), ["a.mical: a.x"]);
+}
+
+#[test]
+fn links_include_and_schema_targets() {
+    let mut client = Client::start();
+    let uri: Uri = "file:///etc/app/config.mical".parse().unwrap();
+    client.open(
+        &uri,
+        "#include  base.mical \n#schema https://example.com/app.json\n#duplicates error\n",
+    );
+    client.diagnostics();
+    let links = client
+        .request::<request::DocumentLinkRequest>(DocumentLinkParams {
+            text_document: TextDocumentIdentifier::new(uri),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let links = links
+        .into_iter()
+        .map(|link| (link.range, link.target.unwrap().as_str().to_owned()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        links,
+        [
+            (range((0, 10), (0, 20)), "file:///etc/app/base.mical".to_owned()),
+            (range((1, 8), (1, 36)), "https://example.com/app.json".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn selection_ranges_grow_through_the_tree() {
+    let mut client = Client::start();
+    client.open(&uri(), "server. {\n  port 80\n}\n");
+    client.diagnostics();
+    let ranges = client
+        .request::<request::SelectionRangeRequest>(SelectionRangeParams {
+            text_document: doc(),
+            positions: vec![Position::new(1, 8)],
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let mut chain = Vec::new();
+    let mut next = Some(&ranges[0]);
+    while let Some(SelectionRange { range, parent }) = next {
+        chain.push(*range);
+        next = parent.as_deref();
+    }
+    assert_eq!(chain, [range((1, 7), (1, 9)), range((1, 2), (2, 0)), range((0, 0), (3, 0)),]);
+}
+
+#[test]
+fn definitions_span_prefix_blocks() {
+    let mut client = Client::start();
+    client.open(&uri(), "server.port 80\nserver. {\n  port 8080\n  host a\n}\nserver. {\n}\n");
+    client.diagnostics();
+    let mut goto = |line, character| {
+        let response = client.request::<request::GotoDefinition>(GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: doc(),
+                position: Position::new(line, character),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        match response {
+            Some(GotoDefinitionResponse::Array(locations)) => {
+                locations.into_iter().map(|l| l.range).collect::<Vec<_>>()
+            }
+            _ => panic!("unexpected response"),
+        }
+    };
+    assert_eq!(goto(2, 3), [range((0, 0), (0, 11)), range((2, 2), (2, 6))]);
+    assert_eq!(goto(5, 0), [range((1, 0), (1, 7)), range((5, 0), (5, 7))]);
+    assert_eq!(goto(0, 13), []);
+}
+
+impl Client {
+    fn semantic_tokens(&mut self) -> (String, Vec<SemanticToken>) {
+        let result = self.request::<request::SemanticTokensFullRequest>(SemanticTokensParams {
+            text_document: doc(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let Some(SemanticTokensResult::Tokens(tokens)) = result else { panic!("no tokens") };
+        (tokens.result_id.unwrap(), tokens.data)
+    }
+
+    /// Decodes `tokens` into the text they cover, their type and their modifiers.
+    fn decode(&self, text: &str, tokens: &[SemanticToken]) -> Vec<(String, String, Vec<String>)> {
+        let Some(SemanticTokensServerCapabilities::SemanticTokensOptions(options)) =
+            &self.capabilities.semantic_tokens_provider
+        else {
+            panic!("no semantic tokens provider")
+        };
+        let legend = &options.legend;
+        let lines = text.lines().collect::<Vec<_>>();
+        let (mut line, mut start) = (0, 0);
+        tokens
+            .iter()
+            .map(|token| {
+                if token.delta_line > 0 {
+                    (line, start) = (line + token.delta_line, 0);
+                }
+                start += token.delta_start;
+                let chars = lines[line as usize].chars().skip(start as usize);
+                let covered = chars.take(token.length as usize).collect();
+                let modifiers = (0..legend.token_modifiers.len())
+                    .filter(|bit| token.token_modifiers_bitset & (1 << bit) != 0)
+                    .map(|bit| legend.token_modifiers[bit].as_str().to_owned())
+                    .collect();
+                let ty = legend.token_types[token.token_type as usize].as_str().to_owned();
+                (covered, ty, modifiers)
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn semantic_tokens_follow_value_types() {
+    let mut client = Client::start();
+    let text = "# note\n#duplicates  error \nflag true\nflags true story\nport 42\nports 42 items\nserver. {\n  name \"a\\tb\\q\"\n}\n'k\\'' |\n  hello\n";
+    client.open(&uri(), text);
+    client.diagnostics();
+    let (_, tokens) = client.semantic_tokens();
+    let token = |text: &str, ty: &str| (text.to_owned(), ty.to_owned(), Vec::new());
+    assert_eq!(
+        client.decode(text, &tokens),
+        [
+            token("# note", "comment"),
+            token("#duplicates", "macro"),
+            token("error", "string"),
+            token("flag", "property"),
+            token("true", "keyword"),
+            token("flags", "property"),
+            token("true story", "string"),
+            token("port", "property"),
+            token("42", "number"),
+            token("ports", "property"),
+            token("42 items", "string"),
+            token("server.", "namespace"),
+            token("name", "property"),
+            token("\"a", "string"),
+            token("\\t", "escapeSequence"),
+            token("b", "string"),
+            ("\\q".to_owned(), "escapeSequence".to_owned(), vec!["invalid".to_owned()]),
+            token("\"", "string"),
+            token("'k", "property"),
+            token("\\'", "escapeSequence"),
+            token("'", "property"),
+            token("|", "operator"),
+            token("hello", "string"),
+        ]
+    );
+}
+
+#[test]
+fn semantic_tokens_delta_against_previous_result() {
+    let mut client = Client::start();
+    client.open(&uri(), "a 1\nb 2\nc 3\n");
+    client.diagnostics();
+    let (result_id, old) = client.semantic_tokens();
+
+    client.notify::<notification::DidChangeTextDocument>(lsp_types::DidChangeTextDocumentParams {
+        text_document: lsp_types::VersionedTextDocumentIdentifier::new(uri(), 2),
+        content_changes: vec![lsp_types::TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "a 1\nb two\nc 3\n".into(),
+        }],
+    });
+    client.diagnostics();
+    let mut delta = |previous_result_id: &str| {
+        client
+            .request::<request::SemanticTokensFullDeltaRequest>(SemanticTokensDeltaParams {
+                text_document: doc(),
+                previous_result_id: previous_result_id.to_owned(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .unwrap()
+    };
+    let SemanticTokensFullDeltaResult::TokensDelta(delta_result) = delta(&result_id) else {
+        panic!("expected a delta")
+    };
+    // Only the type of `two` changed.
+    assert_eq!(delta_result.edits.len(), 1);
+    let edit = &delta_result.edits[0];
+    assert_eq!((edit.start, edit.delete_count), (15, 5));
+    let mut applied = old.clone();
+    applied.splice(3..4, edit.data.clone().unwrap());
+
+    // An unknown result id gets the full tokens, which the delta brought the old ones up to.
+    let SemanticTokensFullDeltaResult::Tokens(full) = delta("stale") else {
+        panic!("expected full tokens")
+    };
+    assert_eq!(applied, full.data);
+    assert_ne!(applied, old);
+}
+
+#[test]
+fn rename_keys_across_prefix_blocks() {
+    let mut client = Client::start();
+    let text = "server.http.port 80\nserver. {\n  http. {\n    port 8080\n  }\n  host a\n}\n";
+    client.open(&uri(), text);
+    client.diagnostics();
+    let position = |line, character| TextDocumentPositionParams {
+        text_document: doc(),
+        position: Position::new(line, character),
+    };
+
+    let prepared = client.request::<request::PrepareRenameRequest>(position(2, 3));
+    assert_eq!(
+        prepared,
+        Some(lsp_types::PrepareRenameResponse::RangeWithPlaceholder {
+            range: range((2, 2), (2, 7)),
+            placeholder: "http.".into(),
+        })
+    );
+
+    let mut rename = |line, character, new_name: &str| {
+        client.try_request::<request::Rename>(lsp_types::RenameParams {
+            text_document_position: position(line, character),
+            new_name: new_name.into(),
+            work_done_progress_params: Default::default(),
+        })
+    };
+    let edit = rename(2, 3, "web.").unwrap().unwrap();
+    let edits = edit.changes.unwrap().remove(&uri()).unwrap();
+    let edits = edits.into_iter().map(|e| (e.range, e.new_text)).collect::<Vec<_>>();
+    assert_eq!(
+        edits,
+        [(range((0, 7), (0, 11)), "web".to_owned()), (range((2, 2), (2, 6)), "web".to_owned())]
+    );
+
+    let error = rename(5, 3, "http.port").unwrap_err();
+    assert_eq!(error.message, "cannot rename: key 'server.http.port' already exists");
+}
@@ -1,11 +1,21 @@
-use mical_cli_syntax::SyntaxKind;
-use std::borrow::Cow;
+use mical_cli_syntax::{Fix, SyntaxError, SyntaxKind, TextRange};
+use std::{borrow::Cow, mem, ops::ControlFlow};
 
 pub(crate) enum Event {
     StartNode { kind: SyntaxKind },
     FinishNode,
     Token { kind: SyntaxKind, len: u32 },
-    Error { message: Cow<'static, str> },
+    Error { message: Cow<'static, str>, fix: Option<Fix> },
+}
+
+/// A step of the preorder walk over the tree [`parse`](crate::parse) builds, as reported by
+/// [`parse_with`](crate::parse_with).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseEvent<'s> {
+    StartNode(SyntaxKind),
+    FinishNode,
+    Token { kind: SyntaxKind, range: TextRange, text: &'s str },
+    Error(SyntaxError),
 }
 
 #[derive(Debug)]
@@ -21,15 +31,20 @@ const _: () = const {
     assert!(size_of::<EventRaw>() == 8);
 };
 
+/// Events not yet passed on. They are held back while a node started before them has no kind yet.
 #[derive(Debug)]
 pub(crate) struct EventContainer {
     events: Vec<EventRaw>,
-    errors: Vec<Cow<'static, str>>,
+    errors: Vec<(Cow<'static, str>, Option<Fix>)>,
+    /// Number of tombstones in `events`.
+    pending: u32,
+    /// Number of events already passed on, which positions count from.
+    flushed: usize,
 }
 
 impl EventContainer {
     pub(crate) fn new() -> Self {
-        EventContainer { events: Vec::new(), errors: Vec::new() }
+        EventContainer { events: Vec::new(), errors: Vec::new(), pending: 0, flushed: 0 }
     }
 
     pub(crate) fn push(&mut self, event: Event) {
@@ -39,20 +54,45 @@ impl EventContainer {
 
     pub(crate) fn push_tombstone(&mut self) {
         self.events.push(EventRaw::Tombstone);
+        self.pending += 1;
     }
 
-    pub(crate) fn replace_tombstone(&mut self, index: usize, event: Event) {
+    pub(crate) fn replace_tombstone(&mut self, pos: usize, event: Event) {
+        let index = pos - self.flushed;
         match &self.events[index] {
             EventRaw::Tombstone => {
                 let event_raw = self.convert_event(event);
                 self.events[index] = event_raw;
+                self.pending -= 1;
             }
-            _ => panic!("Expected a tombstone at index {index}"),
+            _ => panic!("Expected a tombstone at position {pos}"),
         }
     }
 
+    /// Position of the next event.
     pub(crate) fn len(&self) -> usize {
-        self.events.len()
+        self.flushed + self.events.len()
+    }
+
+    /// Passes the held events to `f`, unless a tombstone is left among them.
+    pub(crate) fn flush(&mut self, mut f: impl FnMut(Event)) {
+        if self.pending > 0 || self.events.is_empty() {
+            return;
+        }
+        self.flushed += self.events.len();
+        for event_raw in self.events.drain(..) {
+            f(match event_raw {
+                EventRaw::StartNode { kind } => Event::StartNode { kind },
+                EventRaw::FinishNode => Event::FinishNode,
+                EventRaw::Token { kind, len } => Event::Token { kind, len },
+                EventRaw::Error { message_index } => {
+                    let (message, fix) = mem::take(&mut self.errors[message_index as usize]);
+                    Event::Error { message, fix }
+                }
+                EventRaw::Tombstone => unreachable!("no tombstone is pending"),
+            });
+        }
+        self.errors.clear();
     }
 
     fn convert_event(&mut self, event: Event) -> EventRaw {
@@ -60,57 +100,57 @@ impl EventContainer {
             Event::StartNode { kind } => EventRaw::StartNode { kind },
             Event::FinishNode => EventRaw::FinishNode,
             Event::Token { kind, len } => EventRaw::Token { kind, len },
-            Event::Error { message } => {
+            Event::Error { message, fix } => {
                 let message_index = self.errors.len() as u32;
-                self.errors.push(message);
+                self.errors.push((message, fix));
                 EventRaw::Error { message_index }
             }
         }
     }
-
-    // pub(crate) fn take(&mut self, index: usize) -> Event {
-    //     match mem::replace(&mut self.events[index], EventRaw::Tombstone) {
-    //         EventRaw::StartNode { kind, forward_parent } => {
-    //             Event::StartNode { kind, forward_parent }
-    //         }
-    //         EventRaw::FinishNode => Event::FinishNode,
-    //         EventRaw::Token { kind, len } => Event::Token { kind, len },
-    //         EventRaw::Error { message_index } => {
-    //             let message = self.errors[message_index as usize].clone();
-    //             Event::Error { message }
-    //         }
-    //         EventRaw::Tombstone => panic!("Tombstone should be replaced before taking"),
-    //     }
-    // }
 }
 
-impl IntoIterator for EventContainer {
-    type Item = Event;
-    type IntoIter = IntoIter;
+/// Turns events into [`ParseEvent`]s for the sink of [`parse_with`](crate::parse_with).
+pub(crate) struct Emitter<'s, 'k> {
+    source: &'s str,
+    /// Offset of the next token; errors are placed here.
+    offset: u32,
+    sink: &'k mut dyn FnMut(ParseEvent<'s>) -> ControlFlow<()>,
+    stopped: bool,
+}
 
-    fn into_iter(self) -> Self::IntoIter {
-        IntoIter { iter: self.events.into_iter(), errors: self.errors }
+impl<'s, 'k> Emitter<'s, 'k> {
+    pub(crate) fn new(
+        source: &'s str,
+        sink: &'k mut dyn FnMut(ParseEvent<'s>) -> ControlFlow<()>,
+    ) -> Self {
+        Emitter { source, offset: 0, sink, stopped: false }
     }
-}
 
-pub(crate) struct IntoIter {
-    iter: std::vec::IntoIter<EventRaw>,
-    errors: Vec<Cow<'static, str>>,
-}
+    /// Whether the sink has asked to stop; later events are dropped.
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped
+    }
 
-impl Iterator for IntoIter {
-    type Item = Event;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|event_raw| match event_raw {
-            EventRaw::StartNode { kind } => Event::StartNode { kind },
-            EventRaw::FinishNode => Event::FinishNode,
-            EventRaw::Token { kind, len } => Event::Token { kind, len },
-            EventRaw::Error { message_index } => {
-                let message = self.errors[message_index as usize].clone();
-                Event::Error { message }
+    pub(crate) fn emit(&mut self, event: Event) {
+        if self.stopped {
+            return;
+        }
+        let event = match event {
+            Event::StartNode { kind } => ParseEvent::StartNode(kind),
+            Event::FinishNode => ParseEvent::FinishNode,
+            Event::Token { kind, len } => {
+                let range = TextRange::at(self.offset.into(), len.into());
+                self.offset += len;
+                ParseEvent::Token { kind, range, text: &self.source[range] }
+            }
+            Event::Error { message, fix } => {
+                let error = SyntaxError::new(message, TextRange::empty(self.offset.into()));
+                ParseEvent::Error(match fix {
+                    Some(fix) => error.with_fix(fix),
+                    None => error,
+                })
             }
-            EventRaw::Tombstone => panic!("Tombstone should be replaced before iteration"),
-        })
+        };
+        self.stopped = (self.sink)(event).is_break();
     }
 }
@@ -1,31 +1,90 @@
 use crate::{parser::*, token_set::TokenSet};
-use mical_cli_syntax::{SyntaxKind, SyntaxKind::*, T};
+use mical_cli_syntax::{Fix, Indel, SyntaxKind, SyntaxKind::*, T, TextEdit, TextRange, TextSize};
+use std::num::NonZeroU32;
 
 mod item;
 mod key;
 mod value;
 
 pub(crate) fn source_file(p: &mut Parser) {
-    let m = p.start();
+    let m = p.start().resolve(p, SOURCE_FILE);
+
+    // Longer sources are not even lexed: the tree holds them as one error node, unless they are
+    // too long for its offsets.
+    let max_source_len = p.limits().max_source_len.min(u32::MAX as usize);
+    if p.source_len() > max_source_len {
+        p.error(format!(
+            "the source is {} bytes long, over the limit of {max_source_len}",
+            p.source_len()
+        ));
+        if p.source_len() <= u32::MAX as usize {
+            let error = p.start();
+            p.bump_rest_unlexed(T![string]);
+            error.complete(p, ERROR);
+        }
+        m.complete(p);
+        return;
+    }
 
     p.eat(T![shebang]);
 
-    while !p.at_eof() {
+    while !p.at_eof() && !p.is_stopped() {
         item::item(p);
     }
 
-    m.complete(p, SOURCE_FILE);
+    m.complete(p);
 }
 
-fn is_rest_of_line_blank(p: &Parser, n: usize) -> bool {
+fn is_rest_of_line_blank(p: &mut Parser, n: usize) -> bool {
     if p.nth_at(n, T!['\n']) || p.nth_at_eof(n) {
         return true;
     }
     p.nth_at(n, T![' ']) && (p.nth_at(n + 1, T!['\n']) || p.nth_at_eof(n + 1))
 }
 
+/// Whether an inline comment starts at the `n`th token: a space and a `#`, if
+/// [`ParseOptions::inline_comments`](crate::ParseOptions::inline_comments) is set.
+fn is_inline_comment_at(p: &mut Parser, n: usize) -> bool {
+    p.options().inline_comments && p.nth_at(n, T![' ']) && p.nth_at(n + 1, T![#])
+}
+
+/// Returns the width of the indentation that starts at the `n`th token, and its number of
+/// tokens. Tabs count only if [`ParseOptions::tab_width`](crate::ParseOptions::tab_width) is set;
+/// otherwise the indentation ends at the first tab.
+fn indent_at(p: &mut Parser, n: usize) -> (u32, usize) {
+    let tab_width = p.options().tab_width.map(NonZeroU32::get);
+    let (mut width, mut count) = (0u32, 0);
+    loop {
+        if let Some(len) = p.nth_len_if(n + count, T![' ']) {
+            width = width.saturating_add(len);
+        } else if let Some(tab_width) = tab_width
+            && let Some(len) = p.nth_len_if(n + count, T!['\t'])
+        {
+            // Each tab moves to the next tab stop.
+            width = (width / tab_width).saturating_add(len).saturating_mul(tab_width);
+        } else {
+            break;
+        }
+        count += 1;
+    }
+    (width, count)
+}
+
+/// Consumes the indentation at the current token, returning its width.
+fn eat_indent(p: &mut Parser) -> u32 {
+    let (width, count) = indent_at(p, 0);
+    for _ in 0..count {
+        p.bump_any();
+    }
+    width
+}
+
 fn eat_to_end_of_line(p: &mut Parser) {
     while !(p.at(T!['\n']) || p.at_eof()) {
         p.bump_any();
     }
 }
+
+fn fix(message: &str, indel: Indel) -> Fix {
+    Fix::new(message, TextEdit::new(vec![indel]))
+}
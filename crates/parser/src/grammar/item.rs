@@ -15,16 +15,17 @@ pub(super) fn item(p: &mut Parser) {
         return;
     }
 
-    // leading spaces (indent)
-    let indent_level;
-    if p.at(T![' ']) {
-        indent_level = unsafe { p.current_len().unwrap_unchecked() };
-        p.bump(T![' ']);
-    } else {
-        indent_level = 0;
-    }
-    if p.at(T!['\t']) {
-        p.error("tab indent is not allowed, skipping this line");
+    // leading spaces (indent), and tabs if they may indent
+    let indent_level = eat_indent(p);
+    if let Some(tabs) = p.nth_len_if(0, T!['\t']) {
+        let range = TextRange::at(p.offset(), tabs.into());
+        p.error_with_fix(
+            "tab indent is not allowed, skipping this line",
+            fix(
+                "replace the tab indent with spaces",
+                Indel::replace(range, "  ".repeat(tabs as usize)),
+            ),
+        );
         let m = p.start();
         eat_to_end_of_line(p);
         m.complete(p, ERROR);
@@ -56,7 +57,7 @@ fn directive(p: &mut Parser) {
     m.complete(p, DIRECTIVE);
 }
 
-fn comment(p: &mut Parser) {
+pub(super) fn comment(p: &mut Parser) {
     assert!(p.at(T![#]));
 
     let m = p.start();
@@ -95,9 +96,16 @@ fn entry_or_prefix_block(p: &mut Parser, indent_level: u32) {
     assert!(p.at(T![' ']) || p.at(T!['\t']));
 
     // separator
+    let separator_start = p.offset();
     p.eat(T![' ']);
     if p.at(T!['\t']) {
-        p.error("tab separating is not allowed");
+        let rest = p.rest_of_line();
+        let blanks = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        let range = TextRange::new(separator_start, p.offset() + TextSize::of(&rest[..blanks]));
+        p.error_with_fix(
+            "tab separating is not allowed",
+            fix("replace the tab with a space", Indel::replace(range, " ")),
+        );
         let m = p.start();
         p.bump(T!['\t']);
         m.complete(p, ERROR);
@@ -110,28 +118,53 @@ fn entry_or_prefix_block(p: &mut Parser, indent_level: u32) {
     }
 
     if p.at(T!['{']) && is_rest_of_line_blank(p, 1) {
-        prefix_block(p, m);
+        prefix_block(p, m, indent_level);
     } else {
         entry(p, m, indent_level);
     }
 }
 
-fn prefix_block(p: &mut Parser, m: Marker) {
+fn prefix_block(p: &mut Parser, m: Marker, indent_level: u32) {
     assert!(p.at(T!['{']) && is_rest_of_line_blank(p, 1));
 
+    // The items inside can be passed on before the block ends.
+    let m = m.resolve(p, PREFIX_BLOCK);
+
+    let max_depth = p.limits().max_depth;
+    let too_deep = p.depth() >= max_depth;
+    if too_deep {
+        p.error(format!(
+            "prefix blocks are nested deeper than the limit of {max_depth}, skipping this block"
+        ));
+    }
+
     p.bump(T!['{']);
     p.eat(T![' ']);
 
+    if too_deep {
+        skip_block_items(p);
+    }
+    p.set_depth(p.depth() + 1);
+
     loop {
+        if p.is_stopped() {
+            break;
+        }
+
         while p.eat(T!['\n']) {}
 
         if p.at_eof() {
-            p.error("missing closing '}' for prefix block");
+            let newline = if p.at_line_start() { "" } else { "\n" };
+            let close = format!("{newline}{}}}\n", " ".repeat(indent_level as usize));
+            p.error_with_fix(
+                "missing closing '}' for prefix block",
+                fix("add the missing '}'", Indel::insert(p.offset(), close)),
+            );
             break;
         }
 
         if is_close_brace_line(p) {
-            p.eat(T![' ']);
+            eat_indent(p);
             p.bump(T!['}']);
             p.eat(T![' ']);
             p.eat(T!['\n']);
@@ -141,14 +174,50 @@ fn prefix_block(p: &mut Parser, m: Marker) {
         item(p);
     }
 
-    m.complete(p, PREFIX_BLOCK);
+    p.set_depth(p.depth() - 1);
+    m.complete(p);
+}
+
+/// Wraps the lines of a block up to its closing line in an `ERROR` node. The blocks opened
+/// inside are only counted, so nesting there costs no stack.
+fn skip_block_items(p: &mut Parser) {
+    let mut m = None;
+    let mut depth = 0u32;
+    loop {
+        while p.eat(T!['\n']) {}
+        if p.at_eof() {
+            break;
+        }
+        if is_close_brace_line(p) {
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+        } else if is_open_brace_line(p) {
+            depth += 1;
+        }
+        m.get_or_insert_with(|| p.start());
+        eat_to_end_of_line(p);
+    }
+    if let Some(m) = m {
+        m.complete(p, ERROR);
+    }
 }
 
-fn is_close_brace_line(p: &Parser) -> bool {
+/// Whether the current line ends like the key line of a prefix block: with a `{` after a space.
+fn is_open_brace_line(p: &mut Parser) -> bool {
     let mut n = 0;
-    if p.nth_at(n, T![' ']) {
+    while !(p.nth_at(n, T!['\n']) || p.nth_at_eof(n)) {
         n += 1;
     }
+    if n > 0 && p.nth_at(n - 1, T![' ']) {
+        n -= 1;
+    }
+    n >= 2 && p.nth_at(n - 1, T!['{']) && p.nth_at(n - 2, T![' '])
+}
+
+fn is_close_brace_line(p: &mut Parser) -> bool {
+    let (_, mut n) = indent_at(p, 0);
     if !p.nth_at(n, T!['}']) {
         return false;
     }
@@ -164,7 +233,11 @@ fn entry(p: &mut Parser, m: Marker, indent_level: u32) {
         value::value(p, indent_level);
     }
 
-    // trailing whitespaces
+    // trailing whitespaces, or an inline comment
+    if is_inline_comment_at(p, 0) {
+        p.bump(T![' ']);
+        comment(p);
+    }
     p.eat(T![' ']);
     p.eat(T!['\n']);
 
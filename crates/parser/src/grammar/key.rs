@@ -21,8 +21,8 @@ pub(super) const KEY_LAST: TokenSet = TokenSet::new([T![' '], T!['\n'], T!['\t']
 pub(super) fn key(p: &mut Parser) {
     assert!(p.at_ts(KEY_FIRST));
 
-    match unsafe { p.current().unwrap_unchecked() } {
-        quote @ (T!['"'] | T!['\'']) => quoted_key(p, quote),
+    match p.current() {
+        Some(quote @ (T!['"'] | T!['\''])) => quoted_key(p, quote),
         _ => word_key(p),
     }
 }
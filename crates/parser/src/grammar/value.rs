@@ -19,40 +19,65 @@ pub(super) const VALUE_FIRST: TokenSet = TokenSet::new([
 pub(super) fn value(p: &mut Parser, indent_level: u32) {
     assert!(p.at_ts(VALUE_FIRST));
 
-    match unsafe { p.current().unwrap_unchecked() } {
-        quote @ (T!['"'] | T!['\'']) => {
+    match p.current() {
+        Some(quote @ (T!['"'] | T!['\''])) => {
+            let start = p.offset();
             quoted_value(p, quote);
-            if !(p.at(T!['\n']) || p.at_eof()) {
-                p.error("unexpected token after quoted value");
+            if !(p.at(T!['\n']) || p.at_eof() || is_inline_comment_at(p, 0)) {
+                let rest = p.rest_of_line();
+                let fix = if rest.trim_end().is_empty() {
+                    let range = TextRange::at(p.offset(), TextSize::of(rest));
+                    fix("remove the trailing whitespace", Indel::delete(range))
+                } else {
+                    let end = p.offset() + TextSize::of(rest.trim_end());
+                    let value = p.text(TextRange::new(start, end));
+                    let quoted = value.replace('\\', "\\\\").replace('"', "\\\"");
+                    // Trailing spaces after a quoted value are an error too, so they go as well.
+                    let range = TextRange::new(start, p.offset() + TextSize::of(rest));
+                    fix("quote the whole value", Indel::replace(range, format!("\"{quoted}\"")))
+                };
+                p.error_with_fix("unexpected token after quoted value", fix);
                 let m = p.start();
                 eat_to_end_of_line(p);
                 m.complete(p, ERROR);
             }
         }
-        T![|] | T![>] => {
+        Some(T![|] | T![>]) => {
             let mut shift = 1;
-            if p.nth_at(shift, T![+]) || p.nth_at(shift, T![-]) {
+            if indentation_indicator_before_chomp(p, shift).is_some() {
                 shift += 1;
+            } else {
+                if p.nth_at(shift, T![+]) || p.nth_at(shift, T![-]) {
+                    shift += 1;
+                }
+                if indentation_indicator(p, shift).is_some() {
+                    shift += 1;
+                }
             }
-            if is_rest_of_line_blank(p, shift) {
+            if is_end_of_value(p, shift) {
                 block_string(p, indent_level);
             } else {
-                line_string(p)
+                value_line_string(p, indent_level)
             }
         }
-        T![true] | T![false] if is_rest_of_line_blank(p, 1) => {
+        Some(T![true] | T![false]) if is_end_of_value(p, 1) => {
             boolean(p);
         }
-        T![numeral] if is_rest_of_line_blank(p, 1) => {
+        Some(T![numeral]) if is_end_of_value(p, 1) => {
             integer(p);
         }
-        T![+] | T![-] if p.nth_at(1, T![numeral]) && is_rest_of_line_blank(p, 2) => {
+        Some(T![+] | T![-]) if p.nth_at(1, T![numeral]) && is_end_of_value(p, 2) => {
             integer(p);
         }
-        _ => line_string(p),
+        _ => value_line_string(p, indent_level),
     }
 }
 
+/// Whether a value ends before the `n`th token: at the end of the line or at an inline comment.
+fn is_end_of_value(p: &mut Parser, n: usize) -> bool {
+    is_rest_of_line_blank(p, n) || is_inline_comment_at(p, n)
+}
+
 fn boolean(p: &mut Parser) {
     assert!(p.at(T![true]) || p.at(T![false]));
 
@@ -88,6 +113,64 @@ pub(super) fn line_string(p: &mut Parser) {
     m.complete(p, LINE_STRING);
 }
 
+/// Parses a line string value, which stops before an inline comment and goes on at the next line
+/// after a trailing `\` if the options allow them. It does not go on at a line that closes a
+/// prefix block or is indented less than the entry at `indent_level`.
+fn value_line_string(p: &mut Parser, indent_level: u32) {
+    let m = p.start();
+
+    loop {
+        // A comment needs some text before it; `key # note` is the string "# note".
+        let mut count = 0;
+        while !(p.nth_at(count, T!['\n']) || p.nth_at_eof(count)) {
+            if count > 0 && is_inline_comment_at(p, count) {
+                break;
+            }
+            count += 1;
+        }
+
+        if p.options().line_continuation
+            && count > 0
+            && p.nth_at(count, T!['\n'])
+            && !p.nth_at_eof(count + 1)
+            && continues_at(p, count + 1, indent_level)
+            && let Some(word) = p.nth_text_if(count - 1, T![word])
+            && word.ends_with('\\')
+        {
+            // The text before the `\`, including the spaces, is part of the string.
+            if word.len() > 1 {
+                p.split_nth(count - 1, word.len() as u32 - 1);
+                count += 1;
+            }
+            if count > 1 {
+                p.bump_remap(T![string], count - 1);
+            }
+            p.bump_remap(T!['\\'], 1);
+            p.bump(T!['\n']);
+            while p.at(T![' ']) || p.at(T!['\t']) {
+                p.bump_any();
+            }
+            continue;
+        }
+
+        // Trim trailing space
+        if count > 0 && p.nth_at(count - 1, T![' ']) {
+            count -= 1;
+        }
+        p.bump_remap(T![string], count);
+        break;
+    }
+
+    m.complete(p, LINE_STRING);
+}
+
+/// Whether the line starting at the `n`th token can continue a line string: it is blank, or is
+/// indented at least to `indent_level` and does not start with `}`.
+fn continues_at(p: &mut Parser, n: usize, indent_level: u32) -> bool {
+    let (width, count) = indent_at(p, n);
+    is_rest_of_line_blank(p, n + count) || (width >= indent_level && !p.nth_at(n + count, T!['}']))
+}
+
 fn quoted_value(p: &mut Parser, quote: SyntaxKind) {
     assert!((quote == T!['"'] || quote == T!['\'']) && p.at(quote));
 
@@ -98,7 +181,11 @@ fn quoted_value(p: &mut Parser, quote: SyntaxKind) {
     p.bump(T![string]);
 
     if !p.eat(quote) {
-        p.error("missing closing quote");
+        let closing = if quote == T!['"'] { "\"" } else { "'" };
+        p.error_with_fix(
+            "missing closing quote",
+            fix("add the closing quote", Indel::insert(p.offset(), closing)),
+        );
     }
 
     m.complete(p, QUOTED_STRING);
@@ -109,19 +196,21 @@ fn block_string(p: &mut Parser, indent_level: u32) {
 
     let m = p.start();
 
-    block_string_header(p);
+    let indicator = block_string_header(p);
 
-    // Scan ahead to find base_indent: the indent of the first line with content.
-    // Whitespace-only lines and empty lines are skipped during this scan.
-    let base_indent = {
+    // Without an indentation indicator, scan ahead to find base_indent: the indent of the first
+    // line with content. Whitespace-only lines and empty lines are skipped during this scan.
+    let base_indent = if let Some(indicator) = indicator {
+        Some(indent_level + indicator)
+    } else {
         let mut offset = 0;
         loop {
             if p.nth_at_eof(offset) {
                 break None;
             }
-            if p.nth_at(offset, T![' ']) {
-                let indent = unsafe { p.nth_len(offset).unwrap_unchecked() };
-                offset += 1;
+            let (indent, count) = indent_at(p, offset);
+            if count > 0 {
+                offset += count;
                 if p.nth_at(offset, T!['\n']) || p.nth_at_eof(offset) {
                     // Whitespace-only line, skip
                     offset += 1;
@@ -151,61 +240,51 @@ fn block_string(p: &mut Parser, indent_level: u32) {
 
     // Parse each line using `base_indent` as the reference.
     //
-    // For a line with `line_indent` spaces:
-    //   line_indent >= base_indent  → content line (strip base_indent spaces)
+    // For a line indented by `line_indent` columns:
+    //   line_indent >= base_indent  → content line (strip base_indent columns)
     //   line_indent <= indent_level → block ends
     //   otherwise (between the two) →
     //     whitespace-only → empty line (allowed)
     //     with content    → error (insufficient indentation)
-    while let Some(current) = p.current() {
-        match current {
-            T!['\n'] => {
-                // Completely empty line (no spaces at all).
-                block_string_empty_line(p);
+    while !p.at_eof() {
+        if p.at(T!['\n']) {
+            // Completely empty line (no spaces at all).
+            block_string_empty_line(p);
+        } else {
+            let (line_indent, count) = indent_at(p, 0);
+            if count == 0 {
+                // Non-space content at column 0.
+                break;
             }
-            T![' '] => {
-                let line_indent = unsafe { p.current_len().unwrap_unchecked() };
-
-                if line_indent >= base_indent {
-                    p.bump_upto(T![' '], base_indent);
-
-                    // After stripping base_indent, check if the rest is still blank.
-                    // e.g. a line of only spaces deeper than base_indent.
-                    let is_blank = if p.at(T![' ']) {
-                        p.nth_at(1, T!['\n']) || p.nth_at_eof(1)
-                    } else {
-                        p.at(T!['\n']) || p.at_eof()
-                    };
-
-                    if is_blank {
-                        p.eat(T![' ']);
-                        block_string_empty_line(p);
-                    } else {
-                        block_string_content_line(p);
-                    }
-                } else if line_indent <= indent_level {
-                    break;
+
+            if line_indent >= base_indent {
+                bump_indent(p, base_indent);
+
+                // After stripping base_indent, check if the rest is still blank.
+                // e.g. a line of only spaces deeper than base_indent.
+                let (_, rest) = indent_at(p, 0);
+                if p.nth_at(rest, T!['\n']) || p.nth_at_eof(rest) {
+                    eat_indent(p);
+                    block_string_empty_line(p);
                 } else {
-                    // indent_level < line_indent < base_indent
-                    if p.nth_at(1, T!['\n']) || p.nth_at_eof(1) {
-                        // Whitespace-only lines in this range are allowed.
-                        p.bump(T![' ']);
-                        block_string_empty_line(p);
-                    } else {
-                        // Content in this range is an error.
-                        p.error("block string line has insufficient indentation");
-                        let m = p.start();
-                        p.bump(T![' ']);
-                        while !(p.at(T!['\n']) || p.at_eof()) {
-                            p.bump_any();
-                        }
-                        m.complete(p, ERROR);
-                    }
+                    block_string_content_line(p);
                 }
-            }
-            _ => {
-                // Non-space content at column 0.
+            } else if line_indent <= indent_level {
                 break;
+            } else if p.nth_at(count, T!['\n']) || p.nth_at_eof(count) {
+                // Whitespace-only lines in this range are allowed.
+                eat_indent(p);
+                block_string_empty_line(p);
+            } else {
+                // Content in this range is an error.
+                let missing = " ".repeat((base_indent - line_indent) as usize);
+                p.error_with_fix(
+                    "block string line has insufficient indentation",
+                    fix("indent the line to the block string", Indel::insert(p.offset(), missing)),
+                );
+                let m = p.start();
+                eat_to_end_of_line(p);
+                m.complete(p, ERROR);
             }
         }
 
@@ -220,7 +299,7 @@ fn block_string(p: &mut Parser, indent_level: u32) {
     m.complete(p, BLOCK_STRING);
 }
 
-fn block_continues_after_newline(p: &Parser, indent_level: u32) -> bool {
+fn block_continues_after_newline(p: &mut Parser, indent_level: u32) -> bool {
     assert!(p.at(T!['\n']));
 
     if p.nth_at_eof(1) {
@@ -229,8 +308,8 @@ fn block_continues_after_newline(p: &Parser, indent_level: u32) -> bool {
     if p.nth_at(1, T!['\n']) {
         return true; // Empty line → block continues
     }
-    if p.nth_at(1, T![' ']) {
-        let indent = unsafe { p.nth_len(1).unwrap_unchecked() };
+    let (indent, count) = indent_at(p, 1);
+    if count > 0 {
         // indent > indent_level covers both valid content and error lines;
         // all are still processed within the block.
         return indent > indent_level;
@@ -239,6 +318,27 @@ fn block_continues_after_newline(p: &Parser, indent_level: u32) -> bool {
     false
 }
 
+/// Consumes the first `width` columns of the indentation of a block string line. A tab that
+/// reaches past them is consumed whole.
+fn bump_indent(p: &mut Parser, width: u32) {
+    let tab_width = p.options().tab_width.map(NonZeroU32::get);
+    let mut column = 0;
+    while column < width {
+        if let Some(len) = p.nth_len_if(0, T![' ']) {
+            let len = len.min(width - column);
+            p.bump_upto(T![' '], len);
+            column += len;
+        } else if let Some(tab_width) = tab_width
+            && p.at(T!['\t'])
+        {
+            p.bump_upto(T!['\t'], 1);
+            column = (column / tab_width + 1) * tab_width;
+        } else {
+            break;
+        }
+    }
+}
+
 fn block_string_empty_line(p: &mut Parser) {
     assert!(p.at(T!['\n']) || p.at_eof());
 
@@ -263,21 +363,58 @@ fn block_string_content_line(p: &mut Parser) {
     m.complete(p, LINE_STRING);
 }
 
-fn block_string_header(p: &mut Parser) {
+/// Returns the number of spaces the `n`th token gives the lines of a block string, beyond the
+/// indent of its entry, if it is an indentation indicator: a single digit from 1 to 9.
+fn indentation_indicator(p: &mut Parser, n: usize) -> Option<u32> {
+    match p.nth_text_if(n, T![numeral])?.as_bytes() {
+        [digit @ b'1'..=b'9'] => Some(u32::from(digit - b'0')),
+        _ => None,
+    }
+}
+
+/// Returns the indentation indicator of the `n`th token if it is a word of the indicator followed
+/// by a chomping indicator, like `2-`, which the lexer does not split.
+fn indentation_indicator_before_chomp(p: &mut Parser, n: usize) -> Option<u32> {
+    match p.nth_text_if(n, T![word])?.as_bytes() {
+        [digit @ b'1'..=b'9', b'+' | b'-'] => Some(u32::from(digit - b'0')),
+        _ => None,
+    }
+}
+
+/// Returns the indentation indicator of the header, if it has one.
+fn block_string_header(p: &mut Parser) -> Option<u32> {
     assert!(p.at(T![|]) || p.at(T![>]));
 
     let m = p.start();
 
     p.bump_any(); // | or >
 
-    if p.at(T![+]) {
-        p.bump(T![+]);
-    } else if p.at(T![-]) {
-        p.bump(T![-]);
-    }
+    let indicator = if let Some(indicator) = indentation_indicator_before_chomp(p, 0) {
+        let keep = p.nth_text_if(0, T![word]).is_some_and(|word| word.ends_with('+'));
+        p.split_nth(0, 1);
+        p.bump_remap(T![numeral], 1);
+        p.bump_remap(if keep { T![+] } else { T![-] }, 1);
+        Some(indicator)
+    } else {
+        if p.at(T![+]) {
+            p.bump(T![+]);
+        } else if p.at(T![-]) {
+            p.bump(T![-]);
+        }
+        let indicator = indentation_indicator(p, 0);
+        if indicator.is_some() {
+            p.bump(T![numeral]);
+        }
+        indicator
+    };
 
+    if is_inline_comment_at(p, 0) {
+        p.bump(T![' ']);
+        item::comment(p);
+    }
     p.eat(T![' ']);
     p.eat(T!['\n']);
 
     m.complete(p, BLOCK_STRING_HEADER);
+    indicator
 }
@@ -1,97 +1,66 @@
-use std::borrow::Cow;
-
-use mical_cli_syntax::{GreenNode, SyntaxError, SyntaxKind, token::TokenStream};
-use rowan::{GreenNodeBuilder, TextRange};
+use mical_cli_syntax::{GreenNode, SyntaxError, token::TokenStream};
+use rowan::GreenNodeBuilder;
+use std::ops::ControlFlow;
 
 mod event;
 mod grammar;
+mod options;
 mod parser;
 mod token_set;
 
-use event::Event;
+pub use event::ParseEvent;
+pub use options::ParseOptions;
 use parser::Parser;
 
 pub fn parse<'s>(token_stream: impl TokenStream<'s>) -> (GreenNode, Vec<SyntaxError>) {
-    let source = token_stream.source();
-    let events = {
-        let mut parser = Parser::new(token_stream);
-        grammar::source_file(&mut parser);
-        parser.finish()
-    };
-    let mut builder = NodeBuilder::new(source);
-    // let mut forward_parents = Vec::new();
-    // for i in 0..events.len() {
-    //     match events.take(i) {
-    //         Event::StartNode { kind, forward_parent } => {
-    //             if forward_parent.is_none() {
-    //                 builder.start_node(kind);
-    //                 continue;
-    //             }
-    //             forward_parents.push(kind);
-    //             let mut idx = i;
-    //             let mut fp = forward_parent;
-    //             while let Some(fpi) = fp {
-    //                 idx += fpi.get() as usize;
-    //                 fp = match events.take(idx) {
-    //                     Event::StartNode { kind, forward_parent } => {
-    //                         forward_parents.push(kind);
-    //                         forward_parent
-    //                     }
-    //                     _ => unreachable!(),
-    //                 };
-    //             }
-    //             for kind in forward_parents.drain(..).rev() {
-    //                 builder.start_node(kind);
-    //             }
-    //         }
-    //         Event::FinishNode => builder.finish_node(),
-    //         Event::Token { kind, len } => builder.token(kind, len),
-    //         Event::Error { message } => builder.error(message),
-    //     }
-    // }
-    // let mut builder = GreenNodeBuilder::new();
-    // let mut errors = Vec::new();
-    // let mut offset = 0;
-    for event in events {
-        match event {
-            Event::StartNode { kind } => builder.start_node(kind),
-            Event::FinishNode => builder.finish_node(),
-            Event::Token { kind, len } => builder.token(kind, len),
-            Event::Error { message } => builder.error(message),
-        }
-    }
-    builder.finish()
+    parse_with_options(token_stream, &ParseOptions::default())
 }
 
-struct NodeBuilder<'s> {
-    source: &'s str,
-    builder: GreenNodeBuilder<'s>,
-    errors: Vec<SyntaxError>,
-    offset: u32,
+/// Same as [`parse`], accepting the extensions `options` turn on and reporting a source over
+/// their limits instead of parsing all of it.
+pub fn parse_with_options<'s>(
+    token_stream: impl TokenStream<'s>,
+    options: &ParseOptions,
+) -> (GreenNode, Vec<SyntaxError>) {
+    let mut builder = NodeBuilder::new();
+    parse_with(token_stream, options, |event| {
+        builder.event(event);
+        ControlFlow::Continue(())
+    });
+    builder.finish()
 }
 
-impl<'s> NodeBuilder<'s> {
-    fn new(source: &'s str) -> Self {
-        NodeBuilder { source, builder: GreenNodeBuilder::new(), errors: Vec::new(), offset: 0 }
-    }
-
-    fn start_node(&mut self, kind: SyntaxKind) {
-        self.builder.start_node(kind.into());
-    }
+/// Parses `token_stream` without building a tree. `sink` gets the events of a preorder walk over
+/// the tree [`parse_with_options`] builds, each as soon as the kinds of the nodes around it are
+/// known: at the latest once the item it belongs to is parsed. Parsing stops soon after `sink`
+/// breaks.
+pub fn parse_with<'s>(
+    mut token_stream: impl TokenStream<'s>,
+    options: &ParseOptions,
+    mut sink: impl FnMut(ParseEvent<'s>) -> ControlFlow<()>,
+) {
+    let source = token_stream.source();
+    let mut parser = Parser::new(source, &mut token_stream, options, &mut sink);
+    grammar::source_file(&mut parser);
+}
 
-    fn finish_node(&mut self) {
-        self.builder.finish_node();
-    }
+struct NodeBuilder {
+    builder: GreenNodeBuilder<'static>,
+    errors: Vec<SyntaxError>,
+}
 
-    fn token(&mut self, kind: SyntaxKind, len: u32) {
-        let text = &self.source[(self.offset as usize)..(self.offset + len) as usize];
-        self.builder.token(kind.into(), text);
-        self.offset += len;
+impl NodeBuilder {
+    fn new() -> Self {
+        NodeBuilder { builder: GreenNodeBuilder::new(), errors: Vec::new() }
     }
 
-    fn error(&mut self, message: impl Into<Cow<'static, str>>) {
-        let range = TextRange::empty(self.offset.into());
-        self.errors.push(SyntaxError::new(message, range));
+    fn event(&mut self, event: ParseEvent<'_>) {
+        match event {
+            ParseEvent::StartNode(kind) => self.builder.start_node(kind.into()),
+            ParseEvent::FinishNode => self.builder.finish_node(),
+            ParseEvent::Token { kind, text, .. } => self.builder.token(kind.into(), text),
+            ParseEvent::Error(error) => self.errors.push(error),
+        }
     }
 
     fn finish(self) -> (GreenNode, Vec<SyntaxError>) {
@@ -0,0 +1,28 @@
+use mical_cli_syntax::Limits;
+use std::num::NonZeroU32;
+
+/// How to parse a source: its [`Limits`], and which extensions of the language to accept. Every
+/// extension is off by default, and each one leaves its own tokens in the tree, so the source
+/// can be written back as it was.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub limits: Limits,
+    /// Whether a `#` after a value and a space starts a comment that runs to the end of the line,
+    /// as in `key value # note`. The `COMMENT` node goes in the entry, after the value. A line
+    /// string that starts with `#` is still a value.
+    pub inline_comments: bool,
+    /// Width of a tab in indentation, if tabs may indent lines at all. A tab moves to the next
+    /// multiple of the width, and the `TAB` tokens stay where the indentation is.
+    pub tab_width: Option<NonZeroU32>,
+    /// Whether a line string that ends with `\` goes on at the next line, with the indentation
+    /// of that line left out. The line string then holds a `STRING` token for each line, with
+    /// the `\`, the newline and the indentation between them.
+    pub line_continuation: bool,
+}
+
+impl ParseOptions {
+    /// The default language with `limits`.
+    pub fn with_limits(limits: Limits) -> Self {
+        ParseOptions { limits, ..Default::default() }
+    }
+}
@@ -1,29 +1,53 @@
 use crate::{
-    event::{Event, EventContainer},
+    ParseOptions,
+    event::{Emitter, Event, EventContainer, ParseEvent},
     token_set::TokenSet,
 };
 use mical_cli_syntax::{
-    SyntaxKind, T,
-    token::{Quote, TokenKind, TokenStream},
+    Fix, Limits, SyntaxKind, T, TextRange, TextSize,
+    token::{Quote, Token, TokenKind},
 };
-use std::{borrow::Cow, mem};
+use std::{borrow::Cow, collections::VecDeque, mem, ops::ControlFlow};
 
-pub(crate) struct Parser {
-    kinds: Vec<SyntaxKind>,
-    lens: Vec<u32>,
-    pos: usize,
+pub(crate) struct Parser<'s, 'k> {
+    source: &'s str,
+    tokens: &'k mut dyn Iterator<Item = Token>,
+    /// Kind and length of the current token and the ones after it, lexed as far as the parser
+    /// has looked ahead.
+    lookahead: VecDeque<(SyntaxKind, u32)>,
+    /// Offset in `source` of the next token to be consumed.
+    offset: u32,
     events: EventContainer,
+    emitter: Emitter<'s, 'k>,
+    options: ParseOptions,
+    /// Number of prefix blocks the current token is in.
+    depth: u32,
 }
 
-impl Parser {
-    pub(crate) fn new<'s>(token_stream: impl TokenStream<'s>) -> Self {
-        let mut kinds = Vec::new();
-        let mut lens = Vec::new();
-        let mut push = |kind: SyntaxKind, len: u32| {
-            kinds.push(kind);
-            lens.push(len);
-        };
-        for token in token_stream {
+impl<'s, 'k> Parser<'s, 'k> {
+    pub(crate) fn new(
+        source: &'s str,
+        tokens: &'k mut dyn Iterator<Item = Token>,
+        options: &ParseOptions,
+        sink: &'k mut dyn FnMut(ParseEvent<'s>) -> ControlFlow<()>,
+    ) -> Self {
+        Parser {
+            source,
+            tokens,
+            lookahead: VecDeque::new(),
+            offset: 0,
+            events: EventContainer::new(),
+            emitter: Emitter::new(source, sink),
+            options: *options,
+            depth: 0,
+        }
+    }
+
+    /// Lexes until the `n`th token from the current one is known, or the source ends.
+    fn nth(&mut self, n: usize) -> Option<(SyntaxKind, u32)> {
+        while self.lookahead.len() <= n {
+            let Some(token) = self.tokens.next() else { break };
+            let mut push = |kind: SyntaxKind, len: u32| self.lookahead.push_back((kind, len));
             match token.kind {
                 TokenKind::Word => push(T![word], token.len),
                 TokenKind::True => push(T![true], token.len),
@@ -60,53 +84,125 @@ impl Parser {
                 }
             };
         }
-        Parser { kinds, lens, pos: 0, events: EventContainer::new() }
+        self.lookahead.get(n).copied()
+    }
+
+    /// Records `event`, passing on every event whose enclosing nodes all have their kind.
+    fn push(&mut self, event: Event) {
+        self.events.push(event);
+        let emitter = &mut self.emitter;
+        self.events.flush(|event| emitter.emit(event));
+    }
+
+    /// Whether the consumer of the events has asked to stop; the grammar then stops at the next
+    /// item.
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.emitter.is_stopped()
+    }
+
+    pub(crate) fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    pub(crate) fn limits(&self) -> &Limits {
+        &self.options.limits
+    }
+
+    pub(crate) fn source_len(&self) -> usize {
+        self.source.len()
+    }
+
+    /// Number of prefix blocks the current token is in.
+    pub(crate) fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub(crate) fn set_depth(&mut self, depth: u32) {
+        self.depth = depth;
+    }
+
+    /// Offset of the current token, where an error reported now is placed.
+    pub(crate) fn offset(&self) -> TextSize {
+        self.offset.into()
     }
 
-    pub(crate) fn current(&self) -> Option<SyntaxKind> {
-        self.kinds.get(self.pos).copied()
+    pub(crate) fn text(&self, range: TextRange) -> &'s str {
+        &self.source[range]
     }
 
-    pub(crate) fn current_len(&self) -> Option<u32> {
-        self.lens.get(self.pos).copied()
+    /// Whether the current token starts a line.
+    pub(crate) fn at_line_start(&self) -> bool {
+        self.offset == 0 || self.source.as_bytes()[self.offset as usize - 1] == b'\n'
     }
 
-    pub(crate) fn at(&self, kind: SyntaxKind) -> bool {
-        self.kinds.get(self.pos) == Some(&kind)
+    /// The source text from the current token to the end of its line, excluding the newline.
+    pub(crate) fn rest_of_line(&self) -> &'s str {
+        let rest = &self.source[self.offset as usize..];
+        rest.find('\n').map_or(rest, |i| &rest[..i])
     }
 
-    pub(crate) fn at_ts(&self, kinds: TokenSet) -> bool {
+    pub(crate) fn current(&mut self) -> Option<SyntaxKind> {
+        self.nth(0).map(|(kind, _)| kind)
+    }
+
+    pub(crate) fn current_len(&mut self) -> Option<u32> {
+        self.nth(0).map(|(_, len)| len)
+    }
+
+    pub(crate) fn at(&mut self, kind: SyntaxKind) -> bool {
+        self.current() == Some(kind)
+    }
+
+    pub(crate) fn at_ts(&mut self, kinds: TokenSet) -> bool {
         let Some(current) = self.current() else {
             return false;
         };
         kinds.contains(current)
     }
 
-    pub(crate) fn at_eof(&self) -> bool {
+    pub(crate) fn at_eof(&mut self) -> bool {
         self.current().is_none()
     }
 
-    pub(crate) fn nth_at(&self, n: usize, kind: SyntaxKind) -> bool {
-        self.kinds.get(self.pos + n) == Some(&kind)
+    pub(crate) fn nth_at(&mut self, n: usize, kind: SyntaxKind) -> bool {
+        self.nth(n).map(|(kind, _)| kind) == Some(kind)
     }
 
-    pub(crate) fn nth_at_ts(&self, n: usize, kinds: TokenSet) -> bool {
-        let Some(current) = self.kinds.get(self.pos + n).copied() else {
+    pub(crate) fn nth_at_ts(&mut self, n: usize, kinds: TokenSet) -> bool {
+        let Some((current, _)) = self.nth(n) else {
             return false;
         };
         kinds.contains(current)
     }
 
-    pub(crate) fn nth_at_eof(&self, n: usize) -> bool {
-        self.kinds.get(self.pos + n).is_none()
+    pub(crate) fn nth_at_eof(&mut self, n: usize) -> bool {
+        self.nth(n).is_none()
     }
 
-    pub(crate) fn nth_len(&self, n: usize) -> Option<u32> {
-        self.lens.get(self.pos + n).copied()
+    /// Length of the `n`th token, if it is a `kind` token.
+    pub(crate) fn nth_len_if(&mut self, n: usize, kind: SyntaxKind) -> Option<u32> {
+        self.nth(n).and_then(|(k, len)| (k == kind).then_some(len))
+    }
+
+    /// Text of the `n`th token, if it is a `kind` token.
+    pub(crate) fn nth_text_if(&mut self, n: usize, kind: SyntaxKind) -> Option<&'s str> {
+        let len = self.nth_len_if(n, kind)?;
+        let start = self.offset + self.lookahead.range(..n).map(|&(_, len)| len).sum::<u32>();
+        Some(&self.source[start as usize..(start + len) as usize])
+    }
+
+    /// Splits the `n`th token in two tokens of its kind, the first `len` bytes long.
+    pub(crate) fn split_nth(&mut self, n: usize, len: u32) {
+        let Some((kind, token_len)) = self.nth(n) else {
+            panic!("Unexpected EOF");
+        };
+        assert!(0 < len && len < token_len);
+        self.lookahead[n].1 = len;
+        self.lookahead.insert(n + 1, (kind, token_len - len));
     }
 
     pub(crate) fn start(&mut self) -> Marker {
-        let pos = self.events.len() as u32;
+        let pos = self.events.len();
         self.events.push_tombstone();
         Marker { pos }
     }
@@ -118,8 +214,9 @@ impl Parser {
         let Some(len) = self.current_len() else {
             unreachable!("Unexpected EOF");
         };
-        self.events.push(Event::Token { kind, len });
-        self.pos += 1;
+        self.lookahead.pop_front();
+        self.offset += len;
+        self.push(Event::Token { kind, len });
         true
     }
 
@@ -133,12 +230,13 @@ impl Parser {
         if current_len < len {
             return false;
         }
-        self.events.push(Event::Token { kind, len });
         if current_len > len {
-            self.lens[self.pos] -= len;
+            self.lookahead[0].1 -= len;
         } else {
-            self.pos += 1;
+            self.lookahead.pop_front();
         }
+        self.offset += len;
+        self.push(Event::Token { kind, len });
         true
     }
 
@@ -157,41 +255,57 @@ impl Parser {
         let Some(len) = self.current_len() else {
             panic!("Unexpected EOF");
         };
-        self.events.push(Event::Token { kind, len });
-        self.pos += 1;
+        self.lookahead.pop_front();
+        self.offset += len;
+        self.push(Event::Token { kind, len });
+    }
+
+    /// Consumes the rest of the source as one `kind` token, without lexing it.
+    pub(crate) fn bump_rest_unlexed(&mut self, kind: SyntaxKind) {
+        let len = self.source.len() as u32 - self.offset;
+        self.lookahead.clear();
+        self.offset += len;
+        self.push(Event::Token { kind, len });
     }
 
     pub(crate) fn bump_remap(&mut self, kind: SyntaxKind, n: usize) {
-        if self.pos + n > self.kinds.len() {
+        if n > 0 && self.nth(n - 1).is_none() {
             panic!("Unexpected EOF");
         }
-        let len = self.lens[self.pos..self.pos + n].iter().sum();
-        self.events.push(Event::Token { kind, len });
-        self.pos += n;
+        let len = self.lookahead.drain(..n).map(|(_, len)| len).sum();
+        self.offset += len;
+        self.push(Event::Token { kind, len });
     }
 
     pub(crate) fn error(&mut self, message: impl Into<Cow<'static, str>>) {
         let message = message.into();
-        self.events.push(Event::Error { message });
+        self.push(Event::Error { message, fix: None });
     }
 
-    pub(crate) fn finish(self) -> EventContainer {
-        self.events
+    pub(crate) fn error_with_fix(&mut self, message: impl Into<Cow<'static, str>>, fix: Fix) {
+        let message = message.into();
+        self.push(Event::Error { message, fix: Some(fix) });
     }
 }
 
 #[must_use]
 pub(crate) struct Marker {
-    pos: u32,
+    pos: usize,
 }
 
 impl Marker {
-    pub(crate) fn complete(self, p: &mut Parser, kind: SyntaxKind) {
+    pub(crate) fn complete(self, p: &mut Parser<'_, '_>, kind: SyntaxKind) {
+        self.resolve(p, kind).complete(p);
+        // CompletedMarker { pos }
+    }
+
+    /// Gives the node its kind before it is complete, so that the events inside it can be passed
+    /// on as they come.
+    pub(crate) fn resolve(self, p: &mut Parser<'_, '_>, kind: SyntaxKind) -> ResolvedMarker {
         let pos = self.pos;
         mem::forget(self);
-        p.events.replace_tombstone(pos as usize, Event::StartNode { kind });
-        p.events.push(Event::FinishNode);
-        // CompletedMarker { pos }
+        p.events.replace_tombstone(pos, Event::StartNode { kind });
+        ResolvedMarker { _private: () }
     }
 }
 
@@ -203,8 +317,28 @@ impl Drop for Marker {
     }
 }
 
+#[must_use]
+pub(crate) struct ResolvedMarker {
+    _private: (),
+}
+
+impl ResolvedMarker {
+    pub(crate) fn complete(self, p: &mut Parser<'_, '_>) {
+        mem::forget(self);
+        p.push(Event::FinishNode);
+    }
+}
+
+impl Drop for ResolvedMarker {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            panic!("Marker must be completed")
+        }
+    }
+}
+
 // pub(crate) struct CompletedMarker {
-//     pos: u32,
+//     pos: usize,
 // }
 //
 // impl CompletedMarker {
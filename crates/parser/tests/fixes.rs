@@ -0,0 +1,67 @@
+use mical_cli_syntax::SyntaxError;
+
+fn parse(source: &str) -> Vec<SyntaxError> {
+    mical_cli_parser::parse(mical_cli_lexer::tokenize(source)).1
+}
+
+/// Applies the fix of the first error in `source` and checks that the result parses cleanly.
+#[track_caller]
+fn check(source: &str, message: &str, expected: &str) {
+    let errors = parse(source);
+    assert!(!errors.is_empty());
+    let fix = errors[0].fix().expect("the error has a fix");
+    assert_eq!(fix.message, message);
+    let mut fixed = source.to_owned();
+    fix.edit.apply(&mut fixed);
+    assert_eq!(fixed, expected);
+    assert_eq!(parse(&fixed), []);
+}
+
+#[test]
+fn tab_indent() {
+    check("a. {\n\tb 1\n}\n", "replace the tab indent with spaces", "a. {\n  b 1\n}\n");
+}
+
+#[test]
+fn tab_separator() {
+    check("a\t1\n", "replace the tab with a space", "a 1\n");
+    check("a \t 1\n", "replace the tab with a space", "a 1\n");
+}
+
+#[test]
+fn trailing_content_after_quoted_value() {
+    check("a \"x\"  \n", "remove the trailing whitespace", "a \"x\"\n");
+    check("a \"x\" and \\y \n", "quote the whole value", "a \"\\\"x\\\" and \\\\y\"\n");
+}
+
+#[test]
+fn missing_closing_quote() {
+    check("a 'x\n", "add the closing quote", "a 'x'\n");
+}
+
+#[test]
+fn missing_closing_brace() {
+    check("a. {\n  b 1\n", "add the missing '}'", "a. {\n  b 1\n}\n");
+    check("a. {\n  b 1", "add the missing '}'", "a. {\n  b 1\n}\n");
+}
+
+#[test]
+fn missing_closing_braces_of_nested_blocks() {
+    let source = "a. {\n  b. {\n    c 1\n";
+    let errors = parse(source);
+    let mut fixed = source.to_owned();
+    // Both insertions land at the end of the file; the inner block comes first.
+    let indels = errors.iter().flat_map(|e| e.fix().unwrap().edit.indels().to_vec()).collect();
+    mical_cli_syntax::TextEdit::new(indels).apply(&mut fixed);
+    assert_eq!(fixed, "a. {\n  b. {\n    c 1\n  }\n}\n");
+    assert_eq!(parse(&fixed), []);
+}
+
+#[test]
+fn block_string_indentation() {
+    check(
+        "a |\n    x\n  y\nb 1\n",
+        "indent the line to the block string",
+        "a |\n    x\n    y\nb 1\n",
+    );
+}
@@ -0,0 +1,68 @@
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{Limits, SyntaxError, SyntaxKind, SyntaxNode};
+
+fn parse(source: &str, limits: &Limits) -> (SyntaxNode, Vec<SyntaxError>) {
+    let options = ParseOptions::with_limits(*limits);
+    let (green, errors) =
+        mical_cli_parser::parse_with_options(mical_cli_lexer::tokenize(source), &options);
+    (SyntaxNode::new_root(green), errors)
+}
+
+fn nested(depth: usize) -> String {
+    let mut source = String::new();
+    for i in 0..depth {
+        source.push_str(&"  ".repeat(i));
+        source.push_str("a. {\n");
+    }
+    source.push_str(&"  ".repeat(depth));
+    source.push_str("b 1\n");
+    for i in (0..depth).rev() {
+        source.push_str(&"  ".repeat(i));
+        source.push_str("}\n");
+    }
+    source
+}
+
+#[test]
+fn blocks_within_the_depth_limit_parse_cleanly() {
+    let limits = Limits { max_depth: 4, ..Limits::default() };
+    let source = nested(4);
+    let (tree, errors) = parse(&source, &limits);
+    assert_eq!(errors, []);
+    assert_eq!(tree.to_string(), source);
+}
+
+#[test]
+fn blocks_past_the_depth_limit_are_skipped() {
+    let limits = Limits { max_depth: 4, ..Limits::default() };
+    let source = format!("{}c 2\n", nested(6));
+    let (tree, errors) = parse(&source, &limits);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        "prefix blocks are nested deeper than the limit of 4, skipping this block"
+    );
+    // The tree keeps every byte, and the entry after the skipped blocks is parsed again.
+    assert_eq!(tree.to_string(), source);
+    assert!(tree.last_child().is_some_and(|entry| entry.to_string() == "c 2\n"));
+}
+
+#[test]
+fn deep_nesting_does_not_overflow_the_stack() {
+    let source = "a. {\n".repeat(100_000);
+    let (tree, errors) = parse(&source, &Limits::default());
+    assert!(!errors.is_empty());
+    assert_eq!(tree.to_string(), source);
+}
+
+#[test]
+fn source_over_the_length_limit_is_not_parsed() {
+    let limits = Limits { max_source_len: 8, ..Limits::default() };
+    let (tree, errors) = parse("a 1\nb 2\nc 3\n", &limits);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "the source is 12 bytes long, over the limit of 8");
+    assert_eq!(tree.to_string(), "a 1\nb 2\nc 3\n");
+    let error = tree.first_child().unwrap();
+    assert_eq!(error.kind(), SyntaxKind::ERROR);
+    assert_eq!(error.text_range(), tree.text_range());
+}
@@ -0,0 +1,175 @@
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{SyntaxElement, SyntaxError, SyntaxKind, SyntaxNode};
+use std::num::NonZeroU32;
+
+fn parse(source: &str, options: &ParseOptions) -> (SyntaxNode, Vec<SyntaxError>) {
+    let (green, errors) =
+        mical_cli_parser::parse_with_options(mical_cli_lexer::tokenize(source), options);
+    let tree = SyntaxNode::new_root(green);
+    // Every extension keeps the source as it is.
+    assert_eq!(tree.to_string(), source);
+    (tree, errors)
+}
+
+/// The kinds and texts of the children of `node`.
+fn children(node: &SyntaxNode) -> Vec<(SyntaxKind, String)> {
+    node.children_with_tokens()
+        .map(|element| match element {
+            SyntaxElement::Node(node) => (node.kind(), node.to_string()),
+            SyntaxElement::Token(token) => (token.kind(), token.text().to_owned()),
+        })
+        .collect()
+}
+
+fn find(tree: &SyntaxNode, kind: SyntaxKind) -> SyntaxNode {
+    tree.descendants().find(|node| node.kind() == kind).unwrap()
+}
+
+fn owned(children: &[(SyntaxKind, &str)]) -> Vec<(SyntaxKind, String)> {
+    children.iter().map(|&(kind, text)| (kind, text.to_owned())).collect()
+}
+
+fn inline_comments() -> ParseOptions {
+    ParseOptions { inline_comments: true, ..Default::default() }
+}
+
+#[test]
+fn inline_comments_go_in_the_entry() {
+    let (tree, errors) = parse("a some value  # note\n", &inline_comments());
+    assert_eq!(errors, []);
+    let entry = find(&tree, SyntaxKind::ENTRY);
+    assert_eq!(
+        children(&entry),
+        owned(&[
+            (SyntaxKind::WORD_KEY, "a"),
+            (SyntaxKind::SPACE, " "),
+            (SyntaxKind::LINE_STRING, "some value"),
+            (SyntaxKind::SPACE, "  "),
+            (SyntaxKind::COMMENT, "# note"),
+            (SyntaxKind::NEWLINE, "\n"),
+        ])
+    );
+}
+
+#[test]
+fn inline_comments_after_every_kind_of_value() {
+    let source = "a 1 # n\nb -2 # n\nc true # n\nd \"q\" # n\ne | # n\n  text\n";
+    let (tree, errors) = parse(source, &inline_comments());
+    assert_eq!(errors, []);
+    let values = tree
+        .descendants()
+        .filter(|node| node.kind() == SyntaxKind::ENTRY)
+        .map(|entry| entry.children().nth(1).unwrap().kind())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [
+            SyntaxKind::INTEGER,
+            SyntaxKind::INTEGER,
+            SyntaxKind::BOOLEAN,
+            SyntaxKind::QUOTED_STRING,
+            SyntaxKind::BLOCK_STRING,
+        ]
+    );
+    let header = find(&tree, SyntaxKind::BLOCK_STRING_HEADER);
+    assert_eq!(header.to_string(), "| # n\n");
+    assert!(header.children().any(|node| node.kind() == SyntaxKind::COMMENT));
+}
+
+#[test]
+fn sharp_without_text_before_it_is_a_value() {
+    let (tree, errors) = parse("a # not a comment\nb x#y\n", &inline_comments());
+    assert_eq!(errors, []);
+    assert!(tree.descendants().all(|node| node.kind() != SyntaxKind::COMMENT));
+}
+
+#[test]
+fn inline_comments_are_values_by_default() {
+    let (tree, errors) = parse("a 1 # note\n", &ParseOptions::default());
+    assert_eq!(errors, []);
+    assert_eq!(find(&tree, SyntaxKind::LINE_STRING).to_string(), "1 # note");
+}
+
+#[test]
+fn tabs_indent_with_a_width() {
+    let options = ParseOptions { tab_width: NonZeroU32::new(4), ..Default::default() };
+    let source = "a. {\n\tb 1\n\tc |\n\t\tline\n\t\t  more\n\t}\n";
+    let (tree, errors) = parse(source, &options);
+    assert_eq!(errors, []);
+    let lines = find(&tree, SyntaxKind::BLOCK_STRING)
+        .children()
+        .filter(|node| node.kind() == SyntaxKind::LINE_STRING)
+        .map(|node| node.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, ["line", "  more"]);
+
+    let (_, errors) = parse(source, &ParseOptions::default());
+    assert_eq!(errors[0].message(), "tab indent is not allowed, skipping this line");
+}
+
+#[test]
+fn tabs_move_to_the_next_tab_stop() {
+    // Two spaces and a tab reach column 4, as deep as the tab before the text.
+    let options = ParseOptions { tab_width: NonZeroU32::new(4), ..Default::default() };
+    let (tree, errors) = parse("a |\n\tone\n  \ttwo\n", &options);
+    assert_eq!(errors, []);
+    let lines = find(&tree, SyntaxKind::BLOCK_STRING)
+        .children()
+        .filter(|node| node.kind() == SyntaxKind::LINE_STRING)
+        .map(|node| node.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, ["one", "two"]);
+}
+
+#[test]
+fn line_continuation_keeps_the_joints() {
+    let options = ParseOptions { line_continuation: true, ..Default::default() };
+    let (tree, errors) = parse("a first \\\n  second\\\n\tthird\nb 1\n", &options);
+    assert_eq!(errors, []);
+    assert_eq!(
+        children(&find(&tree, SyntaxKind::LINE_STRING)),
+        owned(&[
+            (SyntaxKind::STRING, "first "),
+            (SyntaxKind::BACKSLASH, "\\"),
+            (SyntaxKind::NEWLINE, "\n"),
+            (SyntaxKind::SPACE, "  "),
+            (SyntaxKind::STRING, "second"),
+            (SyntaxKind::BACKSLASH, "\\"),
+            (SyntaxKind::NEWLINE, "\n"),
+            (SyntaxKind::TAB, "\t"),
+            (SyntaxKind::STRING, "third"),
+        ])
+    );
+    assert_eq!(tree.children().filter(|node| node.kind() == SyntaxKind::ENTRY).count(), 2);
+}
+
+#[test]
+fn backslash_on_the_last_line_is_text() {
+    let options = ParseOptions { line_continuation: true, ..Default::default() };
+    let (tree, errors) = parse("a x\\", &options);
+    assert_eq!(errors, []);
+    assert_eq!(
+        children(&find(&tree, SyntaxKind::LINE_STRING)),
+        owned(&[(SyntaxKind::STRING, "x\\")])
+    );
+}
+
+#[test]
+fn line_continuation_stops_at_a_closing_brace_or_a_dedent() {
+    let options = ParseOptions { line_continuation: true, ..Default::default() };
+    for source in ["a. {\n  x foo \\\n}\nb 1\n", "a. {\n    x foo \\\n  }\nb 1\n"] {
+        let (tree, errors) = parse(source, &options);
+        assert_eq!(errors, []);
+        assert_eq!(
+            children(&find(&tree, SyntaxKind::LINE_STRING)),
+            owned(&[(SyntaxKind::STRING, "foo \\")])
+        );
+        let top = tree.children().map(|node| node.kind()).collect::<Vec<_>>();
+        assert_eq!(top, [SyntaxKind::PREFIX_BLOCK, SyntaxKind::ENTRY]);
+    }
+
+    let (tree, errors) = parse("a. {\n    x foo \\\n  y 1\n}\n", &options);
+    assert_eq!(errors, []);
+    let entries = tree.descendants().filter(|node| node.kind() == SyntaxKind::ENTRY).count();
+    assert_eq!(entries, 2);
+}
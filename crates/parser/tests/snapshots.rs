@@ -375,6 +375,108 @@ fn block_string_clip_multi_trailing() {
     utils::assert_snapshot!(snapshot);
 }
 #[test]
+fn block_string_explicit_indent_before_chomp() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-before-chomp/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-before-chomp", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_folded_clip_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-clip-exact/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-clip-exact", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_folded_clip_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-clip-extra/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-clip-extra", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_folded_keep_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-keep-exact/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-keep-exact", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_folded_keep_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-keep-extra/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-keep-extra", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_folded_strip_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-strip-exact/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-strip-exact", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_folded_strip_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-folded-strip-extra/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-folded-strip-extra", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_insufficient() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-insufficient/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-insufficient", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_invalid() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-invalid/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-invalid", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_leading_lines() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-leading-lines/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-leading-lines", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_literal_clip_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-clip-exact/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-clip-exact", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_literal_clip_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-clip-extra/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-clip-extra", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_literal_keep_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-keep-exact/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-keep-exact", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_literal_keep_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-keep-extra/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-keep-extra", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_literal_strip_exact() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-strip-exact/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-strip-exact", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_literal_strip_extra() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-literal-strip-extra/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-literal-strip-extra", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn block_string_explicit_indent_nested() {
+    let source = include_str!("../../../test-suite/block-string-explicit-indent-nested/input.mical");
+    let snapshot = utils::make_snapshot("block-string-explicit-indent-nested", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
 fn block_string_extra_indent() {
     let source = include_str!("../../../test-suite/block-string-extra-indent/input.mical");
     let snapshot = utils::make_snapshot("block-string-extra-indent", source);
@@ -1392,6 +1494,18 @@ fn quoted_string_escape_only() {
     utils::assert_snapshot!(snapshot);
 }
 #[test]
+fn quoted_string_invalid_code_point() {
+    let source = include_str!("../../../test-suite/quoted-string-invalid-code-point/input.mical");
+    let snapshot = utils::make_snapshot("quoted-string-invalid-code-point", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
+fn quoted_string_malformed_code_point() {
+    let source = include_str!("../../../test-suite/quoted-string-malformed-code-point/input.mical");
+    let snapshot = utils::make_snapshot("quoted-string-malformed-code-point", source);
+    utils::assert_snapshot!(snapshot);
+}
+#[test]
 fn quoted_string_single_all_escapes() {
     let source = include_str!("../../../test-suite/quoted-string-single-all-escapes/input.mical");
     let snapshot = utils::make_snapshot("quoted-string-single-all-escapes", source);
@@ -4,9 +4,15 @@ pub use syntax_kind::*;
 mod syntax_error;
 pub use syntax_error::*;
 
+mod limits;
+pub use limits::Limits;
+
 pub mod ast;
 pub mod token;
 
+mod text_edit;
+pub use text_edit::*;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum MicalLanguage {}
 
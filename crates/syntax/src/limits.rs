@@ -0,0 +1,46 @@
+/// Caps on what parsing and evaluating a source may take, for sources from untrusted parties.
+/// Going over one is reported as an error instead of exhausting the stack or memory.
+///
+/// The parser applies `max_source_len` and `max_depth`, the evaluator the rest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Longest source, in bytes; a longer one is not parsed at all. Sources can never be longer
+    /// than `u32::MAX` bytes.
+    pub max_source_len: usize,
+    /// Deepest nesting of prefix blocks. The items of a block nested deeper are skipped.
+    pub max_depth: u32,
+    /// Most entries a config may have; evaluation stops at the first one over.
+    pub max_entries: usize,
+    /// Longest full key, in bytes.
+    pub max_key_len: usize,
+    /// Longest integer literal, in bytes, sign included.
+    pub max_integer_len: usize,
+}
+
+impl Limits {
+    /// Caps suited to files submitted by users: a few megabytes with keys and numbers of a
+    /// sensible size.
+    pub const fn untrusted() -> Self {
+        Limits {
+            max_source_len: 16 << 20,
+            max_depth: 32,
+            max_entries: 100_000,
+            max_key_len: 1024,
+            max_integer_len: 128,
+        }
+    }
+}
+
+impl Default for Limits {
+    /// Only what it takes not to crash: the nesting that can be parsed without risking the
+    /// stack, and the longest source ranges can address.
+    fn default() -> Self {
+        Limits {
+            max_source_len: u32::MAX as usize,
+            max_depth: 256,
+            max_entries: usize::MAX,
+            max_key_len: usize::MAX,
+            max_integer_len: usize::MAX,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::Fix;
 use rowan::TextRange;
 use std::{borrow::Cow, fmt};
 
@@ -5,11 +6,17 @@ use std::{borrow::Cow, fmt};
 pub struct SyntaxError {
     message: Cow<'static, str>,
     range: TextRange,
+    fix: Option<Fix>,
 }
 
 impl SyntaxError {
     pub fn new(message: impl Into<Cow<'static, str>>, range: TextRange) -> Self {
-        Self { message: message.into(), range }
+        Self { message: message.into(), range, fix: None }
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
     }
 
     pub fn message(&self) -> &str {
@@ -19,6 +26,11 @@ impl SyntaxError {
     pub fn range(&self) -> TextRange {
         self.range
     }
+
+    /// A machine-applicable edit that resolves the error, if there is an obvious one.
+    pub fn fix(&self) -> Option<&Fix> {
+        self.fix.as_ref()
+    }
 }
 
 impl fmt::Display for SyntaxError {
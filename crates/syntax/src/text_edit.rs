@@ -0,0 +1,70 @@
+use rowan::{TextRange, TextSize};
+
+/// A single replacement: `delete` is removed and `insert` put in its place.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Indel {
+    pub delete: TextRange,
+    pub insert: String,
+}
+
+impl Indel {
+    pub fn replace(range: TextRange, insert: impl Into<String>) -> Self {
+        Indel { delete: range, insert: insert.into() }
+    }
+
+    pub fn insert(offset: TextSize, insert: impl Into<String>) -> Self {
+        Indel { delete: TextRange::empty(offset), insert: insert.into() }
+    }
+
+    pub fn delete(range: TextRange) -> Self {
+        Indel { delete: range, insert: String::new() }
+    }
+}
+
+/// A set of non-overlapping [`Indel`]s against one text, ordered by offset.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    indels: Vec<Indel>,
+}
+
+impl TextEdit {
+    /// # Panics
+    ///
+    /// Panics if two indels overlap. Insertions at the same offset are kept in the given order.
+    pub fn new(mut indels: Vec<Indel>) -> Self {
+        indels.retain(|indel| !indel.delete.is_empty() || !indel.insert.is_empty());
+        indels.sort_by_key(|indel| (indel.delete.start(), indel.delete.end()));
+        for pair in indels.windows(2) {
+            assert!(pair[0].delete.end() <= pair[1].delete.start(), "overlapping indels");
+        }
+        TextEdit { indels }
+    }
+
+    pub fn indels(&self) -> &[Indel] {
+        &self.indels
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indels.is_empty()
+    }
+
+    pub fn apply(&self, text: &mut String) {
+        for indel in self.indels.iter().rev() {
+            text.replace_range(std::ops::Range::<usize>::from(indel.delete), &indel.insert);
+        }
+    }
+}
+
+/// A labelled [`TextEdit`] that resolves a diagnostic, such as a [`SyntaxError`](crate::SyntaxError).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fix {
+    /// What the edit does, e.g. "replace the tab with a space".
+    pub message: String,
+    pub edit: TextEdit,
+}
+
+impl Fix {
+    pub fn new(message: impl Into<String>, edit: TextEdit) -> Self {
+        Fix { message: message.into(), edit }
+    }
+}
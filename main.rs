@@ -1,10 +1,21 @@
 use std::fs;
-use std::path::PathBuf;
+use std::num::NonZeroU32;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 use clap::{Args, Parser, Subcommand};
-use mical_cli_config::JsonView;
-use mical_cli_syntax::ast::{AstNode as _, SourceFile};
+use mical_cli_config::{
+    Config, DuplicatePolicy, EnvOverrides, Error as ConfigError, EvalOptions, JsonView, Pattern,
+    Value, Visitor,
+};
+use mical_cli_edit::{Document, Occurrences, RenameError, RenameScope};
+use mical_cli_lint::{Naming, QuoteStyle, Severity};
+use mical_cli_parser::ParseOptions;
+use mical_cli_syntax::{
+    Limits, SyntaxError, TextRange,
+    ast::{AstNode as _, SourceFile},
+};
 
 #[derive(Parser)]
 #[command(name = "mical", version, about = "Mical configuration language tool")]
@@ -18,6 +29,36 @@ enum Command {
     /// Evaluate a .mical file and output the result
     Eval(EvalArgs),
 
+    /// Compare the evaluated entries of two .mical files
+    Diff(DiffArgs),
+
+    /// Print the key hierarchy of a .mical file
+    Tree(TreeArgs),
+
+    /// Set the value of a key in place, adding the key if it is absent
+    Set(SetArgs),
+
+    /// Remove a key in place
+    Unset(UnsetArgs),
+
+    /// Rename a key in place
+    RenameKey(RenameKeyArgs),
+
+    /// Group entries into prefix blocks or flatten prefix blocks, in place
+    Restructure(RestructureArgs),
+
+    /// Sort entries by key within each prefix block, in place
+    Sort(SortArgs),
+
+    /// Check a .mical file for likely mistakes and style issues
+    Lint(LintArgs),
+
+    /// Apply the suggested fixes for syntax and evaluation errors, in place
+    Fix(FixArgs),
+
+    /// Run the language server over stdin and stdout
+    Lsp(LspArgs),
+
     /// (internal) Debug utilities — not for end users
     #[command(hide = true)]
     Dev(DevArgs),
@@ -36,8 +77,67 @@ struct EvalArgs {
     #[arg(short = 'f', long = "format", default_value = "json")]
     format: OutputFormat,
 
+    /// Override keys from environment variables named `<PREFIX>__<SEGMENT>__...`
+    #[arg(long, value_name = "PREFIX")]
+    env_prefix: Option<String>,
+
+    /// Report entries whose value came from the environment (on stderr)
+    #[arg(long, requires = "env_prefix")]
+    show_origin: bool,
+
+    /// Key segment separator used by `--match`
+    #[arg(long, default_value = ".")]
+    separator: String,
+
+    /// How to treat duplicated keys ("keep-all", "first-wins", "last-wins" or "error"), unless
+    /// the file declares it with a `#duplicates` directive
+    #[arg(long, default_value = "keep-all")]
+    duplicates: DuplicatePolicy,
+
+    /// Cap the size, nesting, entry count, key length and integer length of the file, for files
+    /// from untrusted sources
+    #[arg(long)]
+    untrusted: bool,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+
     #[command(flatten)]
     query: QueryArgs,
+
+    #[command(flatten)]
+    get: GetArgs,
+}
+
+#[derive(Args)]
+struct SyntaxArgs {
+    /// Read a `#` after a value and a space as the start of a comment
+    #[arg(long)]
+    inline_comments: bool,
+
+    /// Accept tabs in indentation, each moving to the next multiple of WIDTH columns
+    #[arg(long, value_name = "WIDTH")]
+    tab_width: Option<NonZeroU32>,
+
+    /// Continue a line string that ends with `\` on the next line
+    #[arg(long)]
+    line_continuation: bool,
+}
+
+impl SyntaxArgs {
+    /// The options for a trusted file, with the default limits.
+    fn options(&self) -> ParseOptions {
+        self.parse_options(Limits::default())
+    }
+
+    fn parse_options(&self, limits: Limits) -> ParseOptions {
+        ParseOptions {
+            limits,
+            inline_comments: self.inline_comments,
+            tab_width: self.tab_width,
+            line_continuation: self.line_continuation,
+        }
+    }
 }
 
 #[derive(Args)]
@@ -50,6 +150,50 @@ struct QueryArgs {
     /// Return all entries whose key starts with the given prefix
     #[arg(long)]
     prefix: Option<String>,
+
+    /// Return all entries whose key matches a glob pattern (`*`, `**`, `?`, `{a,b}`)
+    #[arg(long = "match", value_name = "PATTERN")]
+    pattern: Option<String>,
+}
+
+#[derive(Args)]
+struct GetArgs {
+    /// Print values without JSON quoting, one per line
+    #[arg(long, requires = "get", conflicts_with_all = ["prefix", "pattern"])]
+    raw: bool,
+
+    #[command(flatten)]
+    select: SelectArgs,
+
+    /// Value to use when the key is absent, typed like a value in a .mical file
+    #[arg(long, requires = "get", conflicts_with_all = ["prefix", "pattern"], value_name = "VALUE")]
+    default: Option<String>,
+
+    /// Fail when the key is absent, suggesting similar keys
+    #[arg(long, requires = "get", conflicts_with_all = ["default", "prefix", "pattern"])]
+    require: bool,
+
+    /// Stop reading the file at the first entry with the key, which saves time on large files.
+    /// Diagnostics after that entry are not reported, and a `#duplicates` directive after it is
+    /// not honored
+    #[arg(long, requires = "first", conflicts_with_all = ["env_prefix", "require"])]
+    stop_early: bool,
+}
+
+#[derive(Args)]
+#[group(multiple = false)]
+struct SelectArgs {
+    /// Select only the first value of a duplicated key
+    #[arg(long, requires = "get", conflicts_with_all = ["prefix", "pattern"])]
+    first: bool,
+
+    /// Select only the last value of a duplicated key
+    #[arg(long, requires = "get", conflicts_with_all = ["prefix", "pattern"])]
+    last: bool,
+
+    /// Select every value, as an array even if there is only one
+    #[arg(long, requires = "get", conflicts_with_all = ["prefix", "pattern"])]
+    all: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -75,6 +219,256 @@ impl std::fmt::Display for OutputFormat {
     }
 }
 
+#[derive(Args)]
+struct DiffArgs {
+    /// The old .mical file
+    old: PathBuf,
+
+    /// The new .mical file
+    new: PathBuf,
+
+    /// Output format ("human" or "json")
+    #[arg(short = 'f', long = "format", default_value = "human")]
+    format: DiffFormat,
+
+    /// Exit with 1 if there are differences and 0 otherwise
+    #[arg(long)]
+    exit_code: bool,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+}
+
+#[derive(Clone, Debug)]
+enum DiffFormat {
+    Human,
+    Json,
+}
+
+impl std::str::FromStr for DiffFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "human" => Ok(DiffFormat::Human),
+            "json" => Ok(DiffFormat::Json),
+            _ => Err(format!("unsupported format: '{s}' (supported: human, json)")),
+        }
+    }
+}
+
+impl std::fmt::Display for DiffFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffFormat::Human => f.write_str("human"),
+            DiffFormat::Json => f.write_str("json"),
+        }
+    }
+}
+
+#[derive(Args)]
+struct TreeArgs {
+    /// Path to the .mical file
+    file: PathBuf,
+
+    /// Only print the keys below this path
+    path: Option<String>,
+
+    /// Key segment separator
+    #[arg(long, default_value = ".")]
+    separator: String,
+
+    /// Print at most this many levels, summarizing deeper ones
+    #[arg(long)]
+    depth: Option<usize>,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+}
+
+#[derive(Args)]
+struct SetArgs {
+    /// Path to the .mical file to edit
+    file: PathBuf,
+
+    /// Full key to set
+    key: String,
+
+    /// New value, typed like a value in a .mical file
+    value: String,
+
+    /// Always write the value as a string
+    #[arg(long)]
+    string: bool,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+#[derive(Args)]
+struct UnsetArgs {
+    /// Path to the .mical file to edit
+    file: PathBuf,
+
+    /// Full key to remove
+    key: String,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+#[derive(Args)]
+struct RenameKeyArgs {
+    /// Path to the .mical file to edit
+    file: PathBuf,
+
+    /// Full key to rename
+    from: String,
+
+    /// New full key
+    to: String,
+
+    /// Rename every key starting with FROM, such as `server.http.`, keeping the rest of the key
+    #[arg(long, conflicts_with = "first")]
+    prefix: bool,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+#[derive(Args)]
+struct EditArgs {
+    #[command(flatten)]
+    occurrences: OccurrencesArgs,
+
+    /// Print the changes as a unified diff instead of writing the file
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+}
+
+#[derive(Args)]
+#[group(multiple = false)]
+struct OccurrencesArgs {
+    /// Edit every entry of a duplicated key
+    #[arg(long)]
+    all: bool,
+
+    /// Edit only the first entry of a duplicated key
+    #[arg(long)]
+    first: bool,
+}
+
+#[derive(Args)]
+struct RestructureArgs {
+    /// Path to the .mical file to edit
+    file: PathBuf,
+
+    #[command(flatten)]
+    mode: RestructureMode,
+
+    /// Key segment separator used by `--group`
+    #[arg(long, default_value = ".")]
+    separator: String,
+
+    /// Print the changes as a unified diff instead of writing the file
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct RestructureMode {
+    /// Rewrite runs of entries sharing a key segment into prefix blocks
+    #[arg(long)]
+    group: bool,
+
+    /// Expand every prefix block into fully-qualified entries
+    #[arg(long)]
+    flatten: bool,
+}
+
+#[derive(Args)]
+struct SortArgs {
+    /// Path to the .mical file to sort
+    file: PathBuf,
+
+    /// Do not write the file; exit with 1 if it is not sorted
+    #[arg(long, conflicts_with = "dry_run")]
+    check: bool,
+
+    /// Print the changes as a unified diff instead of writing the file
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+}
+
+#[derive(Args)]
+struct LintArgs {
+    /// Path to the .mical file to check
+    file: PathBuf,
+
+    /// Apply the safe fixes in place, then report what is left
+    #[arg(long)]
+    fix: bool,
+
+    /// Report a rule as an error
+    #[arg(long, value_name = "RULE")]
+    deny: Vec<String>,
+
+    /// Report a rule as a warning
+    #[arg(long, value_name = "RULE")]
+    warn: Vec<String>,
+
+    /// Turn a rule off
+    #[arg(long, value_name = "RULE")]
+    allow: Vec<String>,
+
+    /// Do not report duplicates of keys matching a glob pattern
+    #[arg(long, value_name = "PATTERN")]
+    allow_duplicate: Vec<String>,
+
+    /// Naming convention for key segments ("kebab-case", "snake_case" or "camelCase")
+    #[arg(long)]
+    naming: Option<Naming>,
+
+    /// Quote character for quoted keys and strings ("consistent", "double" or "single")
+    #[arg(long, default_value = "consistent")]
+    quote_style: QuoteStyle,
+
+    /// Key segment separator
+    #[arg(long, default_value = ".")]
+    separator: String,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+}
+
+#[derive(Args)]
+struct FixArgs {
+    /// Path to the .mical file to fix
+    file: PathBuf,
+
+    /// Print the changes as a unified diff instead of writing the file
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+}
+
+#[derive(Args)]
+struct LspArgs {
+    #[command(flatten)]
+    syntax: SyntaxArgs,
+}
+
 #[derive(Args)]
 struct DevArgs {
     /// Path to the .mical file
@@ -91,12 +485,25 @@ struct DevArgs {
     /// Print the AST (abstract syntax tree)
     #[arg(long)]
     ast: bool,
+
+    #[command(flatten)]
+    syntax: SyntaxArgs,
 }
 
 fn main() -> ExitCode {
     let cli = Cli::parse();
     match cli.command {
         Command::Eval(args) => cmd_eval(args),
+        Command::Diff(args) => cmd_diff(args),
+        Command::Tree(args) => cmd_tree(args),
+        Command::Set(args) => cmd_set(args),
+        Command::Unset(args) => cmd_unset(args),
+        Command::RenameKey(args) => cmd_rename_key(args),
+        Command::Restructure(args) => cmd_restructure(args),
+        Command::Sort(args) => cmd_sort(args),
+        Command::Lint(args) => cmd_lint(args),
+        Command::Fix(args) => cmd_fix(args),
+        Command::Lsp(args) => cmd_lsp(args),
         Command::Dev(args) => cmd_dev(args),
     }
 }
@@ -106,81 +513,711 @@ fn main() -> ExitCode {
 // ---------------------------------------------------------------------------
 
 fn cmd_eval(args: EvalArgs) -> ExitCode {
-    let source = match fs::read_to_string(&args.file) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("error: cannot read '{}': {e}", args.file.display());
-            return ExitCode::FAILURE;
-        }
+    let limits = if args.untrusted { Limits::untrusted() } else { Limits::default() };
+    let options =
+        EvalOptions { duplicates: args.duplicates, parse: args.syntax.parse_options(limits) };
+    let Some(Source { text: source, is_lossy, .. }) = read_source(&args.file, &limits) else {
+        return ExitCode::FAILURE;
     };
+    if let Some(key) = &args.query.get
+        && args.get.stop_early
+    {
+        let (value, has_errors) = find_first(&source, key, &options);
+        let has_errors = has_errors || is_lossy;
+        let values = match &value {
+            Some(value) => vec![value.as_value()],
+            None => args.get.default.iter().map(|default| Value::from_text(default)).collect(),
+        };
+        let output_str = format_values(&values, &args.get).expect("JSON serialization failed");
+        return write_output(args.output_path, &output_str, has_errors);
+    }
+    let (mut config, has_errors) = load_config(&source, &options);
+    let has_errors = has_errors || is_lossy;
 
-    let (green, syntax_errors) = mical_cli_parser::parse(mical_cli_lexer::tokenize(&source));
-    let syntax_node = mical_cli_syntax::SyntaxNode::new_root(green);
-    let source_file = match SourceFile::cast(syntax_node) {
-        Some(sf) => sf,
+    if let Some(prefix) = &args.env_prefix {
+        let vars = std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        });
+        config.apply_env_overrides(&EnvOverrides::new(prefix), vars);
+        if args.show_origin {
+            for (key, _, var) in config.env_overrides() {
+                eprintln!("note: '{key}' is set by environment variable '{var}'");
+            }
+        }
+    }
+
+    let output_str = match (&args.query.get, &args.query.prefix, &args.query.pattern) {
+        (Some(key), None, None) => {
+            let mut values: Vec<_> = config.query(key).collect();
+            if values.is_empty() {
+                if let Some(default) = &args.get.default {
+                    values.push(Value::from_text(default));
+                } else if args.get.require {
+                    eprintln!("error: key '{key}' not found");
+                    for similar in config.similar_keys(key, 3) {
+                        eprintln!("  did you mean '{similar}'?");
+                    }
+                    return ExitCode::FAILURE;
+                }
+            }
+            if args.get.select.first {
+                values.truncate(1);
+            } else if args.get.select.last {
+                values.drain(..values.len().saturating_sub(1));
+            }
+            format_values(&values, &args.get)
+        }
+        (None, Some(prefix), None) => {
+            let values = config.query_prefix(prefix);
+            serde_json::to_string_pretty(&JsonView(&values)).map(|s| s + "\n")
+        }
+        (None, None, Some(pattern)) => {
+            let pattern = match Pattern::with_separator(pattern, &args.separator) {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    eprintln!("error: invalid pattern '{pattern}': {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let values = config.query_pattern(&pattern);
+            serde_json::to_string_pretty(&JsonView(&values)).map(|s| s + "\n")
+        }
+        (None, None, None) => serde_json::to_string_pretty(&JsonView(&config)).map(|s| s + "\n"),
+        _ => unreachable!("clap ensures mutual exclusivity"),
+    }
+    .expect("JSON serialization failed");
+
+    write_output(args.output_path, &output_str, has_errors)
+}
+
+/// Prints the values selected by `--get`.
+fn format_values(values: &[Value<'_>], args: &GetArgs) -> serde_json::Result<String> {
+    if args.raw {
+        Ok(values.iter().map(|v| format!("{}\n", raw_value(v))).collect())
+    } else if args.select.all {
+        serde_json::to_string_pretty(&JsonView(values)).map(|s| s + "\n")
+    } else {
+        match values.len() {
+            0 => serde_json::to_string_pretty(&serde_json::Value::Null),
+            1 => serde_json::to_string_pretty(&JsonView(&values[0])),
+            _ => serde_json::to_string_pretty(&JsonView(values)),
+        }
+        .map(|s| s + "\n")
+    }
+}
+
+fn write_output(output_path: Option<PathBuf>, output_str: &str, has_errors: bool) -> ExitCode {
+    match output_path {
+        Some(path) => {
+            if let Err(e) = fs::write(&path, output_str) {
+                eprintln!("error: cannot write to '{}': {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
         None => {
-            eprintln!("error: failed to parse source file");
-            return ExitCode::FAILURE;
+            print!("{output_str}");
+        }
+    }
+
+    if has_errors {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Finds the value `--get <key> --first` selects, reading `source` only up to the entry that has
+/// it: the first entry with the key, or the last one once a `last-wins` policy is in effect.
+/// Returns the value and whether any diagnostic was reported.
+fn find_first(source: &str, key: &str, options: &EvalOptions) -> (Option<OwnedValue>, bool) {
+    struct FindFirst<'k> {
+        key: &'k str,
+        policy: DuplicatePolicy,
+        value: Option<OwnedValue>,
+        has_errors: bool,
+    }
+
+    impl Visitor for FindFirst<'_> {
+        fn entry(&mut self, key: &str, value: Value<'_>, _range: TextRange) -> ControlFlow<()> {
+            if key != self.key {
+                return ControlFlow::Continue(());
+            }
+            self.value = Some(OwnedValue::new(value));
+            match self.policy {
+                DuplicatePolicy::LastWins => ControlFlow::Continue(()),
+                _ => ControlFlow::Break(()),
+            }
+        }
+
+        fn duplicates(&mut self, policy: DuplicatePolicy) {
+            self.policy = policy;
+        }
+
+        fn syntax_error(&mut self, error: SyntaxError) -> ControlFlow<()> {
+            eprintln!("syntax error: {error}");
+            self.has_errors = true;
+            ControlFlow::Continue(())
+        }
+
+        fn error(&mut self, error: ConfigError) -> ControlFlow<()> {
+            eprintln!("config error: {error}");
+            self.has_errors = true;
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut find = FindFirst { key, policy: options.duplicates, value: None, has_errors: false };
+    mical_cli_config::visit_with_options(source, &options.parse, &mut find);
+    (find.value, find.has_errors)
+}
+
+/// A [`Value`] that outlives the evaluation it came from.
+enum OwnedValue {
+    Bool(bool),
+    Integer(String),
+    String(String),
+}
+
+impl OwnedValue {
+    fn new(value: Value<'_>) -> Self {
+        match value {
+            Value::Bool(b) => OwnedValue::Bool(b),
+            Value::Integer(s) => OwnedValue::Integer(s.to_owned()),
+            Value::String(s) => OwnedValue::String(s.to_owned()),
+        }
+    }
+
+    fn as_value(&self) -> Value<'_> {
+        match self {
+            OwnedValue::Bool(b) => Value::Bool(*b),
+            OwnedValue::Integer(s) => Value::Integer(s),
+            OwnedValue::String(s) => Value::String(s),
+        }
+    }
+}
+
+/// Renders a value the way shell scripts want it: strings unquoted, integers in decimal.
+fn raw_value(value: &Value<'_>) -> String {
+    match value {
+        Value::String(s) => s.to_string(),
+        _ => serde_json::to_string(&JsonView(value)).expect("JSON serialization failed"),
+    }
+}
+
+/// The decoded contents of a file.
+struct Source {
+    text: String,
+    /// Whether the file starts with a byte order mark, which is left out of `text`.
+    has_bom: bool,
+    /// Whether the file has invalid UTF-8, read as U+FFFD in `text`.
+    is_lossy: bool,
+}
+
+/// Reads and decodes `path`, reporting on stderr when it cannot be read, when it is longer than
+/// `limits` allow, and where it is not valid UTF-8.
+fn read_source(path: &Path, limits: &Limits) -> Option<Source> {
+    // Checked before reading, so an oversized file is never loaded.
+    if let Ok(metadata) = fs::metadata(path)
+        && metadata.len() > limits.max_source_len as u64
+    {
+        let (len, max) = (metadata.len(), limits.max_source_len);
+        eprintln!("error: '{}' is {len} bytes long, over the limit of {max}", path.display());
+        return None;
+    }
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("error: cannot read '{}': {e}", path.display());
+            return None;
         }
     };
+    let decoded = mical_cli_lexer::decode(&bytes);
+    for err in &decoded.errors {
+        let (line, col) = mical_cli_lint::line_col(&decoded.text, err.range().start());
+        eprintln!("{}:{line}:{col}: error: {}", path.display(), err.message());
+    }
+    Some(Source {
+        text: decoded.text.into_owned(),
+        has_bom: decoded.has_bom,
+        is_lossy: !decoded.errors.is_empty(),
+    })
+}
+
+/// Reads `path` to edit it, refusing files that are not valid UTF-8, whose invalid bytes would be
+/// lost when writing them back.
+fn read_editable(path: &Path) -> Option<Source> {
+    let source = read_source(path, &Limits::default())?;
+    if source.is_lossy {
+        eprintln!("error: refusing to edit '{}' because it is not valid UTF-8", path.display());
+        return None;
+    }
+    Some(source)
+}
 
+/// Evaluates `source`, reporting every diagnostic on stderr.
+///
+/// Returns the config and whether any diagnostic was reported.
+fn load_config<'s>(source: &'s str, options: &EvalOptions) -> (Config<'s>, bool) {
+    let (config, syntax_errors, config_errors) = Config::parse(source, options);
     for err in &syntax_errors {
         eprintln!("syntax error: {err}");
     }
-
-    let (config, config_errors) = mical_cli_config::Config::from_source_file(source_file);
     for err in &config_errors {
         eprintln!("config error: {err}");
     }
 
-    let output_str = match (&args.query.get, &args.query.prefix) {
-        (Some(key), None) => {
-            let values: Vec<_> = config.query(key).collect();
-            match values.len() {
-                0 => serde_json::to_string_pretty(&serde_json::Value::Null),
-                1 => serde_json::to_string_pretty(&JsonView(&values[0])),
-                _ => serde_json::to_string_pretty(&JsonView(values.as_slice())),
-            }
+    (config, !syntax_errors.is_empty() || !config_errors.is_empty())
+}
+
+// ---------------------------------------------------------------------------
+// diff
+// ---------------------------------------------------------------------------
+
+fn cmd_diff(args: DiffArgs) -> ExitCode {
+    // Like `diff(1)`: 1 means "differences found" with `--exit-code`, so errors use 2.
+    let error = ExitCode::from(2);
+    let Some(old_source) = read_source(&args.old, &Limits::default()) else {
+        return error;
+    };
+    let Some(new_source) = read_source(&args.new, &Limits::default()) else {
+        return error;
+    };
+    let options = EvalOptions { parse: args.syntax.options(), ..Default::default() };
+    let (old, old_has_errors) = load_config(&old_source.text, &options);
+    let (new, new_has_errors) = load_config(&new_source.text, &options);
+    if old_has_errors || new_has_errors || old_source.is_lossy || new_source.is_lossy {
+        return error;
+    }
+
+    let diff = old.diff(&new);
+    match args.format {
+        DiffFormat::Human => print!("{diff}"),
+        DiffFormat::Json => {
+            let json =
+                serde_json::to_string_pretty(&JsonView(&diff)).expect("JSON serialization failed");
+            println!("{json}");
         }
-        (None, Some(prefix)) => {
-            let values = config.query_prefix(prefix);
-            serde_json::to_string_pretty(&JsonView(&values))
+    }
+
+    if args.exit_code && !diff.is_empty() {
+        return ExitCode::from(1);
+    }
+    ExitCode::SUCCESS
+}
+
+// ---------------------------------------------------------------------------
+// tree
+// ---------------------------------------------------------------------------
+
+fn cmd_tree(args: TreeArgs) -> ExitCode {
+    let Some(Source { text: source, is_lossy, .. }) = read_source(&args.file, &Limits::default())
+    else {
+        return ExitCode::FAILURE;
+    };
+    let options = EvalOptions { parse: args.syntax.options(), ..Default::default() };
+    let (config, has_errors) = load_config(&source, &options);
+    let has_errors = has_errors || is_lossy;
+    if args.separator.is_empty() {
+        eprintln!("error: the separator must not be empty");
+        return ExitCode::FAILURE;
+    }
+
+    let namespace = config.namespace(&args.separator);
+    let path = args.path.as_deref().unwrap_or("");
+    if !path.is_empty() && namespace.children(path).is_empty() && config.query(path).count() == 0 {
+        eprintln!("error: no keys below '{path}'");
+        return ExitCode::FAILURE;
+    }
+
+    let mut out = String::new();
+    out.push_str(if path.is_empty() { "." } else { path });
+    push_tree_values(&mut out, &config, path);
+    out.push('\n');
+    print_tree(&mut out, &config, &args, path, "", 1);
+    print!("{out}");
+
+    if has_errors {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_tree(
+    out: &mut String,
+    config: &Config,
+    args: &TreeArgs,
+    path: &str,
+    indent: &str,
+    depth: usize,
+) {
+    let namespace = config.namespace(&args.separator);
+    let children = namespace.children(path);
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let key = match path {
+            "" => child.segment.to_owned(),
+            _ => format!("{path}{}{}", args.separator, child.segment),
+        };
+        out.push_str(indent);
+        out.push_str(if last { "└── " } else { "├── " });
+        out.push_str(child.segment);
+        if child.values > 0 {
+            push_tree_values(out, config, &key);
         }
-        (None, None) => serde_json::to_string_pretty(&JsonView(&config)),
-        _ => unreachable!("clap ensures mutual exclusivity"),
+        if child.descendants > 0 && args.depth.is_some_and(|max| depth >= max) {
+            out.push_str(&format!(" ({} more)", child.descendants));
+        }
+        out.push('\n');
+        if child.descendants > 0 && args.depth.is_none_or(|max| depth < max) {
+            let indent = format!("{indent}{}", if last { "    " } else { "│   " });
+            print_tree(out, config, args, &key, &indent, depth + 1);
+        }
+    }
+}
+
+/// Appends ` = value` (or ` = [values...]` for a duplicated key) if `key` has values.
+fn push_tree_values(out: &mut String, config: &Config, key: &str) {
+    let values = config.query(key).collect::<Vec<_>>();
+    let json = match values.len() {
+        0 => return,
+        1 => serde_json::to_string(&JsonView(&values[0])),
+        _ => serde_json::to_string(&JsonView(values.as_slice())),
     }
     .expect("JSON serialization failed");
+    out.push_str(" = ");
+    out.push_str(&json);
+}
 
-    match args.output_path {
-        Some(path) => {
-            if let Err(e) = fs::write(&path, format!("{output_str}\n")) {
-                eprintln!("error: cannot write to '{}': {e}", path.display());
-                return ExitCode::FAILURE;
+// ---------------------------------------------------------------------------
+// set / unset / rename-key
+// ---------------------------------------------------------------------------
+
+fn cmd_set(args: SetArgs) -> ExitCode {
+    let value =
+        if args.string { Value::String(&args.value) } else { Value::from_text(&args.value) };
+    edit_file(&args.file, &args.edit, &args.key, true, |doc, occurrences| {
+        doc.set(&args.key, value, occurrences)
+            .map(drop)
+            .map_err(|e| format!("cannot set '{}': {e}", args.key))
+    })
+}
+
+fn cmd_unset(args: UnsetArgs) -> ExitCode {
+    edit_file(&args.file, &args.edit, &args.key, false, |doc, occurrences| {
+        doc.remove(&args.key, occurrences)
+            .map(drop)
+            .map_err(|e| format!("cannot remove '{}': {e}", args.key))
+    })
+}
+
+fn cmd_rename_key(args: RenameKeyArgs) -> ExitCode {
+    let options = args.edit.syntax.options();
+    let Some((source, mut doc)) = read_document(&args.file, &options) else {
+        return ExitCode::FAILURE;
+    };
+    let scope = if args.prefix { RenameScope::Prefix } else { RenameScope::Key };
+    let count = doc.count(&args.from);
+    let occurrences = match (args.edit.occurrences.all, args.edit.occurrences.first) {
+        (true, _) => Occurrences::All,
+        (_, true) => Occurrences::First,
+        _ if scope == RenameScope::Key && count > 1 => {
+            eprintln!("error: key '{}' has {count} entries, pass --all or --first", args.from);
+            return ExitCode::FAILURE;
+        }
+        _ => Occurrences::All,
+    };
+    match doc.rename_keys(&args.from, &args.to, scope, occurrences) {
+        Ok(_) => write_edited(&args.file, &source, doc.text(), &options, args.edit.dry_run),
+        Err(RenameError::NotFound) => {
+            match scope {
+                RenameScope::Key => eprintln!("error: key '{}' not found", args.from),
+                RenameScope::Prefix => eprintln!("error: no key starts with '{}'", args.from),
             }
+            ExitCode::FAILURE
         }
-        None => {
-            println!("{output_str}");
+        Err(e) => {
+            eprintln!("error: cannot rename '{}' to '{}': {e}", args.from, args.to);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Applies `edit` to the entries of `key` in `path`, then writes the file back or, with
+/// `--dry-run`, prints the change.
+///
+/// Fails if the file has syntax errors, if `key` is absent and `allow_absent` is not set, if
+/// `key` is duplicated and neither `--all` nor `--first` was given, or if `edit` fails.
+fn edit_file(
+    path: &Path,
+    args: &EditArgs,
+    key: &str,
+    allow_absent: bool,
+    edit: impl FnOnce(&mut Document, Occurrences) -> Result<(), String>,
+) -> ExitCode {
+    let options = args.syntax.options();
+    let Some((source, mut doc)) = read_document(path, &options) else {
+        return ExitCode::FAILURE;
+    };
+
+    let count = doc.count(key);
+    if count == 0 && !allow_absent {
+        eprintln!("error: key '{key}' not found");
+        return ExitCode::FAILURE;
+    }
+    let occurrences = match (args.occurrences.all, args.occurrences.first) {
+        (true, _) => Occurrences::All,
+        (_, true) => Occurrences::First,
+        _ if count > 1 => {
+            eprintln!("error: key '{key}' has {count} entries, pass --all or --first");
+            return ExitCode::FAILURE;
         }
+        _ => Occurrences::All,
+    };
+    if let Err(e) = edit(&mut doc, occurrences) {
+        eprintln!("error: {e}");
+        return ExitCode::FAILURE;
     }
+    write_edited(path, &source, doc.text(), &options, args.dry_run)
+}
 
-    if !syntax_errors.is_empty() || !config_errors.is_empty() {
+/// Writes the `edited` text back to `path`, with the byte order mark `source` had, or, with
+/// `dry_run`, prints a unified diff against the original `source`.
+///
+/// Refuses to write a text with more syntax errors than `source`, so that an edit never leaves a
+/// file the edit commands cannot read back.
+fn write_edited(
+    path: &Path,
+    source: &Source,
+    edited: &str,
+    options: &ParseOptions,
+    dry_run: bool,
+) -> ExitCode {
+    let syntax_errors = |text: &str| {
+        mical_cli_parser::parse_with_options(mical_cli_lexer::tokenize(text), options).1
+    };
+    let errors = syntax_errors(edited);
+    if errors.len() > syntax_errors(&source.text).len() {
+        for err in &errors {
+            eprintln!("syntax error: {err}");
+        }
+        eprintln!("error: refusing to write '{}' because the edit broke it", path.display());
+        return ExitCode::FAILURE;
+    }
+    if dry_run {
+        let name = path.display().to_string();
+        let diff = similar::TextDiff::from_lines(source.text.as_str(), edited);
+        print!("{}", diff.unified_diff().header(&name, &name));
+        return ExitCode::SUCCESS;
+    }
+    let bom = if source.has_bom { "\u{feff}" } else { "" };
+    if edited != source.text
+        && let Err(e) = fs::write(path, format!("{bom}{edited}"))
+    {
+        eprintln!("error: cannot write to '{}': {e}", path.display());
         return ExitCode::FAILURE;
     }
     ExitCode::SUCCESS
 }
 
+/// Reads `path` as a [`Document`] with `options`, refusing files with syntax errors.
+fn read_document(path: &Path, options: &ParseOptions) -> Option<(Source, Document)> {
+    let source = read_editable(path)?;
+    let doc = Document::parse_with_options(source.text.as_str(), options);
+    if !doc.syntax_errors().is_empty() {
+        for err in doc.syntax_errors() {
+            eprintln!("syntax error: {err}");
+        }
+        eprintln!("error: refusing to edit '{}' because it has syntax errors", path.display());
+        return None;
+    }
+    Some((source, doc))
+}
+
 // ---------------------------------------------------------------------------
-// dev
+// restructure
 // ---------------------------------------------------------------------------
 
-fn cmd_dev(args: DevArgs) -> ExitCode {
-    let source = match fs::read_to_string(&args.file) {
-        Ok(s) => s,
+fn cmd_restructure(args: RestructureArgs) -> ExitCode {
+    let options = args.syntax.options();
+    let Some((source, mut doc)) = read_document(&args.file, &options) else {
+        return ExitCode::FAILURE;
+    };
+    let result = if args.mode.group { doc.group(&args.separator) } else { doc.flatten() };
+    if let Err(e) = result {
+        eprintln!("error: cannot restructure '{}': {e}", args.file.display());
+        return ExitCode::FAILURE;
+    }
+    write_edited(&args.file, &source, doc.text(), &options, args.dry_run)
+}
+
+// ---------------------------------------------------------------------------
+// sort
+// ---------------------------------------------------------------------------
+
+fn cmd_sort(args: SortArgs) -> ExitCode {
+    let parse = args.syntax.options();
+    let Some((source, _)) = read_document(&args.file, &parse) else {
+        return ExitCode::FAILURE;
+    };
+    let options = mical_cli_formatter::Options { sort_entries: true, parse };
+    let sorted = match mical_cli_formatter::format(&source.text, &options) {
+        Ok(sorted) => sorted,
         Err(e) => {
-            eprintln!("error: cannot read '{}': {e}", args.file.display());
+            eprintln!("error: cannot sort '{}': {e}", args.file.display());
             return ExitCode::FAILURE;
         }
     };
+    if args.check {
+        if sorted != source.text {
+            eprintln!("'{}' is not sorted", args.file.display());
+            return ExitCode::from(1);
+        }
+        return ExitCode::SUCCESS;
+    }
+    write_edited(&args.file, &source, &sorted, &parse, args.dry_run)
+}
+
+// ---------------------------------------------------------------------------
+// lint
+// ---------------------------------------------------------------------------
+
+fn cmd_lint(args: LintArgs) -> ExitCode {
+    let mut options = mical_cli_lint::Options::default();
+    options.separator = args.separator;
+    options.naming = args.naming;
+    options.quote_style = args.quote_style;
+    options.parse = args.syntax.options();
+    for pattern in &args.allow_duplicate {
+        match Pattern::with_separator(pattern, &options.separator) {
+            Ok(pattern) => options.allowed_duplicates.push(pattern),
+            Err(e) => {
+                eprintln!("error: invalid pattern '{pattern}': {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let levels = [
+        (&args.allow, Severity::Off),
+        (&args.warn, Severity::Warning),
+        (&args.deny, Severity::Error),
+    ];
+    for (rules, severity) in levels {
+        for rule in rules {
+            if !options.set_severity(rule, severity) {
+                eprintln!("error: unknown lint rule '{rule}'");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let read = if args.fix {
+        read_editable(&args.file)
+    } else {
+        read_source(&args.file, &Limits::default())
+    };
+    let Some(Source { text: mut source, has_bom, is_lossy }) = read else {
+        return ExitCode::FAILURE;
+    };
+    if args.fix {
+        let (fixed, count) = mical_cli_lint::fix(&source, &options);
+        if count > 0 {
+            let bom = if has_bom { "\u{feff}" } else { "" };
+            if let Err(e) = fs::write(&args.file, format!("{bom}{fixed}")) {
+                eprintln!("error: cannot write to '{}': {e}", args.file.display());
+                return ExitCode::FAILURE;
+            }
+            eprintln!("fixed {count} problem(s) in '{}'", args.file.display());
+        }
+        source = fixed;
+    }
+
+    let report = mical_cli_lint::lint(&source, &options);
+    let name = args.file.display();
+    for err in &report.syntax_errors {
+        let (line, col) = mical_cli_lint::line_col(&source, err.range().start());
+        eprintln!("{name}:{line}:{col}: syntax error: {}", err.message());
+    }
+    let mut fixable = 0;
+    for diagnostic in &report.diagnostics {
+        let (line, col) = mical_cli_lint::line_col(&source, diagnostic.range.start());
+        let note = match &diagnostic.fix {
+            Some(fix) => {
+                fixable += 1;
+                format!(" (fix: {})", fix.message)
+            }
+            None => String::new(),
+        };
+        eprintln!(
+            "{name}:{line}:{col}: {}[{}]: {}{note}",
+            diagnostic.severity, diagnostic.rule, diagnostic.message
+        );
+    }
+    if fixable > 0 && !args.fix {
+        eprintln!("{fixable} problem(s) can be fixed with `mical lint --fix`");
+    }
+
+    let has_errors = is_lossy
+        || !report.syntax_errors.is_empty()
+        || report.diagnostics.iter().any(|d| d.severity == Severity::Error);
+    if has_errors {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+// ---------------------------------------------------------------------------
+// fix
+// ---------------------------------------------------------------------------
+
+fn cmd_fix(args: FixArgs) -> ExitCode {
+    let Some(source) = read_editable(&args.file) else {
+        return ExitCode::FAILURE;
+    };
+    let options = args.syntax.options();
+    let mut doc = Document::parse_with_options(source.text.as_str(), &options);
+    let count = doc.fix_errors();
+    if count > 0 && !args.dry_run {
+        eprintln!("fixed {count} error(s) in '{}'", args.file.display());
+    }
+
+    // What is left needs a human.
+    let name = args.file.display();
+    let (_, config_errors) = Config::from_source_file(doc.source_file().clone());
+    for err in doc.syntax_errors() {
+        let (line, col) = mical_cli_lint::line_col(doc.text(), err.range().start());
+        eprintln!("{name}:{line}:{col}: syntax error: {}", err.message());
+    }
+    for err in &config_errors {
+        let (line, col) = mical_cli_lint::line_col(doc.text(), err.range().start());
+        eprintln!("{name}:{line}:{col}: config error: {err}");
+    }
+    let remaining = !doc.syntax_errors().is_empty() || !config_errors.is_empty();
+
+    let code = write_edited(&args.file, &source, doc.text(), &options, args.dry_run);
+    if remaining { ExitCode::FAILURE } else { code }
+}
+
+// ---------------------------------------------------------------------------
+// lsp
+// ---------------------------------------------------------------------------
+
+fn cmd_lsp(args: LspArgs) -> ExitCode {
+    match mical_cli_lsp::run_stdio(&args.syntax.options()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: language server failed: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// dev
+// ---------------------------------------------------------------------------
+
+fn cmd_dev(args: DevArgs) -> ExitCode {
+    let Some(Source { text: source, .. }) = read_source(&args.file, &Limits::default()) else {
+        return ExitCode::FAILURE;
+    };
 
     // Default: print CST + AST if no flag is given
     let print_default = !args.token && !args.cst && !args.ast;
@@ -195,7 +1232,10 @@ fn cmd_dev(args: DevArgs) -> ExitCode {
         }
     }
 
-    let (green, syntax_errors) = mical_cli_parser::parse(mical_cli_lexer::tokenize(&source));
+    let (green, syntax_errors) = mical_cli_parser::parse_with_options(
+        mical_cli_lexer::tokenize(&source),
+        &args.syntax.options(),
+    );
     let syntax_node = mical_cli_syntax::SyntaxNode::new_root(green);
 
     if args.cst || print_default {
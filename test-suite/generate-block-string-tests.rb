@@ -8,6 +8,7 @@
 #   ruby test-suite/generate-block-string-tests.rb 1     # phase 1 only (0-line)
 #   ruby test-suite/generate-block-string-tests.rb 2     # phase 2 only (1-line)
 #   ruby test-suite/generate-block-string-tests.rb 3     # phase 3 only (multi-line)
+#   ruby test-suite/generate-block-string-tests.rb 4     # phase 4 only (explicit indent)
 #
 # Requires: `cargo build` first (uses target/debug/mical to generate output.json)
 
@@ -317,6 +318,31 @@ def run_phase3
   run_cases(phase3_cases)
 end
 
+# --- Phase 4: explicit indentation indicator (12 cases, exhaustive) ---
+# Headers put the indicator after the chomp indicator (`|-2`); the other order is covered by
+# the manual block-string-explicit-indent-before-chomp test.
+
+FIRSTS = [['exact', '  '], ['extra', '    ']]
+
+def run_phase4
+  phase4_cases = []
+
+  STYLES.each_index do |si|
+    CHOMPS.each_index do |ci|
+      FIRSTS.each do |(fname, findent)|
+        lines = ["key #{indicator(si, ci)}2", "#{findent}hello", '  world', '', 'other val']
+        phase4_cases << {
+          name: "block-string-explicit-indent-#{style_name(si)}-#{chomp_name(ci)}-#{fname}",
+          input: lines.join("\n") + "\n"
+        }
+      end
+    end
+  end
+
+  puts "=== Phase 4: explicit indentation indicator (#{phase4_cases.size} cases) ==="
+  run_cases(phase4_cases)
+end
+
 # --- Runner ---
 
 $has_failures = false
@@ -348,10 +374,12 @@ case phase
 when 1 then run_phase1
 when 2 then run_phase2
 when 3 then run_phase3
+when 4 then run_phase4
 else
   run_phase1
   run_phase2
   run_phase3
+  run_phase4
 end
 
 exit 1 if $has_failures
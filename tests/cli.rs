@@ -0,0 +1,214 @@
+use pretty_assertions::assert_eq;
+use std::{fs, path::PathBuf, process::Command};
+
+/// A directory of its own under the system temporary directory, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("mical-cli-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    fn file(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs `mical` with `args`, returning whether it succeeded, its stdout and its stderr.
+fn mical(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_mical")).args(args).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    (output.status.success(), stdout, stderr)
+}
+
+const CONFIG: &str = "host localhost\nport 8080\ntag a\ntag \"b c\"\n";
+
+/// Runs `mical eval` on [`CONFIG`] with `args` before the file, expecting it to succeed.
+fn eval(name: &str, args: &[&str]) -> String {
+    let dir = TempDir::new(name);
+    let path = dir.file("config.mical", CONFIG);
+    let args = [&["eval"], args, &[path.to_str().unwrap()]].concat();
+    let (success, stdout, stderr) = mical(&args);
+    assert!(success, "{stderr}");
+    assert_eq!(stderr, "");
+    stdout
+}
+
+#[test]
+fn eval_get() {
+    assert_eq!(eval("get", &["--get", "host"]), "\"localhost\"\n");
+    assert_eq!(eval("get-dup", &["--get", "tag"]), "[\n  \"a\",\n  \"b c\"\n]\n");
+    assert_eq!(eval("get-missing", &["--get", "user"]), "null\n");
+}
+
+#[test]
+fn eval_raw() {
+    assert_eq!(eval("raw", &["--get", "host", "--raw"]), "localhost\n");
+    assert_eq!(eval("raw-int", &["--get", "port", "--raw"]), "8080\n");
+    assert_eq!(eval("raw-dup", &["--get", "tag", "--raw"]), "a\nb c\n");
+}
+
+#[test]
+fn eval_first_last_all() {
+    assert_eq!(eval("first", &["--get", "tag", "--first"]), "\"a\"\n");
+    assert_eq!(eval("last", &["--get", "tag", "--last", "--raw"]), "b c\n");
+    assert_eq!(eval("all", &["--get", "port", "--all"]), "[\n  8080\n]\n");
+    assert_eq!(eval("all-raw", &["--get", "tag", "--all", "--raw"]), "a\nb c\n");
+    assert_eq!(eval("stop-early", &["--get", "tag", "--first", "--stop-early"]), "\"a\"\n");
+}
+
+#[test]
+fn eval_default() {
+    assert_eq!(eval("default", &["--get", "user", "--default", "root"]), "\"root\"\n");
+    assert_eq!(eval("default-typed", &["--get", "user", "--default", "0x10"]), "16\n");
+    assert_eq!(eval("default-trimmed", &["--get", "user", "--default", "true "]), "true\n");
+    assert_eq!(eval("default-unused", &["--get", "port", "--default", "80"]), "8080\n");
+}
+
+#[test]
+fn eval_require() {
+    assert_eq!(eval("require", &["--get", "host", "--require"]), "\"localhost\"\n");
+
+    let dir = TempDir::new("require-missing");
+    let path = dir.file("config.mical", CONFIG);
+    let (success, stdout, stderr) =
+        mical(&["eval", "--get", "hots", "--require", path.to_str().unwrap()]);
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "error: key 'hots' not found\n  did you mean 'host'?\n");
+}
+
+#[test]
+fn eval_rejects_conflicting_flags() {
+    let dir = TempDir::new("conflicts");
+    let path = dir.file("config.mical", CONFIG);
+    let path = path.to_str().unwrap();
+    for args in [
+        &["--get", "tag", "--first", "--last"][..],
+        &["--get", "tag", "--default", "x", "--require"],
+        &["--raw"],
+        &["--prefix", "t", "--raw"],
+        &["--prefix", "t", "--first"],
+        &["--prefix", "t", "--last"],
+        &["--prefix", "t", "--all"],
+        &["--prefix", "t", "--default", "x"],
+        &["--prefix", "t", "--require"],
+        &["--match", "t*", "--raw"],
+        &["--match", "t*", "--first"],
+        &["--match", "t*", "--default", "x"],
+    ] {
+        let (success, _, _) = mical(&[&["eval"], args, &[path]].concat());
+        assert!(!success, "{args:?}");
+    }
+}
+
+/// Runs `mical` with `args` and then the path of a file holding `source`, returning whether it
+/// succeeded, its stderr and the file afterwards.
+fn edit(name: &str, source: &str, args: &[&str]) -> (bool, String, String) {
+    let dir = TempDir::new(name);
+    let path = dir.file("config.mical", source);
+    let path = path.to_str().unwrap();
+    let (success, _, stderr) = mical(&[&args[..1], &[path], &args[1..]].concat());
+    (success, stderr, fs::read_to_string(path).unwrap())
+}
+
+#[test]
+fn set_writes_the_file() {
+    let (success, stderr, text) = edit("set", CONFIG, &["set", "port", "9090"]);
+    assert!(success, "{stderr}");
+    assert_eq!(text, CONFIG.replace("8080", "9090"));
+
+    let (success, _, text) = edit("set-new", CONFIG, &["set", "user", "root"]);
+    assert!(success);
+    assert_eq!(text, format!("{CONFIG}user root\n"));
+
+    let (success, stderr, text) = edit("set-dup", CONFIG, &["set", "tag", "x"]);
+    assert!(!success);
+    assert_eq!(stderr, "error: key 'tag' has 2 entries, pass --all or --first\n");
+    assert_eq!(text, CONFIG);
+}
+
+#[test]
+fn set_dry_run_leaves_the_file() {
+    let dir = TempDir::new("set-dry-run");
+    let path = dir.file("config.mical", CONFIG);
+    let path = path.to_str().unwrap();
+    let (success, stdout, _) = mical(&["set", path, "port", "9090", "--dry-run"]);
+    assert!(success);
+    assert!(stdout.contains("-port 8080\n+port 9090\n"), "{stdout}");
+    assert_eq!(fs::read_to_string(path).unwrap(), CONFIG);
+}
+
+#[test]
+fn unset_writes_the_file() {
+    let (success, stderr, text) = edit("unset", CONFIG, &["unset", "tag", "--all"]);
+    assert!(success, "{stderr}");
+    assert_eq!(text, "host localhost\nport 8080\n");
+
+    let (success, _, text) = edit("unset-first", CONFIG, &["unset", "tag", "--first"]);
+    assert!(success);
+    assert_eq!(text, CONFIG.replace("tag a\n", ""));
+
+    let (success, stderr, _) = edit("unset-missing", CONFIG, &["unset", "user"]);
+    assert!(!success);
+    assert_eq!(stderr, "error: key 'user' not found\n");
+}
+
+#[test]
+fn rename_key_writes_the_file() {
+    let (success, stderr, text) = edit("rename", CONFIG, &["rename-key", "host", "hostname"]);
+    assert!(success, "{stderr}");
+    assert_eq!(text, CONFIG.replace("host ", "hostname "));
+
+    let (success, _, text) = edit("rename-all", CONFIG, &["rename-key", "tag", "label", "--all"]);
+    assert!(success);
+    assert_eq!(text, CONFIG.replace("tag ", "label "));
+
+    let (success, _, text) = edit("rename-first", CONFIG, &["rename-key", "tag", "t", "--first"]);
+    assert!(success);
+    assert_eq!(text, CONFIG.replacen("tag ", "t ", 1));
+
+    let source = "a. {\n  b 1\n}\na.b 2\n";
+    let (success, stderr, text) =
+        edit("rename-first-block", source, &["rename-key", "a.b", "c", "--first"]);
+    assert!(success, "{stderr}");
+    assert_eq!(text, "c 1\na.b 2\n");
+}
+
+#[test]
+fn rename_key_refuses_a_taken_key() {
+    for (name, flag) in [("taken-all", "--all"), ("taken-first", "--first")] {
+        let (success, stderr, text) = edit(name, CONFIG, &["rename-key", "tag", "port", flag]);
+        assert!(!success, "{flag}");
+        assert_eq!(stderr, "error: cannot rename 'tag' to 'port': key 'port' already exists\n");
+        assert_eq!(text, CONFIG);
+    }
+}
+
+#[test]
+fn edits_keep_the_byte_order_mark() {
+    let source = format!("\u{feff}{CONFIG}");
+    let (success, stderr, text) = edit("bom-set", &source, &["set", "port", "9090"]);
+    assert!(success, "{stderr}");
+    assert_eq!(text, source.replace("8080", "9090"));
+
+    let (success, _, text) = edit("bom-unset", &source, &["unset", "host"]);
+    assert!(success);
+    assert_eq!(text, source.replace("host localhost\n", ""));
+
+    let (success, _, text) = edit("bom-rename", &source, &["rename-key", "port", "listen"]);
+    assert!(success);
+    assert_eq!(text, source.replace("port ", "listen "));
+}